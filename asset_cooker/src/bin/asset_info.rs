@@ -0,0 +1,151 @@
+#![deny(unsafe_code)]
+#![deny(unstable_features)]
+
+use asset_system::{
+    Asset, AssetError, AssetFile, AssetReader, AssetType, CompressionMode, MaterialAsset,
+    MeshAsset, SceneAsset, ShaderAsset, Texture, TextureAsset, ASSET_FILE_MAGIC,
+};
+use clap::Parser;
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Prints the header and metadata of an asset file")]
+struct Arguments {
+    asset_file: PathBuf,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write the decompressed blob to a file"
+    )]
+    extract_blob: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Convert a texture asset to a PNG file"
+    )]
+    png: Option<PathBuf>,
+    #[arg(long, default_value_t = 0, help = "Mip level converted to PNG")]
+    mip_level: u32,
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Array layer or depth slice converted to PNG"
+    )]
+    slice: u32,
+}
+
+fn main() -> ExitCode {
+    let arguments = Arguments::parse();
+
+    match inspect(&arguments) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn inspect(arguments: &Arguments) -> Result<(), AssetError> {
+    let path = &arguments.asset_file;
+    let file_size = std::fs::metadata(path)?.len();
+    let mut magic = [0; 4];
+    std::fs::File::open(path)?.read_exact(&mut magic)?;
+
+    // Only the header of asset files of the current format is read, so the header of a file with
+    // a corrupted blob can still be printed. Legacy ones are a single LZ4 frame.
+    let (format, asset_file, reader, stored_size, raw_size) = match &magic == ASSET_FILE_MAGIC {
+        true => {
+            let reader = AssetReader::open(path)?;
+            let stored_size = file_size.saturating_sub(reader.raw_data_offset());
+            let raw_size = reader.raw_data_size();
+            (
+                "RAST",
+                reader.header().clone(),
+                Some(reader),
+                stored_size,
+                raw_size,
+            )
+        }
+        false => {
+            let asset_file = AssetFile::load_asset_file(path)?;
+            let raw_size = asset_file.raw_data().len() as u64;
+            ("Legacy", asset_file, None, file_size, raw_size)
+        }
+    };
+
+    let codec = match asset_file.compression_mode() {
+        CompressionMode::None => "None".to_string(),
+        compression_mode => format!("LZ4 level {}", compression_mode as u8),
+    };
+    println!("File:          {} ({file_size} bytes)", path.display());
+    println!("Format:        {format}");
+    println!("Version:       {}", asset_file.version());
+    println!("GUID:          {}", asset_file.guid());
+    println!("Name:          {}", asset_file.name());
+    println!("Path:          {}", asset_file.path());
+    println!("Type:          {:?}", asset_file.asset_type());
+    println!(
+        "Codec:         {codec} ({:?})",
+        asset_file.compression_mode()
+    );
+    println!("Blob size:     {stored_size} bytes stored, {raw_size} bytes decompressed");
+    println!("Dependencies:  {}", asset_file.dependencies().len());
+    for dependency in asset_file.dependencies() {
+        println!("  {dependency}");
+    }
+    println!("Metadata:\n{}", typed_metadata(&asset_file)?);
+
+    if arguments.extract_blob.is_none() && arguments.png.is_none() {
+        return Ok(());
+    }
+    // The blob is verified against the checksum as it's read.
+    let asset_file = match reader {
+        Some(reader) => reader.into_asset_file()?,
+        None => asset_file,
+    };
+    if let Some(blob_path) = &arguments.extract_blob {
+        std::fs::write(blob_path, asset_file.raw_data())?;
+        println!("Wrote the blob to {}", blob_path.display());
+    }
+    if let Some(png_path) = &arguments.png {
+        write_png(asset_file, png_path, arguments.mip_level, arguments.slice)?;
+        println!("Wrote the texture to {}", png_path.display());
+    }
+
+    Ok(())
+}
+
+// Metadata parsed as the type of the asset, so missing fields show up with their defaults.
+fn typed_metadata(asset_file: &AssetFile) -> Result<String, AssetError> {
+    fn pretty<T: Serialize + for<'de> serde::Deserialize<'de>>(
+        metadata: &str,
+    ) -> Result<String, AssetError> {
+        let metadata: T = ron::from_str(metadata)?;
+
+        Ok(ron::ser::to_string_pretty(&metadata, Default::default())?)
+    }
+
+    match asset_file.asset_type() {
+        AssetType::Mesh => pretty::<MeshAsset>(asset_file.metadata()),
+        AssetType::Texture => pretty::<TextureAsset>(asset_file.metadata()),
+        AssetType::Material => pretty::<MaterialAsset>(asset_file.metadata()),
+        AssetType::Scene => pretty::<SceneAsset>(asset_file.metadata()),
+        AssetType::Shader => pretty::<ShaderAsset>(asset_file.metadata()),
+    }
+}
+
+fn write_png(
+    asset_file: AssetFile,
+    path: &Path,
+    mip_level: u32,
+    slice: u32,
+) -> Result<(), AssetError> {
+    let texture = Texture::from_asset_file(asset_file)?;
+    std::fs::write(path, texture.to_png(mip_level, slice)?)?;
+
+    Ok(())
+}
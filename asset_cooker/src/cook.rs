@@ -0,0 +1,573 @@
+use asset_system::{
+    AssetError, AssetFile, AssetGuid, BuildCache, BuildKey, CompressionMode, GltfImporter,
+    ImportSettings, ObjImporter, Shader, ShaderCompiler, TextureImporter, ASSET_FILE_EXTENSION,
+    GLSL_SOURCE_EXTENSIONS, GLTF_SOURCE_EXTENSIONS, OBJ_SOURCE_EXTENSIONS,
+    TEXTURE_SOURCE_EXTENSIONS,
+};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Texture,
+    Gltf,
+    Obj,
+    Shader,
+}
+
+impl SourceKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        let kinds = [
+            (SourceKind::Texture, TEXTURE_SOURCE_EXTENSIONS),
+            (SourceKind::Gltf, GLTF_SOURCE_EXTENSIONS),
+            (SourceKind::Obj, OBJ_SOURCE_EXTENSIONS),
+            (SourceKind::Shader, GLSL_SOURCE_EXTENSIONS),
+        ];
+
+        kinds
+            .into_iter()
+            .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+            .map(|(kind, _)| kind)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CookReport {
+    pub cooked: Vec<CookedSource>,
+    // Sources that were cooked before with the same content and settings.
+    pub skipped: Vec<PathBuf>,
+    // Sources without an importer.
+    pub unsupported: Vec<PathBuf>,
+    pub failed: Vec<FailedSource>,
+    // Asset files of previous cooks that aren't produced anymore.
+    pub deleted: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CookedSource {
+    pub source: PathBuf,
+    pub kind: SourceKind,
+    pub outputs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailedSource {
+    pub source: PathBuf,
+    pub error: String,
+}
+
+impl CookReport {
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        for cooked in &self.cooked {
+            summary += &format!(
+                "Cooked {} into {} asset file(s)\n",
+                cooked.source.display(),
+                cooked.outputs.len()
+            );
+        }
+        for source in &self.unsupported {
+            summary += &format!("Unsupported {}\n", source.display());
+        }
+        for failed in &self.failed {
+            summary += &format!("Failed {}: {}\n", failed.source.display(), failed.error);
+        }
+        for deleted in &self.deleted {
+            summary += &format!("Deleted {}\n", deleted.display());
+        }
+        summary += &format!(
+            "{} cooked, {} unchanged, {} unsupported, {} failed, {} deleted",
+            self.cooked.len(),
+            self.skipped.len(),
+            self.unsupported.len(),
+            self.failed.len(),
+            self.deleted.len()
+        );
+
+        summary
+    }
+}
+
+// Imports sources into asset files placed into `output` with the same relative paths. The
+// build cache is kept in `output`, sources are identified by their paths relative to inputs.
+#[derive(Clone, Debug)]
+pub struct Cooker {
+    pub output: PathBuf,
+    pub compression_mode: CompressionMode,
+    // Cooks sources even if they're up to date.
+    pub force: bool,
+    // Deletes asset files of sources that aren't among the inputs anymore.
+    pub prune: bool,
+    pub texture_importer: TextureImporter,
+    pub gltf_importer: GltfImporter,
+    pub obj_importer: ObjImporter,
+    pub shader_compiler: ShaderCompiler,
+}
+
+impl Cooker {
+    pub fn new<T: AsRef<Path> + ?Sized>(output: &T) -> Self {
+        Self {
+            output: output.as_ref().to_path_buf(),
+            compression_mode: CompressionMode::Default,
+            force: false,
+            prune: false,
+            texture_importer: TextureImporter::default(),
+            gltf_importer: GltfImporter::default(),
+            obj_importer: ObjImporter::default(),
+            shader_compiler: ShaderCompiler::default(),
+        }
+    }
+
+    // Inputs are source files or directories searched recursively. Files of directories
+    // without a known extension are ignored.
+    pub fn cook<T: AsRef<Path>>(&self, inputs: &[T]) -> Result<CookReport, AssetError> {
+        std::fs::create_dir_all(&self.output)?;
+        let mut cache = BuildCache::open(&self.output)?;
+        let mut report = CookReport::default();
+        let sources = source_paths(inputs)?;
+
+        for (source, relative_path, root) in &sources {
+            let Some(kind) = SourceKind::from_path(source) else {
+                report.unsupported.push(source.clone());
+                continue;
+            };
+            let failed = |error: AssetError| FailedSource {
+                source: source.clone(),
+                error: error.to_string(),
+            };
+
+            let settings = match ImportSettings::for_source(source, root) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    report.failed.push(failed(e));
+                    continue;
+                }
+            };
+            let key = match std::fs::read(source) {
+                Ok(content) => self.build_key(source, kind, &content, &settings),
+                Err(e) => Err(AssetError::Io(e)),
+            };
+            let key = match key {
+                Ok(Some(key)) => key,
+                Ok(None) => {
+                    report.unsupported.push(source.clone());
+                    continue;
+                }
+                Err(e) => {
+                    report.failed.push(failed(e));
+                    continue;
+                }
+            };
+            // Files the source read last time decide whether it's up to date as well.
+            let is_up_to_date = key
+                .with_files(cache.files(relative_path))
+                .is_ok_and(|key| cache.is_up_to_date(relative_path, key));
+            if !self.force && is_up_to_date {
+                report.skipped.push(source.clone());
+                continue;
+            }
+
+            let outputs = self
+                .cook_source(source, relative_path, kind, &settings)
+                .and_then(|(outputs, files)| {
+                    let key = key.with_files(&files)?;
+                    report.deleted.extend(cache.insert(
+                        relative_path,
+                        key,
+                        files,
+                        outputs.clone(),
+                    )?);
+                    Ok(outputs)
+                });
+            match outputs {
+                Ok(outputs) => report.cooked.push(CookedSource {
+                    source: source.clone(),
+                    kind,
+                    outputs,
+                }),
+                Err(e) => report.failed.push(failed(e)),
+            }
+        }
+
+        if self.prune {
+            let deleted = cache.prune(|cached| {
+                sources
+                    .iter()
+                    .any(|(_, relative_path, _)| relative_path == cached)
+            })?;
+            report.deleted.extend(deleted);
+        }
+        cache.save()?;
+
+        Ok(report)
+    }
+
+    // `None` if there is no importer for the source.
+    fn build_key(
+        &self,
+        source: &Path,
+        kind: SourceKind,
+        content: &[u8],
+        settings: &ImportSettings,
+    ) -> Result<Option<BuildKey>, AssetError> {
+        let key = match kind {
+            SourceKind::Texture => BuildKey::new(
+                content,
+                "texture",
+                TextureImporter::VERSION,
+                &self.texture_importer(settings),
+            )?,
+            SourceKind::Gltf => BuildKey::new(
+                content,
+                "gltf",
+                GltfImporter::VERSION,
+                &self.gltf_importer(settings),
+            )?,
+            SourceKind::Obj => BuildKey::new(
+                content,
+                "obj",
+                ObjImporter::VERSION,
+                &self.obj_importer(settings),
+            )?,
+            SourceKind::Shader => match ShaderCompiler::stage(source) {
+                Some(_) => BuildKey::new(
+                    content,
+                    "shader",
+                    ShaderCompiler::VERSION,
+                    &self.shader_compiler,
+                )?,
+                // Files without a stage are only included by shaders.
+                None => return Ok(None),
+            },
+        };
+
+        Ok(Some(key))
+    }
+
+    // Returns the paths of the saved asset files and of the files read besides the source. Asset
+    // paths, and the GUIDs importers derive from them, are relative to the output, so they don't
+    // depend on where it is.
+    fn cook_source(
+        &self,
+        source: &Path,
+        relative_path: &Path,
+        kind: SourceKind,
+        settings: &ImportSettings,
+    ) -> Result<(Vec<PathBuf>, Vec<PathBuf>), AssetError> {
+        // An empty parent would make the asset paths absolute.
+        let directory = match relative_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let name = source
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        let directory = directory.to_string_lossy();
+
+        match kind {
+            SourceKind::Texture => {
+                let path = format!("{directory}/{name}.{ASSET_FILE_EXTENSION}");
+                let asset_file = self
+                    .texture_importer(settings)
+                    .import(source, &name, &path)?;
+
+                Ok((vec![asset_file.save_to_project(&self.output)?], Vec::new()))
+            }
+            SourceKind::Gltf => {
+                let assets = self
+                    .gltf_importer(settings)
+                    .import(source, &name, &directory)?;
+
+                Ok((self.save(assets.iter())?, assets.files))
+            }
+            SourceKind::Obj => {
+                let assets = self
+                    .obj_importer(settings)
+                    .import(source, &name, &directory)?;
+
+                Ok((self.save(assets.iter())?, assets.files))
+            }
+            // Every permutation becomes a shader asset named after the shader file followed by its
+            // keywords, e.g. `lit.frag` and `lit.frag.ALPHA_TEST`.
+            SourceKind::Shader => {
+                let stage = ShaderCompiler::stage(source).ok_or_else(|| {
+                    AssetError::InvalidShader(format!(
+                        "The stage of {} is unknown.",
+                        source.display()
+                    ))
+                })?;
+                let shader_source = self.shader_compiler.preprocess(source)?;
+                let file_name = source
+                    .file_name()
+                    .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+
+                let outputs = shader_source
+                    .permutations()
+                    .iter()
+                    .map(|keywords| {
+                        let code = self
+                            .shader_compiler
+                            .compile(&shader_source, stage, keywords)?;
+                        let name = keywords.iter().fold(file_name.clone(), |name, keyword| {
+                            format!("{name}.{keyword}")
+                        });
+                        let path = format!("{directory}/{name}.{ASSET_FILE_EXTENSION}");
+                        let asset_file = Shader::from_spirv(vec![code])?
+                            .into_asset_file(&name, &path, self.compression_mode)?
+                            .with_guid(AssetGuid::from_name(&path));
+
+                        asset_file.save_to_project(&self.output)
+                    })
+                    .collect::<Result<_, AssetError>>()?;
+
+                // The shader file comes first, followed by its includes.
+                Ok((outputs, shader_source.files()[1..].to_vec()))
+            }
+        }
+    }
+
+    fn save<'a>(
+        &self,
+        asset_files: impl Iterator<Item = &'a AssetFile>,
+    ) -> Result<Vec<PathBuf>, AssetError> {
+        asset_files
+            .map(|asset_file| asset_file.save_to_project(&self.output))
+            .collect()
+    }
+
+    // Settings of the source win over the compression mode of the cooker.
+    fn texture_importer(&self, settings: &ImportSettings) -> TextureImporter {
+        settings.texture_importer(&TextureImporter {
+            compression_mode: self.compression_mode,
+            ..self.texture_importer.clone()
+        })
+    }
+
+    fn gltf_importer(&self, settings: &ImportSettings) -> GltfImporter {
+        settings.gltf_importer(&GltfImporter {
+            compression_mode: self.compression_mode,
+            ..self.gltf_importer.clone()
+        })
+    }
+
+    fn obj_importer(&self, settings: &ImportSettings) -> ObjImporter {
+        settings.obj_importer(&ObjImporter {
+            compression_mode: self.compression_mode,
+            ..self.obj_importer.clone()
+        })
+    }
+}
+
+// Every source file with its path relative to the input it was found in and the folder whose
+// import settings defaults apply to it.
+fn source_paths<T: AsRef<Path>>(
+    inputs: &[T],
+) -> Result<Vec<(PathBuf, PathBuf, PathBuf)>, AssetError> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        let input = input.as_ref();
+        match input.is_dir() {
+            true => {
+                let mut files = Vec::new();
+                collect_files(input, &mut files)?;
+                files.sort();
+                paths.extend(
+                    files
+                        .into_iter()
+                        .filter(|file| SourceKind::from_path(file).is_some())
+                        .map(|file| {
+                            let relative_path = file.strip_prefix(input).unwrap_or(&file);
+                            (
+                                file.clone(),
+                                relative_path.to_path_buf(),
+                                input.to_path_buf(),
+                            )
+                        }),
+                );
+            }
+            false => {
+                let file_name = input.file_name().map(PathBuf::from).unwrap_or_default();
+                let root = input.parent().unwrap_or(Path::new("")).to_path_buf();
+                paths.push((input.to_path_buf(), file_name, root));
+            }
+        }
+    }
+
+    Ok(paths)
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) -> Result<(), AssetError> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        match path.is_dir() {
+            true => collect_files(&path, files)?,
+            false => files.push(path),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asset_system::{Asset, ShaderStage};
+
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn skip_unchanged_sources() {
+        let sources = Path::new("src/test_files/skip_unchanged_sources/sources");
+        let output = Path::new("src/test_files/skip_unchanged_sources/cooked");
+        std::fs::create_dir_all(sources.join("meshes")).unwrap();
+        std::fs::write(sources.join("meshes/triangle.obj"), TRIANGLE).unwrap();
+        std::fs::write(sources.join("notes.txt"), "Not an asset.").unwrap();
+        std::fs::write(
+            sources.join("lit.frag"),
+            "#version 450\n#pragma permutation ALPHA_TEST\nvoid main() {}\n",
+        )
+        .unwrap();
+        std::fs::write(sources.join("common.glsl"), "const float PI = 3.14159;\n").unwrap();
+
+        let cooker = Cooker::new(output);
+        let report = cooker.cook(&[sources]).unwrap();
+        assert_eq!(report.cooked.len(), 2);
+        assert_eq!(report.cooked[0].kind, SourceKind::Shader);
+        assert_eq!(
+            report.cooked[0].outputs,
+            [
+                output.join("lit.frag.bin"),
+                output.join("lit.frag.ALPHA_TEST.bin")
+            ]
+        );
+        assert_eq!(report.cooked[1].kind, SourceKind::Obj);
+        assert_eq!(
+            report.cooked[1].outputs,
+            [output.join("meshes/triangle.bin")]
+        );
+        assert!(output.join("meshes/triangle.bin").is_file());
+        let shader = Shader::from_asset_file(
+            AssetFile::load_asset_file(&output.join("lit.frag.bin")).unwrap(),
+        )
+        .unwrap();
+        assert!(shader.code(ShaderStage::Fragment).is_some());
+        assert_eq!(report.unsupported, [sources.join("common.glsl")]);
+        assert!(report.failed.is_empty());
+
+        let report = cooker.cook(&[sources]).unwrap();
+        assert!(report.cooked.is_empty());
+        assert_eq!(
+            report.skipped,
+            [
+                sources.join("lit.frag"),
+                sources.join("meshes/triangle.obj")
+            ]
+        );
+
+        std::fs::write(
+            sources.join("meshes/triangle.obj"),
+            TRIANGLE.replace("v 0 1 0", "v 0 2 0"),
+        )
+        .unwrap();
+        let report = cooker.cook(&[sources]).unwrap();
+        assert_eq!(report.cooked.len(), 1);
+
+        let cooker = Cooker {
+            compression_mode: CompressionMode::None,
+            ..cooker
+        };
+        let report = cooker.cook(&[sources]).unwrap();
+        assert_eq!(report.cooked.len(), 1);
+
+        // Changed import settings cook the source again.
+        std::fs::write(
+            sources.join("meshes/triangle.obj.import.ron"),
+            "(mesh: (scale: Some(2.0)))",
+        )
+        .unwrap();
+        let report = cooker.cook(&[sources]).unwrap();
+        assert_eq!(report.cooked.len(), 1);
+
+        // Cooking the same source again gives the same asset file.
+        let cooked = std::fs::read(output.join("meshes/triangle.bin")).unwrap();
+        let cooker = Cooker {
+            force: true,
+            ..cooker
+        };
+        cooker.cook(&[sources]).unwrap();
+        assert_eq!(
+            std::fs::read(output.join("meshes/triangle.bin")).unwrap(),
+            cooked
+        );
+
+        // So does cooking it into another output.
+        let other_output = Path::new("src/test_files/skip_unchanged_sources/other");
+        Cooker {
+            output: other_output.to_path_buf(),
+            ..cooker.clone()
+        }
+        .cook(&[sources])
+        .unwrap();
+        assert_eq!(
+            std::fs::read(other_output.join("meshes/triangle.bin")).unwrap(),
+            cooked
+        );
+        assert_eq!(
+            std::fs::read(other_output.join("lit.frag.bin")).unwrap(),
+            std::fs::read(output.join("lit.frag.bin")).unwrap()
+        );
+
+        // Asset files of sources that disappeared from the inputs are deleted.
+        std::fs::remove_file(sources.join("meshes/triangle.obj")).unwrap();
+        let cooker = Cooker {
+            prune: true,
+            ..cooker
+        };
+        let report = cooker.cook(&[sources]).unwrap();
+        assert_eq!(report.deleted, [output.join("meshes/triangle.bin")]);
+        assert!(!output.join("meshes/triangle.bin").exists());
+
+        std::fs::remove_dir_all("src/test_files/skip_unchanged_sources").unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn cook_again_when_dependencies_change() {
+        let sources = Path::new("src/test_files/cook_again_when_dependencies_change/sources");
+        let output = Path::new("src/test_files/cook_again_when_dependencies_change/cooked");
+        std::fs::create_dir_all(sources).unwrap();
+        std::fs::write(
+            sources.join("triangle.obj"),
+            format!("mtllib triangle.mtl\nusemtl red\n{TRIANGLE}"),
+        )
+        .unwrap();
+        std::fs::write(sources.join("triangle.mtl"), "newmtl red\nKd 1 0 0\n").unwrap();
+        std::fs::write(
+            sources.join("lit.frag"),
+            "#version 450\n#include \"common.glsl\"\nvoid main() {}\n",
+        )
+        .unwrap();
+        std::fs::write(sources.join("common.glsl"), "const float PI = 3.14159;\n").unwrap();
+
+        let cooker = Cooker::new(output);
+        let report = cooker.cook(&[sources]).unwrap();
+        assert_eq!(report.cooked.len(), 2);
+        let report = cooker.cook(&[sources]).unwrap();
+        assert!(report.cooked.is_empty());
+
+        std::fs::write(sources.join("triangle.mtl"), "newmtl red\nKd 0 1 0\n").unwrap();
+        let report = cooker.cook(&[sources]).unwrap();
+        assert_eq!(report.cooked.len(), 1);
+        assert_eq!(report.cooked[0].source, sources.join("triangle.obj"));
+
+        std::fs::write(sources.join("common.glsl"), "const float TAU = 6.28318;\n").unwrap();
+        let report = cooker.cook(&[sources]).unwrap();
+        assert_eq!(report.cooked.len(), 1);
+        assert_eq!(report.cooked[0].source, sources.join("lit.frag"));
+
+        std::fs::remove_dir_all("src/test_files/cook_again_when_dependencies_change").unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+#![deny(unsafe_code)]
+#![deny(unstable_features)]
+
+mod cook;
+
+use asset_system::CompressionMode;
+use clap::{Parser, ValueEnum};
+use cook::Cooker;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Imports source assets into asset files")]
+struct Arguments {
+    #[arg(
+        required = true,
+        help = "Source files or directories, directories are searched recursively"
+    )]
+    inputs: Vec<PathBuf>,
+    #[arg(
+        short,
+        long,
+        default_value = "cooked",
+        help = "Directory of the asset files"
+    )]
+    output: PathBuf,
+    #[arg(short, long, value_enum, default_value_t = Compression::Default)]
+    compression: Compression,
+    #[arg(long, help = "Cook sources even if they're up to date")]
+    force: bool,
+    #[arg(
+        long,
+        help = "Delete asset files of sources that aren't among the inputs anymore"
+    )]
+    prune: bool,
+    #[arg(long, help = "Print the report as JSON")]
+    json: bool,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Compression {
+    None,
+    Fast,
+    Default,
+    High,
+    VeryHigh,
+}
+
+impl From<Compression> for CompressionMode {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::None => CompressionMode::None,
+            Compression::Fast => CompressionMode::Fast,
+            Compression::Default => CompressionMode::Default,
+            Compression::High => CompressionMode::HighCompression,
+            Compression::VeryHigh => CompressionMode::VeryHighCompression,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let arguments = Arguments::parse();
+    let cooker = Cooker {
+        compression_mode: arguments.compression.into(),
+        force: arguments.force,
+        prune: arguments.prune,
+        ..Cooker::new(&arguments.output)
+    };
+
+    let report = match cooker.cook(&arguments.inputs) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match arguments.json {
+        true => match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("Error: Failed to serialize the report: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        false => println!("{}", report.summary()),
+    }
+
+    match report.failed.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
@@ -4,6 +4,7 @@
 use engine::Engine;
 use winit::event::{Event, KeyboardInput, WindowEvent};
 
+#[allow(clippy::collapsible_match)]
 fn main() {
     // TODO: Change to the SDL2 due to more feature availability and capabilities, but maybe it's unreasonable.
     let event_loop = winit::event_loop::EventLoop::new();
@@ -0,0 +1,127 @@
+use crate::{AssetError, TextureFormat};
+use block_compression::{BC7Settings, CompressionVariant};
+use image::RgbaImage;
+
+// Encodes an RGBA8 image into BC1, BC3, BC4, BC5 or BC7 blocks. BC4 keeps the red channel and BC5
+// the red and green channels, e.g. of a mask or a normal map.
+pub(crate) fn compress(format: TextureFormat, image: &RgbaImage) -> Result<Vec<u8>, AssetError> {
+    // The encoders only work on whole blocks, so the edge texels are repeated into the padding.
+    let (width, height) = (image.width(), image.height());
+    let padded = RgbaImage::from_fn(width.div_ceil(4) * 4, height.div_ceil(4) * 4, |x, y| {
+        *image.get_pixel(x.min(width - 1), y.min(height - 1))
+    });
+    let has_alpha = padded.pixels().any(|texel| texel.0[3] != u8::MAX);
+    let variant = match format.to_unorm() {
+        TextureFormat::BC1RgbUnorm => CompressionVariant::BC1,
+        TextureFormat::BC3Unorm => CompressionVariant::BC3,
+        TextureFormat::BC4Unorm => CompressionVariant::BC4,
+        TextureFormat::BC5Unorm => CompressionVariant::BC5,
+        TextureFormat::BC7Unorm => CompressionVariant::BC7(match has_alpha {
+            true => BC7Settings::alpha_basic(),
+            false => BC7Settings::opaque_basic(),
+        }),
+        _ => return Err(AssetError::UnsupportedTextureFormat(format)),
+    };
+
+    let mut blocks = vec![0; variant.blocks_byte_size(padded.width(), padded.height())];
+    block_compression::encode::compress_rgba8(
+        variant,
+        &padded,
+        &mut blocks,
+        padded.width(),
+        padded.height(),
+        padded.width() * 4,
+    );
+
+    Ok(blocks)
+}
+
+// Decodes blocks of BC1–BC5, BC7 and ASTC formats into RGBA8 texels.
+pub(crate) fn decompress(
+    format: TextureFormat,
+    blocks: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, AssetError> {
+    use TextureFormat::*;
+
+    let (width, height) = (width as usize, height as usize);
+    let mut texels = vec![0; width * height];
+    let (block_width, block_height) = format.block_dimensions();
+    let result = match format.to_unorm() {
+        BC1RgbUnorm | BC1RgbaUnorm => {
+            texture2ddecoder::decode_bc1(blocks, width, height, &mut texels)
+        }
+        BC2Unorm => texture2ddecoder::decode_bc2(blocks, width, height, &mut texels),
+        BC3Unorm => texture2ddecoder::decode_bc3(blocks, width, height, &mut texels),
+        BC4Unorm => texture2ddecoder::decode_bc4(blocks, width, height, &mut texels),
+        BC5Unorm => texture2ddecoder::decode_bc5(blocks, width, height, &mut texels),
+        BC7Unorm => texture2ddecoder::decode_bc7(blocks, width, height, &mut texels),
+        format if format.is_astc() => texture2ddecoder::decode_astc(
+            blocks,
+            width,
+            height,
+            block_width as usize,
+            block_height as usize,
+            &mut texels,
+        ),
+        _ => return Err(AssetError::UnsupportedTextureFormat(format)),
+    };
+    result.map_err(|e| AssetError::Compression(e.to_string()))?;
+
+    // The decoder packs texels as BGRA.
+    Ok(texels
+        .into_iter()
+        .flat_map(|texel| {
+            let [b, g, r, a] = texel.to_le_bytes();
+            [r, g, b, a]
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn compress_and_decompress_blocks() {
+        // Not a multiple of the block size on purpose.
+        let image = RgbaImage::from_fn(6, 5, |x, y| {
+            image::Rgba([x as u8 * 8, y as u8 * 10, 90, 255])
+        });
+
+        for format in [
+            TextureFormat::BC1RgbSrgb,
+            TextureFormat::BC3Unorm,
+            TextureFormat::BC4Unorm,
+            TextureFormat::BC5Unorm,
+            TextureFormat::BC7Srgb,
+        ] {
+            let blocks = compress(format, &image).unwrap();
+            assert_eq!(blocks.len(), format.data_size(8, 8), "{format:?}");
+
+            let texels = decompress(format, &blocks, 6, 5).unwrap();
+            assert_eq!(texels.len(), 6 * 5 * 4);
+            // Compression is lossy, but the texels must stay close to the source.
+            let channels = match format {
+                TextureFormat::BC4Unorm => 1,
+                TextureFormat::BC5Unorm => 2,
+                _ => 3,
+            };
+            for (decoded, source) in texels.chunks(4).zip(image.pixels()) {
+                for channel in 0..channels {
+                    assert!(
+                        decoded[channel].abs_diff(source.0[channel]) <= 16,
+                        "{format:?}: {decoded:?} != {source:?}"
+                    );
+                }
+            }
+        }
+
+        assert!(matches!(
+            compress(TextureFormat::RGBA8Srgb, &image),
+            Err(AssetError::UnsupportedTextureFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::AssetError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const BUILD_CACHE_FILE_NAME: &str = "asset_build_cache.ron";
+
+// Identifies one import of a source: its content, the importer with its version and the
+// import settings. Importers produce the same asset files for the same key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BuildKey(u64);
+
+impl BuildKey {
+    pub fn new<S: Serialize>(
+        source: &[u8],
+        importer: &str,
+        importer_version: u32,
+        settings: &S,
+    ) -> Result<Self, AssetError> {
+        let settings = ron::to_string(settings)?;
+        let mut hasher = xxhash_rust::xxh64::Xxh64::new(0);
+        // Lengths keep the boundaries between the parts unambiguous.
+        for part in [source, importer.as_bytes(), settings.as_bytes()] {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        hasher.update(&importer_version.to_le_bytes());
+
+        Ok(Self(hasher.digest()))
+    }
+
+    // Also covers the content of other files the import read, e.g. textures of a material
+    // library. Missing files are hashed as such, so creating them changes the key too.
+    pub fn with_files<T: AsRef<Path>>(self, files: &[T]) -> Result<Self, AssetError> {
+        let mut hasher = xxhash_rust::xxh64::Xxh64::new(0);
+        hasher.update(&self.0.to_le_bytes());
+        for file in files {
+            let path = file.as_ref().to_string_lossy();
+            hasher.update(&(path.len() as u64).to_le_bytes());
+            hasher.update(path.as_bytes());
+            match std::fs::read(file) {
+                Ok(content) => {
+                    hasher.update(&[1]);
+                    hasher.update(&(content.len() as u64).to_le_bytes());
+                    hasher.update(&content);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => hasher.update(&[0]),
+                Err(e) => return Err(AssetError::Io(e)),
+            }
+        }
+
+        Ok(Self(hasher.digest()))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildIndex {
+    version: String,
+    entries: BTreeMap<PathBuf, BuildEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct BuildEntry {
+    key: BuildKey,
+    // Paths are relative to the root of the cache.
+    outputs: Vec<PathBuf>,
+    // Files read besides the source, whose content is part of the key.
+    #[serde(default)]
+    files: Vec<PathBuf>,
+}
+
+// Remembers which asset files every source was imported into and with which key, so only
+// changed sources are imported again. Outputs are expected to be inside `root`, sources are
+// identified by paths chosen by the caller, e.g. relative to the project.
+#[derive(Debug)]
+pub struct BuildCache {
+    root: PathBuf,
+    index: BuildIndex,
+}
+
+impl BuildCache {
+    pub fn new<T: AsRef<Path> + ?Sized>(root: &T) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            index: BuildIndex {
+                version: crate::CURRENT_ASSET_SYSTEM_VERSION.to_string(),
+                entries: BTreeMap::new(),
+            },
+        }
+    }
+
+    // Opens the cache stored in `root`, a cache of another version of the asset system is
+    // discarded, so everything is imported again.
+    pub fn open<T: AsRef<Path> + ?Sized>(root: &T) -> Result<Self, AssetError> {
+        let mut cache = Self::new(root);
+
+        match std::fs::read_to_string(cache.index_path()) {
+            Ok(content) => {
+                let index: BuildIndex = ron::from_str(&content)?;
+                if index.version == cache.index.version {
+                    cache.index = index;
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(AssetError::Io(e)),
+        }
+
+        Ok(cache)
+    }
+
+    pub fn save(&self) -> Result<(), AssetError> {
+        let serialized = ron::ser::to_string_pretty(&self.index, Default::default())?;
+
+        crate::save_atomically(&self.index_path(), |writer| {
+            Ok(writer.write_all(serialized.as_bytes())?)
+        })
+    }
+
+    // The source was imported with the same key and none of its asset files is missing.
+    pub fn is_up_to_date<T: AsRef<Path> + ?Sized>(&self, source: &T, key: BuildKey) -> bool {
+        self.index
+            .entries
+            .get(source.as_ref())
+            .is_some_and(|entry| {
+                entry.key == key
+                    && entry
+                        .outputs
+                        .iter()
+                        .all(|output| self.root.join(output).is_file())
+            })
+    }
+
+    // Files the last import of the source read besides it, to compute the key to compare with
+    // `BuildKey::with_files`.
+    pub fn files<T: AsRef<Path> + ?Sized>(&self, source: &T) -> &[PathBuf] {
+        self.index
+            .entries
+            .get(source.as_ref())
+            .map_or(&[], |entry| entry.files.as_slice())
+    }
+
+    // Asset files of the source, relative to the root.
+    pub fn outputs<T: AsRef<Path> + ?Sized>(&self, source: &T) -> Option<&[PathBuf]> {
+        self.index
+            .entries
+            .get(source.as_ref())
+            .map(|entry| entry.outputs.as_slice())
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &Path> {
+        self.index.entries.keys().map(PathBuf::as_path)
+    }
+
+    // Records an import of the source that read `files` besides it. Asset files of the previous
+    // import that weren't produced again are deleted and returned.
+    pub fn insert<T: AsRef<Path> + ?Sized>(
+        &mut self,
+        source: &T,
+        key: BuildKey,
+        files: Vec<PathBuf>,
+        outputs: Vec<PathBuf>,
+    ) -> Result<Vec<PathBuf>, AssetError> {
+        let outputs = outputs
+            .into_iter()
+            .map(|output| match output.strip_prefix(&self.root) {
+                Ok(relative_path) => relative_path.to_path_buf(),
+                Err(_) => output,
+            })
+            .collect::<Vec<_>>();
+        let previous = self.index.entries.insert(
+            source.as_ref().to_path_buf(),
+            BuildEntry {
+                key,
+                outputs,
+                files,
+            },
+        );
+
+        match previous {
+            Some(previous) => {
+                let outputs = &self.index.entries[source.as_ref()].outputs;
+                let stale = previous
+                    .outputs
+                    .into_iter()
+                    .filter(|output| !outputs.contains(output))
+                    .collect();
+
+                self.delete_outputs(stale)
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // Forgets the source and deletes its asset files, which are returned.
+    pub fn remove<T: AsRef<Path> + ?Sized>(
+        &mut self,
+        source: &T,
+    ) -> Result<Vec<PathBuf>, AssetError> {
+        match self.index.entries.remove(source.as_ref()) {
+            Some(entry) => self.delete_outputs(entry.outputs),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // Removes the sources for which `keep` returns false, e.g. deleted ones, together with
+    // their asset files. Returns the deleted asset files.
+    pub fn prune<F: FnMut(&Path) -> bool>(
+        &mut self,
+        mut keep: F,
+    ) -> Result<Vec<PathBuf>, AssetError> {
+        let removed = self
+            .index
+            .entries
+            .keys()
+            .filter(|source| !keep(source))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut deleted = Vec::new();
+        for source in removed {
+            deleted.extend(self.remove(&source)?);
+        }
+
+        Ok(deleted)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn delete_outputs(&self, outputs: Vec<PathBuf>) -> Result<Vec<PathBuf>, AssetError> {
+        let mut deleted = Vec::new();
+        for output in outputs {
+            let path = self.root.join(&output);
+            match std::fs::remove_file(&path) {
+                Ok(()) => deleted.push(path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+                Err(e) => return Err(AssetError::Io(e)),
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.root.join(BUILD_CACHE_FILE_NAME)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn track_and_prune_outputs() {
+        let root = Path::new("src/test_asset_files/build_cache");
+        std::fs::create_dir_all(root).unwrap();
+        for output in ["a.bin", "b.bin", "c.bin"] {
+            std::fs::write(root.join(output), []).unwrap();
+        }
+
+        let settings = ("Albedo", true);
+        let key = BuildKey::new(b"source", "texture", 1, &settings).unwrap();
+        assert_eq!(
+            key,
+            BuildKey::new(b"source", "texture", 1, &settings).unwrap()
+        );
+        assert_ne!(
+            key,
+            BuildKey::new(b"source", "texture", 2, &settings).unwrap()
+        );
+        assert_ne!(
+            key,
+            BuildKey::new(b"source", "texture", 1, &("Mask", true)).unwrap()
+        );
+
+        let mut cache = BuildCache::new(root);
+        let outputs = vec![root.join("a.bin"), root.join("b.bin")];
+        let files = vec![root.join("x.bin")];
+        assert!(cache
+            .insert("x.gltf", key, files.clone(), outputs)
+            .unwrap()
+            .is_empty());
+        cache
+            .insert("y.png", key, Vec::new(), vec![root.join("c.bin")])
+            .unwrap();
+        assert!(cache.is_up_to_date("x.gltf", key));
+        cache.save().unwrap();
+
+        // A new import of the source no longer producing `b.bin` deletes it.
+        let mut cache = BuildCache::open(root).unwrap();
+        assert_eq!(
+            cache.outputs("x.gltf").unwrap(),
+            [PathBuf::from("a.bin"), PathBuf::from("b.bin")]
+        );
+        assert_eq!(cache.files("x.gltf"), files);
+        assert!(cache.files("z.obj").is_empty());
+
+        // The content of the files read besides the source is part of the key.
+        let with_files = key.with_files(&files).unwrap();
+        assert_ne!(with_files, key);
+        std::fs::write(root.join("x.bin"), [1]).unwrap();
+        assert_ne!(key.with_files(&files).unwrap(), with_files);
+        std::fs::remove_file(root.join("x.bin")).unwrap();
+        assert_eq!(key.with_files(&files).unwrap(), with_files);
+
+        let changed_key = BuildKey::new(b"changed", "texture", 1, &settings).unwrap();
+        assert!(!cache.is_up_to_date("x.gltf", changed_key));
+        let deleted = cache
+            .insert("x.gltf", changed_key, Vec::new(), vec![root.join("a.bin")])
+            .unwrap();
+        assert_eq!(deleted, [root.join("b.bin")]);
+        assert!(!root.join("b.bin").exists());
+
+        let deleted = cache.prune(|source| source != Path::new("y.png")).unwrap();
+        assert_eq!(deleted, [root.join("c.bin")]);
+        assert_eq!(cache.sources().collect::<Vec<_>>(), [Path::new("x.gltf")]);
+
+        std::fs::remove_file(root.join("a.bin")).unwrap();
+        assert!(!cache.is_up_to_date("x.gltf", changed_key));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
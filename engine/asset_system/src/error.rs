@@ -0,0 +1,127 @@
+use crate::{AssetGuid, AssetType, TextureFormat};
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum AssetError {
+    Io(std::io::Error),
+    Compression(String),
+    Serialization(String),
+    ChecksumMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    UnknownGuid(AssetGuid),
+    UnexpectedAssetType {
+        expected: AssetType,
+        actual: AssetType,
+    },
+    // The file is already cached as another runtime type than the named one.
+    UnexpectedRuntimeType(&'static str),
+    Cancelled,
+    DependencyFailed(AssetGuid),
+    DependencyCycle(Vec<AssetGuid>),
+    DuplicateGuid(AssetGuid),
+    InvalidPack(String),
+    Import(String),
+    UnsupportedTextureFormat(TextureFormat),
+    // The path has no file name or leaves the project.
+    InvalidPath(PathBuf),
+    InvalidShader(String),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io(e) => write!(f, "Error: I/O failure of an asset file: {e}"),
+            AssetError::Compression(e) => {
+                write!(f, "Error: Failed to (de)compress an asset file: {e}")
+            }
+            AssetError::Serialization(e) => {
+                write!(f, "Error: Failed to (de)serialize an asset file: {e}")
+            }
+            AssetError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "Error: Asset file is corrupted, checksum mismatch (expected {expected:#018x}, got {actual:#018x})"
+            ),
+            AssetError::UnknownGuid(guid) => write!(f, "Error: Unknown asset GUID {guid}"),
+            AssetError::UnexpectedAssetType { expected, actual } => write!(
+                f,
+                "Error: Unexpected type of an asset file (expected {expected:?}, got {actual:?})"
+            ),
+            AssetError::UnexpectedRuntimeType(expected) => write!(
+                f,
+                "Error: Asset file is already loaded as another type than {expected}"
+            ),
+            AssetError::Cancelled => write!(f, "Error: Loading of an asset file was cancelled"),
+            AssetError::DependencyFailed(guid) => {
+                write!(f, "Error: Failed to load a dependency {guid}")
+            }
+            AssetError::DependencyCycle(cycle) => {
+                let cycle = cycle
+                    .iter()
+                    .map(|guid| guid.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                write!(f, "Error: Asset dependencies form a cycle: {cycle}")
+            }
+            AssetError::DuplicateGuid(guid) => {
+                write!(f, "Error: Several assets have the same GUID {guid}")
+            }
+            AssetError::InvalidPack(e) => write!(f, "Error: Invalid asset pack: {e}"),
+            AssetError::Import(e) => write!(f, "Error: Failed to import a source asset: {e}"),
+            AssetError::UnsupportedTextureFormat(format) => {
+                write!(f, "Error: Unsupported texture format {format:?}")
+            }
+            AssetError::InvalidPath(path) => {
+                write!(f, "Error: Invalid path of an asset file {}", path.display())
+            }
+            AssetError::InvalidShader(e) => write!(f, "Error: Invalid shader: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssetError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AssetError {
+    fn from(e: std::io::Error) -> Self {
+        AssetError::Io(e)
+    }
+}
+
+impl From<ron::Error> for AssetError {
+    fn from(e: ron::Error) -> Self {
+        AssetError::Serialization(e.to_string())
+    }
+}
+
+impl From<image::ImageError> for AssetError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => AssetError::Io(e),
+            e => AssetError::Import(e.to_string()),
+        }
+    }
+}
+
+impl From<gltf::Error> for AssetError {
+    fn from(e: gltf::Error) -> Self {
+        match e {
+            gltf::Error::Io(e) => AssetError::Io(e),
+            e => AssetError::Import(e.to_string()),
+        }
+    }
+}
+
+impl From<tobj::LoadError> for AssetError {
+    fn from(e: tobj::LoadError) -> Self {
+        AssetError::Import(e.to_string())
+    }
+}
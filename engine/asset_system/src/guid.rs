@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Stable identifier of an asset, assigned once at creation and stored in the asset file,
+// so references survive moving or renaming the file. Importers derive it from the path of the
+// asset file, so re-importing a source keeps the references to its assets valid. That path
+// should be relative to the project, or the GUIDs change with the location of the project.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AssetGuid(uuid::Uuid);
+
+impl AssetGuid {
+    pub fn new() -> Self {
+        Self(uuid::Uuid::new_v4())
+    }
+
+    // The same name always gives the same GUID.
+    pub fn from_name(name: &str) -> Self {
+        let hash = xxhash_rust::xxh3::xxh3_128(name.as_bytes());
+
+        Self(uuid::Builder::from_custom_bytes(hash.to_le_bytes()).into_uuid())
+    }
+
+    pub const fn from_u128(value: u128) -> Self {
+        Self(uuid::Uuid::from_u128(value))
+    }
+
+    pub const fn as_u128(&self) -> u128 {
+        self.0.as_u128()
+    }
+}
+
+impl Default for AssetGuid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for AssetGuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.hyphenated().fmt(f)
+    }
+}
+
+impl std::str::FromStr for AssetGuid {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        uuid::Uuid::parse_str(s).map(Self)
+    }
+}
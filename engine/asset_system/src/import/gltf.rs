@@ -0,0 +1,547 @@
+use super::{MeshProcessing, TextureImporter, TextureUsage};
+use crate::{
+    AlphaMode, AssetError, AssetFile, AssetGuid, CompressionMode, MaterialAsset, Mesh, SceneAsset,
+    SceneNode, Submesh, Vertex, ASSET_FILE_EXTENSION,
+};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const GLTF_SOURCE_EXTENSIONS: &[&str] = &["gltf", "glb"];
+
+// Asset files produced from a glTF document. Materials depend on textures, meshes on
+// materials and the scene on meshes, all of them by GUID.
+#[derive(Debug)]
+pub struct GltfAssets {
+    pub textures: Vec<AssetFile>,
+    pub materials: Vec<AssetFile>,
+    pub meshes: Vec<AssetFile>,
+    pub scene: AssetFile,
+    // Files read besides the glTF file, its external buffers and images.
+    pub files: Vec<PathBuf>,
+}
+
+impl GltfAssets {
+    pub fn iter(&self) -> impl Iterator<Item = &AssetFile> {
+        self.textures
+            .iter()
+            .chain(&self.materials)
+            .chain(&self.meshes)
+            .chain(std::iter::once(&self.scene))
+    }
+
+    pub fn save(&self) -> Result<(), AssetError> {
+        self.iter().try_for_each(AssetFile::save_asset_file)
+    }
+}
+
+// Reads .gltf and .glb files into texture, material, mesh and scene assets. Every glTF mesh
+// becomes one mesh asset with a submesh per primitive, missing normals and tangents are
+// computed. Asset files are named after the source and placed into `directory`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GltfImporter {
+    pub compression_mode: CompressionMode,
+    // Settings of the textures, their usage is decided by the material slots referencing them.
+    pub texture_importer: TextureImporter,
+    pub mesh_processing: MeshProcessing,
+}
+
+impl Default for GltfImporter {
+    fn default() -> Self {
+        Self {
+            compression_mode: CompressionMode::Default,
+            texture_importer: TextureImporter::default(),
+            mesh_processing: MeshProcessing::default(),
+        }
+    }
+}
+
+impl GltfImporter {
+    // Bumped whenever the output for the same source and settings changes.
+    pub const VERSION: u32 = 2;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn import<T: AsRef<Path> + ?Sized>(
+        &self,
+        source: &T,
+        name: &str,
+        directory: &str,
+    ) -> Result<GltfAssets, AssetError> {
+        let gltf::Gltf { document, blob } = gltf::Gltf::open(source)?;
+
+        self.import_document(&document, blob, source.as_ref().parent(), name, directory)
+    }
+
+    // External buffers and images can't be resolved, only embedded ones and those in the
+    // binary chunk of .glb files.
+    pub fn import_from_memory(
+        &self,
+        source: &[u8],
+        name: &str,
+        directory: &str,
+    ) -> Result<GltfAssets, AssetError> {
+        let gltf::Gltf { document, blob } = gltf::Gltf::from_slice(source)?;
+
+        self.import_document(&document, blob, None, name, directory)
+    }
+
+    fn import_document(
+        &self,
+        document: &gltf::Document,
+        blob: Option<Vec<u8>>,
+        base: Option<&Path>,
+        name: &str,
+        directory: &str,
+    ) -> Result<GltfAssets, AssetError> {
+        let buffers = gltf::import_buffers(document, base, blob)?;
+        let asset_path =
+            |asset_name: &str| format!("{directory}/{asset_name}.{ASSET_FILE_EXTENSION}");
+
+        // The first material slot referencing an image decides how it's imported.
+        let mut usages = vec![None; document.images().count()];
+        for material in document.materials() {
+            let pbr = material.pbr_metallic_roughness();
+            let slots = [
+                (
+                    pbr.base_color_texture().map(|info| info.texture()),
+                    TextureUsage::Albedo,
+                ),
+                (
+                    material.emissive_texture().map(|info| info.texture()),
+                    TextureUsage::Albedo,
+                ),
+                (
+                    material.normal_texture().map(|normal| normal.texture()),
+                    TextureUsage::NormalMap,
+                ),
+                (
+                    pbr.metallic_roughness_texture().map(|info| info.texture()),
+                    TextureUsage::Packed,
+                ),
+                (
+                    material
+                        .occlusion_texture()
+                        .map(|occlusion| occlusion.texture()),
+                    TextureUsage::Packed,
+                ),
+            ];
+            for (texture, usage) in slots {
+                if let Some(texture) = texture {
+                    usages[texture.source().index()].get_or_insert(usage);
+                }
+            }
+        }
+
+        let mut textures = Vec::new();
+        for (image, data) in document
+            .images()
+            .zip(gltf::import_images(document, base, &buffers)?)
+        {
+            let importer = TextureImporter {
+                usage: usages[image.index()].unwrap_or_default(),
+                compression_mode: self.compression_mode,
+                ..self.texture_importer.clone()
+            };
+            let (texture_asset, texels) = importer.convert(dynamic_image(data)?)?;
+            let texture_name = asset_name(name, "texture", image.index(), image.name());
+
+            let texture_path = asset_path(&texture_name);
+
+            textures.push(
+                AssetFile::new(
+                    texture_asset,
+                    &texture_name,
+                    &texture_path,
+                    texels,
+                    self.compression_mode,
+                )?
+                .with_guid(AssetGuid::from_name(&texture_path)),
+            );
+        }
+        let texture_guid = |texture: gltf::Texture| textures[texture.source().index()].guid();
+
+        let mut materials = Vec::new();
+        for material in document.materials() {
+            let pbr = material.pbr_metallic_roughness();
+            let material_asset = MaterialAsset {
+                base_color_factor: pbr.base_color_factor(),
+                base_color_texture: pbr
+                    .base_color_texture()
+                    .map(|info| texture_guid(info.texture())),
+                metallic_factor: pbr.metallic_factor(),
+                roughness_factor: pbr.roughness_factor(),
+                metallic_roughness_texture: pbr
+                    .metallic_roughness_texture()
+                    .map(|info| texture_guid(info.texture())),
+                normal_texture: material
+                    .normal_texture()
+                    .map(|normal| texture_guid(normal.texture())),
+                normal_scale: material
+                    .normal_texture()
+                    .map_or(1.0, |normal| normal.scale()),
+                occlusion_texture: material
+                    .occlusion_texture()
+                    .map(|occlusion| texture_guid(occlusion.texture())),
+                occlusion_strength: material
+                    .occlusion_texture()
+                    .map_or(1.0, |occlusion| occlusion.strength()),
+                emissive_factor: material.emissive_factor(),
+                emissive_texture: material
+                    .emissive_texture()
+                    .map(|info| texture_guid(info.texture())),
+                alpha_mode: match material.alpha_mode() {
+                    gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                    gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+                    gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+                },
+                alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+                double_sided: material.double_sided(),
+                ..Default::default()
+            }
+            .with_pbr_render_state();
+            let index = material.index().unwrap_or(materials.len());
+            let material_name = asset_name(name, "material", index, material.name());
+
+            let material_path = asset_path(&material_name);
+
+            materials.push(
+                material_asset
+                    .into_asset_file(&material_name, &material_path, self.compression_mode)?
+                    .with_guid(AssetGuid::from_name(&material_path)),
+            );
+        }
+
+        let mut meshes = Vec::new();
+        for mesh in document.meshes() {
+            let mesh_name = asset_name(name, "mesh", mesh.index(), mesh.name());
+
+            let mesh = self
+                .mesh_processing
+                .process(import_mesh(&mesh, &buffers, &materials)?)?;
+            let mesh_path = asset_path(&mesh_name);
+
+            meshes.push(
+                mesh.into_asset_file(&mesh_name, &mesh_path, self.compression_mode)?
+                    .with_guid(AssetGuid::from_name(&mesh_path)),
+            );
+        }
+
+        let nodes = document
+            .nodes()
+            .map(|node| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+
+                SceneNode {
+                    name: node.name().unwrap_or_default().to_string(),
+                    children: node.children().map(|child| child.index() as u32).collect(),
+                    translation,
+                    rotation,
+                    scale,
+                    mesh: node.mesh().map(|mesh| meshes[mesh.index()].guid()),
+                }
+            })
+            .collect();
+        let roots = match document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+        {
+            Some(scene) => scene.nodes().map(|node| node.index() as u32).collect(),
+            None => Vec::new(),
+        };
+        let scene_path = asset_path(name);
+        let scene = SceneAsset { nodes, roots }
+            .into_asset_file(name, &scene_path, self.compression_mode)?
+            .with_guid(AssetGuid::from_name(&scene_path));
+
+        Ok(GltfAssets {
+            textures,
+            materials,
+            meshes,
+            scene,
+            files: external_files(document, base),
+        })
+    }
+}
+
+// Buffers and images referenced by relative URIs, which aren't percent-decoded.
+fn external_files(document: &gltf::Document, base: Option<&Path>) -> Vec<PathBuf> {
+    let Some(base) = base else {
+        return Vec::new();
+    };
+    let buffers = document
+        .buffers()
+        .filter_map(|buffer| match buffer.source() {
+            gltf::buffer::Source::Uri(uri) => Some(uri),
+            gltf::buffer::Source::Bin => None,
+        });
+    let images = document.images().filter_map(|image| match image.source() {
+        gltf::image::Source::Uri { uri, .. } => Some(uri),
+        gltf::image::Source::View { .. } => None,
+    });
+
+    buffers
+        .chain(images)
+        .filter(|uri| !uri.starts_with("data:"))
+        .map(|uri| base.join(uri))
+        .collect()
+}
+
+// Primitives other than triangle lists are skipped.
+fn import_mesh(
+    mesh: &gltf::Mesh,
+    buffers: &[gltf::buffer::Data],
+    materials: &[AssetFile],
+) -> Result<Mesh, AssetError> {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut submeshes = Vec::new();
+
+    for primitive in mesh.primitives() {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            continue;
+        }
+
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let positions = reader.read_positions().ok_or_else(|| {
+            AssetError::Import(format!(
+                "A primitive of the mesh {} has no positions.",
+                mesh.index()
+            ))
+        })?;
+        let mut primitive_vertices = positions
+            .map(|position| Vertex {
+                position,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let primitive_indices = match reader.read_indices() {
+            Some(read_indices) => read_indices.into_u32().collect(),
+            None => (0..primitive_vertices.len() as u32).collect::<Vec<_>>(),
+        };
+        if let Some(index) = primitive_indices
+            .iter()
+            .find(|&&index| index as usize >= primitive_vertices.len())
+        {
+            return Err(AssetError::Import(format!(
+                "Index {index} of the mesh {} is out of bounds.",
+                mesh.index()
+            )));
+        }
+
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            for (vertex, uv) in primitive_vertices.iter_mut().zip(uvs.into_f32()) {
+                vertex.uv = uv;
+            }
+        }
+        let normals = reader.read_normals();
+        let tangents = reader.read_tangents();
+        let (has_normals, has_tangents) = (normals.is_some(), tangents.is_some());
+        if let Some(normals) = normals {
+            for (vertex, normal) in primitive_vertices.iter_mut().zip(normals) {
+                vertex.normal = normal;
+            }
+        }
+        if let Some(tangents) = tangents {
+            for (vertex, tangent) in primitive_vertices.iter_mut().zip(tangents) {
+                vertex.tangent = tangent;
+            }
+        }
+
+        let mut primitive_mesh = Mesh::new(primitive_vertices, primitive_indices, Vec::new());
+        if !has_normals {
+            primitive_mesh.compute_normals();
+        }
+        if !has_tangents {
+            primitive_mesh.compute_tangents();
+        }
+
+        let vertex_offset = vertices.len() as u32;
+        submeshes.push(Submesh {
+            lods: Vec::new(),
+            meshlet_offset: 0,
+            meshlet_count: 0,
+            index_offset: indices.len() as u32,
+            index_count: primitive_mesh.indices.len() as u32,
+            material: primitive
+                .material()
+                .index()
+                .map(|index| materials[index].guid()),
+        });
+        vertices.extend(primitive_mesh.vertices);
+        indices.extend(
+            primitive_mesh
+                .indices
+                .iter()
+                .map(|index| index + vertex_offset),
+        );
+    }
+
+    Ok(Mesh::new(vertices, indices, submeshes))
+}
+
+fn asset_name(name: &str, kind: &str, index: usize, asset_name: Option<&str>) -> String {
+    match asset_name {
+        Some(asset_name) if !asset_name.is_empty() => format!("{name}_{kind}_{index}_{asset_name}"),
+        _ => format!("{name}_{kind}_{index}"),
+    }
+}
+
+// 16-bit and float texels of decoded glTF images are in the native byte order.
+fn dynamic_image(data: gltf::image::Data) -> Result<DynamicImage, AssetError> {
+    use gltf::image::Format;
+
+    let gltf::image::Data {
+        pixels,
+        format,
+        width,
+        height,
+    } = data;
+    let u16s = |pixels: &[u8]| {
+        pixels
+            .chunks_exact(2)
+            .map(|texel| u16::from_ne_bytes([texel[0], texel[1]]))
+            .collect::<Vec<_>>()
+    };
+    let f32s = |pixels: &[u8]| {
+        pixels
+            .chunks_exact(4)
+            .map(|texel| f32::from_ne_bytes(texel.try_into().unwrap()))
+            .collect::<Vec<_>>()
+    };
+
+    let image = match format {
+        Format::R8 => image::GrayImage::from_raw(width, height, pixels).map(Into::into),
+        Format::R8G8 => image::GrayAlphaImage::from_raw(width, height, pixels).map(Into::into),
+        Format::R8G8B8 => image::RgbImage::from_raw(width, height, pixels).map(Into::into),
+        Format::R8G8B8A8 => image::RgbaImage::from_raw(width, height, pixels).map(Into::into),
+        Format::R16 => {
+            image::ImageBuffer::<image::Luma<u16>, _>::from_raw(width, height, u16s(&pixels))
+                .map(Into::into)
+        }
+        Format::R16G16 => {
+            image::ImageBuffer::<image::LumaA<u16>, _>::from_raw(width, height, u16s(&pixels))
+                .map(Into::into)
+        }
+        Format::R16G16B16 => {
+            image::ImageBuffer::<image::Rgb<u16>, _>::from_raw(width, height, u16s(&pixels))
+                .map(Into::into)
+        }
+        Format::R16G16B16A16 => {
+            image::ImageBuffer::<image::Rgba<u16>, _>::from_raw(width, height, u16s(&pixels))
+                .map(Into::into)
+        }
+        Format::R32G32B32FLOAT => {
+            image::Rgb32FImage::from_raw(width, height, f32s(&pixels)).map(Into::into)
+        }
+        Format::R32G32B32A32FLOAT => {
+            image::Rgba32FImage::from_raw(width, height, f32s(&pixels)).map(Into::into)
+        }
+    };
+
+    image.ok_or_else(|| AssetError::Import("Texels of a glTF image are truncated.".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Asset, Texture, TextureFormat};
+
+    // A red triangle with a texture and a two-node hierarchy, all in the binary chunk.
+    fn triangle_glb() -> Vec<u8> {
+        let mut png = std::io::Cursor::new(Vec::new());
+        DynamicImage::from(image::RgbaImage::from_pixel(
+            2,
+            2,
+            image::Rgba([255, 0, 0, 255]),
+        ))
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+        let png = png.into_inner();
+
+        let mut bin = Vec::new();
+        for position in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            position.iter().for_each(|c| bin.extend(c.to_le_bytes()));
+        }
+        [0u16, 1, 2]
+            .iter()
+            .for_each(|i| bin.extend(i.to_le_bytes()));
+        bin.resize(44, 0);
+        bin.extend(&png);
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        let json = format!(
+            r#"{{"asset":{{"version":"2.0"}},"scene":0,"scenes":[{{"nodes":[0]}}],
+            "nodes":[{{"name":"root","children":[1]}},{{"mesh":0,"translation":[1,2,3]}}],
+            "meshes":[{{"primitives":[{{"attributes":{{"POSITION":0}},"indices":1,"material":0}}]}}],
+            "materials":[{{"name":"red","pbrMetallicRoughness":{{"baseColorFactor":[1,0,0,1],
+            "baseColorTexture":{{"index":0}},"metallicFactor":0.5}}}}],
+            "textures":[{{"source":0}}],"images":[{{"bufferView":2,"mimeType":"image/png"}}],
+            "accessors":[{{"bufferView":0,"componentType":5126,"count":3,"type":"VEC3",
+            "min":[0,0,0],"max":[1,1,0]}},{{"bufferView":1,"componentType":5123,"count":3,"type":"SCALAR"}}],
+            "bufferViews":[{{"buffer":0,"byteOffset":0,"byteLength":36}},
+            {{"buffer":0,"byteOffset":36,"byteLength":6}},
+            {{"buffer":0,"byteOffset":44,"byteLength":{}}}],
+            "buffers":[{{"byteLength":{}}}]}}"#,
+            png.len(),
+            bin.len()
+        );
+        let mut json = json.into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+
+        let mut glb = Vec::new();
+        glb.extend(b"glTF");
+        glb.extend(2u32.to_le_bytes());
+        glb.extend(((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(b"JSON");
+        glb.extend(json);
+        glb.extend((bin.len() as u32).to_le_bytes());
+        glb.extend(b"BIN\0");
+        glb.extend(bin);
+
+        glb
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn import_gltf_binary() {
+        let mut assets = GltfImporter::new()
+            .import_from_memory(&triangle_glb(), "triangle", "assets")
+            .unwrap();
+        assert_eq!(assets.iter().count(), 4);
+        assert_eq!(assets.scene.path(), "assets/triangle.bin");
+
+        let texture_guid = assets.textures[0].guid();
+        let texture = Texture::from_asset_file(assets.textures.remove(0)).unwrap();
+        assert_eq!(texture.metadata.texture_format, TextureFormat::RGBA8Srgb);
+
+        let material_guid = assets.materials[0].guid();
+        assert_eq!(assets.materials[0].name(), "triangle_material_0_red");
+        assert_eq!(assets.materials[0].dependencies(), [texture_guid]);
+        let material = MaterialAsset::from_asset_file(assets.materials.remove(0)).unwrap();
+        assert_eq!(material.base_color_factor, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(material.base_color_texture, Some(texture_guid));
+        assert_eq!(material.metallic_factor, 0.5);
+
+        let mesh_guid = assets.meshes[0].guid();
+        assert_eq!(assets.meshes[0].dependencies(), [material_guid]);
+        let mesh = Mesh::from_asset_file(assets.meshes.remove(0)).unwrap();
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert_eq!(mesh.metadata.submeshes[0].material, Some(material_guid));
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|vertex| vertex.normal == [0.0, 0.0, 1.0]));
+
+        assert_eq!(assets.scene.dependencies(), [mesh_guid]);
+        let scene = SceneAsset::from_asset_file(assets.scene).unwrap();
+        assert_eq!(scene.roots, [0]);
+        assert_eq!(scene.nodes[0].name, "root");
+        assert_eq!(scene.nodes[0].children, [1]);
+        assert_eq!(scene.nodes[1].translation, [1.0, 2.0, 3.0]);
+        assert_eq!(scene.nodes[1].mesh, Some(mesh_guid));
+    }
+}
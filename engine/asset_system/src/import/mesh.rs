@@ -0,0 +1,356 @@
+use crate::{AssetError, Lod, Mesh, Meshlet, Vertex};
+use meshopt::{DecodePosition, SimplifyOptions, VertexDataAdapter};
+use serde::{Deserialize, Serialize};
+
+// Limits of meshoptimizer for the size of meshlets.
+const MAX_MESHLET_VERTICES: u32 = 256;
+const MAX_MESHLET_TRIANGLES: u32 = 512;
+
+// Axis pointing up in the source, meshes are converted to the Y up convention of the engine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    // Right-handed with Z up, as in Blender or 3ds Max.
+    Z,
+}
+
+// Optional processing of imported meshes before they are packed. Nothing is done by default.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MeshProcessing {
+    // Applied to the vertices before anything else, e.g. 0.01 for sources in centimeters.
+    pub scale: f32,
+    pub up_axis: UpAxis,
+    // Reorders triangles for the post-transform vertex cache and overdraw, then vertices for
+    // fetching in the order they're used.
+    pub optimize: bool,
+    // How much worse the vertex cache may get to reduce overdraw, 1.05 allows 5%.
+    pub overdraw_threshold: f32,
+    // Number of simplified levels below the full detail one, each aiming for `lod_ratio` of
+    // the indices of the previous level. The chain stops early once a level doesn't shrink.
+    pub lod_count: u32,
+    pub lod_ratio: f32,
+    // Largest deviation a level may have, relative to the extent of the mesh.
+    pub lod_target_error: f32,
+    // Splits the full detail submeshes into meshlets with bounding spheres and normal cones.
+    pub build_meshlets: bool,
+    pub max_meshlet_vertices: u32,
+    // Has to be divisible by 4.
+    pub max_meshlet_triangles: u32,
+    // Between 0 and 1, higher values give tighter normal cones at the cost of bigger bounds.
+    pub cone_weight: f32,
+}
+
+impl Default for MeshProcessing {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            up_axis: UpAxis::Y,
+            optimize: false,
+            overdraw_threshold: 1.05,
+            lod_count: 0,
+            lod_ratio: 0.5,
+            lod_target_error: 0.01,
+            build_meshlets: false,
+            max_meshlet_vertices: 64,
+            max_meshlet_triangles: 124,
+            cone_weight: 0.25,
+        }
+    }
+}
+
+impl DecodePosition for Vertex {
+    fn decode_position(&self) -> [f32; 3] {
+        self.position
+    }
+}
+
+impl MeshProcessing {
+    // The indices of the result hold the full detail submeshes in their order followed by the
+    // levels of detail. Existing levels and meshlets of `mesh` are replaced.
+    pub fn process(&self, mut mesh: Mesh) -> Result<Mesh, AssetError> {
+        self.validate()?;
+        self.transform(&mut mesh.vertices);
+
+        let mut indices = Vec::with_capacity(mesh.indices.len());
+        for submesh in &mut mesh.metadata.submeshes {
+            let range = submesh.index_offset as usize
+                ..(submesh.index_offset + submesh.index_count) as usize;
+            let mut submesh_indices =
+                mesh.indices
+                    .get(range)
+                    .map(<[u32]>::to_vec)
+                    .ok_or_else(|| {
+                        AssetError::Import(
+                            "A submesh is out of the bounds of the indices.".to_string(),
+                        )
+                    })?;
+            if self.optimize {
+                submesh_indices =
+                    meshopt::optimize_vertex_cache(&submesh_indices, mesh.vertices.len());
+                meshopt::optimize_overdraw_in_place_decoder(
+                    &mut submesh_indices,
+                    &mesh.vertices,
+                    self.overdraw_threshold,
+                );
+            }
+
+            submesh.index_offset = indices.len() as u32;
+            submesh.lods.clear();
+            indices.extend(submesh_indices);
+        }
+
+        // Errors of levels are relative to the extent of the mesh.
+        let scale = match self.lod_count {
+            0 => 1.0,
+            _ => meshopt::simplify_scale_decoder(&mesh.vertices),
+        };
+        for submesh in &mut mesh.metadata.submeshes {
+            let base = submesh.index_offset as usize
+                ..(submesh.index_offset + submesh.index_count) as usize;
+            let mut previous_count = submesh.index_count as usize;
+
+            for _ in 0..self.lod_count {
+                let target_count = (previous_count as f32 * self.lod_ratio) as usize / 3 * 3;
+                let mut error = 0.0;
+                let mut lod_indices = meshopt::simplify_decoder(
+                    &indices[base.clone()],
+                    &mesh.vertices,
+                    target_count,
+                    self.lod_target_error,
+                    SimplifyOptions::None,
+                    Some(&mut error),
+                );
+                if lod_indices.is_empty() || lod_indices.len() >= previous_count {
+                    break;
+                }
+                if self.optimize {
+                    lod_indices = meshopt::optimize_vertex_cache(&lod_indices, mesh.vertices.len());
+                }
+
+                previous_count = lod_indices.len();
+                submesh.lods.push(Lod {
+                    index_offset: indices.len() as u32,
+                    index_count: lod_indices.len() as u32,
+                    error: error * scale,
+                });
+                indices.extend(lod_indices);
+            }
+        }
+
+        if self.optimize {
+            mesh.vertices = meshopt::optimize_vertex_fetch(&mut indices, &mesh.vertices);
+        }
+        mesh.indices = indices;
+
+        mesh.meshlets.clear();
+        mesh.meshlet_vertices.clear();
+        mesh.meshlet_triangles.clear();
+        if self.build_meshlets {
+            self.build_meshlets(&mut mesh)?;
+        }
+
+        mesh.update_metadata();
+
+        Ok(mesh)
+    }
+
+    fn transform(&self, vertices: &mut [Vertex]) {
+        if self.scale == 1.0 && self.up_axis == UpAxis::Y {
+            return;
+        }
+
+        // Z up is rotated by -90 degrees around X, which keeps the handedness of tangents.
+        let convert = |[x, y, z]: [f32; 3]| match self.up_axis {
+            UpAxis::Y => [x, y, z],
+            UpAxis::Z => [x, z, -y],
+        };
+        for vertex in vertices {
+            vertex.position = convert(vertex.position).map(|value| value * self.scale);
+            vertex.normal = convert(vertex.normal);
+            let [x, y, z, w] = vertex.tangent;
+            let [x, y, z] = convert([x, y, z]);
+            vertex.tangent = [x, y, z, w];
+        }
+    }
+
+    fn validate(&self) -> Result<(), AssetError> {
+        if !(self.scale > 0.0 && self.scale.is_finite()) {
+            return Err(AssetError::Import(
+                "The scale of meshes has to be positive.".to_string(),
+            ));
+        }
+        if self.build_meshlets
+            && (!(3..=MAX_MESHLET_VERTICES).contains(&self.max_meshlet_vertices)
+                || !(4..=MAX_MESHLET_TRIANGLES).contains(&self.max_meshlet_triangles)
+                || !self.max_meshlet_triangles.is_multiple_of(4))
+        {
+            return Err(AssetError::Import(format!(
+                "Meshlets are limited to {MAX_MESHLET_VERTICES} vertices and \
+                 {MAX_MESHLET_TRIANGLES} triangles, a multiple of 4."
+            )));
+        }
+        if self.lod_count > 0 && !(self.lod_ratio > 0.0 && self.lod_ratio < 1.0) {
+            return Err(AssetError::Import(
+                "The LOD ratio has to be between 0 and 1.".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn build_meshlets(&self, mesh: &mut Mesh) -> Result<(), AssetError> {
+        let positions = mesh
+            .vertices
+            .iter()
+            .map(|vertex| vertex.position)
+            .collect::<Vec<_>>();
+        let adapter = VertexDataAdapter::new(
+            meshopt::typed_to_bytes(&positions),
+            std::mem::size_of::<[f32; 3]>(),
+            0,
+        )
+        .map_err(|error| AssetError::Import(error.to_string()))?;
+
+        for submesh in &mut mesh.metadata.submeshes {
+            let range = submesh.index_offset as usize
+                ..(submesh.index_offset + submesh.index_count) as usize;
+            let meshlets = meshopt::build_meshlets(
+                &mesh.indices[range],
+                &adapter,
+                self.max_meshlet_vertices as usize,
+                self.max_meshlet_triangles as usize,
+                self.cone_weight,
+            );
+
+            submesh.meshlet_offset = mesh.meshlets.len() as u32;
+            submesh.meshlet_count = meshlets.len() as u32;
+            for meshlet in meshlets.iter() {
+                let bounds = meshopt::compute_meshlet_bounds_decoder(meshlet, &mesh.vertices);
+                mesh.meshlets.push(Meshlet {
+                    vertex_offset: mesh.meshlet_vertices.len() as u32,
+                    vertex_count: meshlet.vertices.len() as u32,
+                    triangle_offset: (mesh.meshlet_triangles.len() / 3) as u32,
+                    triangle_count: (meshlet.triangles.len() / 3) as u32,
+                    center: bounds.center,
+                    radius: bounds.radius,
+                    cone_axis: bounds.cone_axis,
+                    cone_cutoff: bounds.cone_cutoff,
+                });
+                mesh.meshlet_vertices.extend(meshlet.vertices);
+                mesh.meshlet_triangles.extend(meshlet.triangles);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Asset, CompressionMode};
+
+    // A flat grid of `size` by `size` quads with a wave, so simplification has to keep some
+    // triangles.
+    fn grid(size: u32) -> Mesh {
+        let mut vertices = Vec::new();
+        for y in 0..=size {
+            for x in 0..=size {
+                let height = (x as f32 * 0.5).sin() * 0.2;
+                vertices.push(Vertex {
+                    position: [x as f32, height, y as f32],
+                    ..Default::default()
+                });
+            }
+        }
+        let mut indices = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                let corner = y * (size + 1) + x;
+                indices.extend([corner, corner + size + 1, corner + 1]);
+                indices.extend([corner + 1, corner + size + 1, corner + size + 2]);
+            }
+        }
+
+        Mesh::new(vertices, indices, Vec::new())
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn build_lods_and_meshlets() {
+        let mesh = grid(32);
+        let triangle_count = mesh.indices.len() / 3;
+        let processing = MeshProcessing {
+            optimize: true,
+            lod_count: 3,
+            lod_target_error: 0.1,
+            build_meshlets: true,
+            ..Default::default()
+        };
+        let mesh = processing.process(mesh).unwrap();
+
+        let submesh = &mesh.metadata.submeshes[0];
+        assert_eq!(submesh.index_count as usize, triangle_count * 3);
+        assert!(!submesh.lods.is_empty());
+        let mut previous_count = submesh.index_count;
+        for lod in &submesh.lods {
+            assert!(lod.index_count < previous_count);
+            assert!(lod.error >= 0.0);
+            previous_count = lod.index_count;
+        }
+        let last_lod = submesh.lods.last().unwrap();
+        assert_eq!(
+            mesh.indices.len(),
+            (last_lod.index_offset + last_lod.index_count) as usize
+        );
+
+        // Every triangle ends up in exactly one meshlet.
+        let meshlets =
+            &mesh.meshlets[submesh.meshlet_offset as usize..][..submesh.meshlet_count as usize];
+        let meshlet_triangles = meshlets
+            .iter()
+            .map(|meshlet| meshlet.triangle_count as usize)
+            .sum::<usize>();
+        assert_eq!(meshlet_triangles, triangle_count);
+        for meshlet in meshlets {
+            assert!(meshlet.vertex_count <= processing.max_meshlet_vertices);
+            assert!(meshlet.triangle_count <= processing.max_meshlet_triangles);
+            assert!(meshlet.radius > 0.0);
+        }
+
+        let asset_file = mesh
+            .clone()
+            .into_asset_file("grid", "grid.bin", CompressionMode::Default)
+            .unwrap();
+        let unpacked = Mesh::from_asset_file(asset_file).unwrap();
+        assert_eq!(unpacked.metadata, mesh.metadata);
+        assert_eq!(unpacked.indices, mesh.indices);
+        assert_eq!(unpacked.meshlets, mesh.meshlets);
+        assert_eq!(unpacked.meshlet_vertices, mesh.meshlet_vertices);
+        assert_eq!(unpacked.meshlet_triangles, mesh.meshlet_triangles);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn convert_z_up_meshes() {
+        let processing = MeshProcessing {
+            scale: 0.5,
+            up_axis: UpAxis::Z,
+            ..Default::default()
+        };
+        let mesh = processing.process(grid(1)).unwrap();
+        assert_eq!(mesh.vertices[2].position, [0.0, 0.5, 0.0]);
+        assert_eq!(mesh.metadata.bounds.max[1], 0.5);
+    }
+
+    #[test]
+    fn reject_oversized_meshlets() {
+        let processing = MeshProcessing {
+            build_meshlets: true,
+            max_meshlet_triangles: 126,
+            ..Default::default()
+        };
+        assert!(processing.process(grid(1)).is_err());
+    }
+}
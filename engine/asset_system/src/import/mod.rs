@@ -0,0 +1,17 @@
+// Importers turn source files authored in external tools into packed asset files.
+mod gltf;
+mod mesh;
+mod obj;
+mod settings;
+mod shader;
+mod texture;
+
+pub use gltf::{GltfAssets, GltfImporter, GLTF_SOURCE_EXTENSIONS};
+pub use mesh::{MeshProcessing, UpAxis};
+pub use obj::{ObjAssets, ObjImporter, OBJ_SOURCE_EXTENSIONS};
+pub use settings::{
+    ImportSettings, MeshSettings, TextureSettings, FOLDER_IMPORT_SETTINGS_FILE_NAME,
+    IMPORT_SETTINGS_EXTENSION,
+};
+pub use shader::{ShaderCompiler, ShaderSource, GLSL_SOURCE_EXTENSIONS};
+pub use texture::{BlockCompression, TextureImporter, TextureUsage, TEXTURE_SOURCE_EXTENSIONS};
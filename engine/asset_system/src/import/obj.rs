@@ -0,0 +1,442 @@
+use super::{MeshProcessing, TextureImporter, TextureUsage};
+use crate::{
+    AlphaMode, AssetError, AssetFile, AssetGuid, CompressionMode, MaterialAsset, Mesh, Submesh,
+    Vertex, ASSET_FILE_EXTENSION,
+};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+pub const OBJ_SOURCE_EXTENSIONS: &[&str] = &["obj"];
+
+// Asset files produced from a Wavefront OBJ file, the mesh depends on the materials and the
+// materials on the textures.
+#[derive(Debug)]
+pub struct ObjAssets {
+    pub textures: Vec<AssetFile>,
+    pub materials: Vec<AssetFile>,
+    pub mesh: AssetFile,
+    // Files read besides the OBJ file, its MTL libraries and textures.
+    pub files: Vec<PathBuf>,
+}
+
+impl ObjAssets {
+    pub fn iter(&self) -> impl Iterator<Item = &AssetFile> {
+        self.textures
+            .iter()
+            .chain(&self.materials)
+            .chain(std::iter::once(&self.mesh))
+    }
+
+    pub fn save(&self) -> Result<(), AssetError> {
+        self.iter().try_for_each(AssetFile::save_asset_file)
+    }
+}
+
+// Reads OBJ files with their MTL libraries into one mesh asset with a submesh per material.
+// Faces are triangulated, vertices with the same position, normal and texture coordinates are
+// shared and missing normals are computed. Asset files are placed into `directory`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObjImporter {
+    pub compression_mode: CompressionMode,
+    // Settings of the textures referenced by the MTL materials.
+    pub texture_importer: TextureImporter,
+    pub mesh_processing: MeshProcessing,
+}
+
+impl Default for ObjImporter {
+    fn default() -> Self {
+        Self {
+            compression_mode: CompressionMode::Default,
+            texture_importer: TextureImporter::default(),
+            mesh_processing: MeshProcessing::default(),
+        }
+    }
+}
+
+impl ObjImporter {
+    // Bumped whenever the output for the same source and settings changes.
+    pub const VERSION: u32 = 2;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // MTL libraries and textures are resolved relative to the OBJ file.
+    pub fn import<T: AsRef<Path> + ?Sized>(
+        &self,
+        source: &T,
+        name: &str,
+        directory: &str,
+    ) -> Result<ObjAssets, AssetError> {
+        let source = source.as_ref();
+        let base = source.parent();
+        let libraries = RefCell::new(Vec::new());
+        let mut obj = BufReader::new(std::fs::File::open(source)?);
+        let (models, materials) = tobj::load_obj_buf(&mut obj, &load_options(), |statement| {
+            load_libraries(statement, base, &mut libraries.borrow_mut())
+        })?;
+        let mut assets = self.import_models(models, materials?, base, name, directory)?;
+        assets.files.splice(0..0, libraries.into_inner());
+
+        Ok(assets)
+    }
+
+    // Every MTL library the OBJ file refers to is read from `mtl`, textures are resolved
+    // relative to the working directory.
+    pub fn import_from_memory(
+        &self,
+        obj: &[u8],
+        mtl: Option<&[u8]>,
+        name: &str,
+        directory: &str,
+    ) -> Result<ObjAssets, AssetError> {
+        let (models, materials) =
+            tobj::load_obj_buf(&mut &obj[..], &load_options(), |_| match mtl {
+                Some(mut mtl) => tobj::load_mtl_buf(&mut mtl),
+                None => Err(tobj::LoadError::OpenFileFailed),
+            })?;
+
+        self.import_models(models, materials?, None, name, directory)
+    }
+
+    fn import_models(
+        &self,
+        models: Vec<tobj::Model>,
+        materials: Vec<tobj::Material>,
+        base: Option<&Path>,
+        name: &str,
+        directory: &str,
+    ) -> Result<ObjAssets, AssetError> {
+        let asset_path =
+            |asset_name: &str| format!("{directory}/{asset_name}.{ASSET_FILE_EXTENSION}");
+
+        // Textures shared by several materials are imported once.
+        let mut textures = Vec::new();
+        let mut texture_indices = HashMap::<PathBuf, usize>::new();
+        let mut files = Vec::new();
+        let mut import_texture = |texture: &Option<String>, usage: TextureUsage| {
+            let Some(texture) = texture else {
+                return Ok(None);
+            };
+            let texture_path = match base {
+                Some(base) => base.join(texture),
+                None => PathBuf::from(texture),
+            };
+            if let Some(&index) = texture_indices.get(&texture_path) {
+                return Ok(Some(index));
+            }
+            files.push(texture_path.clone());
+
+            let importer = TextureImporter {
+                usage,
+                compression_mode: self.compression_mode,
+                ..self.texture_importer.clone()
+            };
+            let stem = texture_path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+            let texture_name = format!("{name}_texture_{}_{stem}", textures.len());
+            textures.push(importer.import(
+                &texture_path,
+                &texture_name,
+                &asset_path(&texture_name),
+            )?);
+            texture_indices.insert(texture_path, textures.len() - 1);
+
+            Ok::<_, AssetError>(Some(textures.len() - 1))
+        };
+
+        let mut material_assets = Vec::new();
+        for material in &materials {
+            let base_color_texture =
+                import_texture(&material.diffuse_texture, TextureUsage::Albedo)?;
+            let normal_texture = import_texture(&material.normal_texture, TextureUsage::NormalMap)?;
+            material_assets.push((material, base_color_texture, normal_texture));
+        }
+
+        let mut material_files = Vec::new();
+        for (index, (material, base_color_texture, normal_texture)) in
+            material_assets.into_iter().enumerate()
+        {
+            let material_asset = MaterialAsset {
+                base_color_texture: base_color_texture.map(|index| textures[index].guid()),
+                normal_texture: normal_texture.map(|index| textures[index].guid()),
+                ..convert_material(material)
+            };
+            let material_name = match material.name.is_empty() {
+                true => format!("{name}_material_{index}"),
+                false => format!("{name}_material_{index}_{}", material.name),
+            };
+
+            let material_path = asset_path(&material_name);
+
+            material_files.push(
+                material_asset
+                    .into_asset_file(&material_name, &material_path, self.compression_mode)?
+                    .with_guid(AssetGuid::from_name(&material_path)),
+            );
+        }
+
+        let mesh = self
+            .mesh_processing
+            .process(merge_models(models, &material_files)?)?;
+        let mesh_path = asset_path(name);
+        let mesh = mesh
+            .into_asset_file(name, &mesh_path, self.compression_mode)?
+            .with_guid(AssetGuid::from_name(&mesh_path));
+
+        Ok(ObjAssets {
+            textures,
+            materials: material_files,
+            mesh,
+            files,
+        })
+    }
+}
+
+fn load_options() -> tobj::LoadOptions {
+    tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    }
+}
+
+// PBR parameters approximated from the Phong ones, unless the PBR extension of MTL is used.
+fn convert_material(material: &tobj::Material) -> MaterialAsset {
+    let pbr_parameter = |key: &str| {
+        material
+            .unknown_param
+            .get(key)
+            .and_then(|value| value.trim().parse::<f32>().ok())
+    };
+    let [r, g, b] = material.diffuse.unwrap_or([1.0; 3]);
+    let dissolve = material.dissolve.unwrap_or(1.0);
+    let roughness = pbr_parameter("Pr").unwrap_or_else(|| match material.shininess {
+        Some(shininess) => (2.0 / (shininess.max(0.0) + 2.0)).sqrt(),
+        None => 1.0,
+    });
+
+    MaterialAsset {
+        base_color_factor: [r, g, b, dissolve],
+        metallic_factor: pbr_parameter("Pm").unwrap_or(0.0),
+        roughness_factor: roughness,
+        emissive_factor: material.emissive.unwrap_or([0.0; 3]),
+        alpha_mode: match dissolve < 1.0 {
+            true => AlphaMode::Blend,
+            false => AlphaMode::Opaque,
+        },
+        ..Default::default()
+    }
+    .with_pbr_render_state()
+}
+
+// Models using the same material are drawn by one submesh.
+fn merge_models(models: Vec<tobj::Model>, materials: &[AssetFile]) -> Result<Mesh, AssetError> {
+    let mut groups = Vec::<(Option<usize>, Vec<tobj::Mesh>)>::new();
+    for model in models {
+        let material_id = model.mesh.material_id;
+        match groups.iter_mut().find(|(id, _)| *id == material_id) {
+            Some((_, meshes)) => meshes.push(model.mesh),
+            None => groups.push((material_id, vec![model.mesh])),
+        }
+    }
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut submeshes = Vec::new();
+    for (material_id, meshes) in groups {
+        let material = match material_id {
+            Some(id) => Some(
+                materials
+                    .get(id)
+                    .ok_or_else(|| AssetError::Import(format!("Unknown material {id}.")))?
+                    .guid(),
+            ),
+            None => None,
+        };
+        let index_offset = indices.len() as u32;
+
+        for mesh in meshes {
+            let mut model_vertices = mesh
+                .positions
+                .chunks_exact(3)
+                .map(|position| Vertex {
+                    position: [position[0], position[1], position[2]],
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>();
+            for (vertex, normal) in model_vertices.iter_mut().zip(mesh.normals.chunks_exact(3)) {
+                vertex.normal = [normal[0], normal[1], normal[2]];
+            }
+            // OBJ puts the origin of texture coordinates at the bottom.
+            for (vertex, uv) in model_vertices
+                .iter_mut()
+                .zip(mesh.texcoords.chunks_exact(2))
+            {
+                vertex.uv = [uv[0], 1.0 - uv[1]];
+            }
+
+            let mut model_mesh = Mesh::new(model_vertices, mesh.indices, Vec::new());
+            if mesh.normals.is_empty() {
+                model_mesh.compute_normals();
+            }
+            model_mesh.compute_tangents();
+
+            let vertex_offset = vertices.len() as u32;
+            vertices.extend(model_mesh.vertices);
+            indices.extend(model_mesh.indices.iter().map(|index| index + vertex_offset));
+        }
+
+        submeshes.push(Submesh {
+            lods: Vec::new(),
+            meshlet_offset: 0,
+            meshlet_count: 0,
+            index_offset,
+            index_count: indices.len() as u32 - index_offset,
+            material,
+        });
+    }
+
+    Ok(Mesh::new(vertices, indices, submeshes))
+}
+
+// tobj passes everything after `mtllib` as one path, but a statement may list several libraries.
+// A name with spaces is kept if such a file exists. Libraries are relative to `base` and are
+// added to `libraries`.
+fn load_libraries(
+    statement: &Path,
+    base: Option<&Path>,
+    libraries: &mut Vec<PathBuf>,
+) -> tobj::MTLLoadResult {
+    let resolve =
+        |library: &Path| base.map_or_else(|| library.to_path_buf(), |base| base.join(library));
+    let paths = match resolve(statement) {
+        path if path.is_file() => vec![path],
+        _ => statement
+            .to_string_lossy()
+            .split_whitespace()
+            .map(|library| resolve(Path::new(library)))
+            .collect(),
+    };
+    libraries.extend(paths.iter().cloned());
+
+    let mut loaded = paths.iter().map(tobj::load_mtl);
+    let (mut materials, mut names) = loaded.next().unwrap_or_else(|| Ok(Default::default()))?;
+    for result in loaded {
+        let (library_materials, library_names) = result?;
+        let offset = materials.len();
+        for (name, index) in library_names {
+            names.entry(name).or_insert(offset + index);
+        }
+        materials.extend(library_materials);
+    }
+
+    Ok((materials, names))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Asset;
+
+    const OBJ: &str = "mtllib materials.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+o quad
+usemtl red
+f 1/1 2/2 3/3 4/4
+o triangle
+usemtl glass
+f 1/1 5/2 2/3
+";
+
+    const MTL: &str = "newmtl red
+Kd 1 0 0
+Ns 198
+newmtl glass
+Kd 1 1 1
+d 0.25
+Pm 1
+";
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn import_obj_with_materials() {
+        let assets = ObjImporter::new()
+            .import_from_memory(OBJ.as_bytes(), Some(MTL.as_bytes()), "shapes", "assets")
+            .unwrap();
+        assert!(assets.textures.is_empty());
+        assert_eq!(assets.materials.len(), 2);
+        assert_eq!(assets.mesh.path(), "assets/shapes.bin");
+
+        let material_guids = assets
+            .materials
+            .iter()
+            .map(AssetFile::guid)
+            .collect::<Vec<_>>();
+        assert_eq!(assets.mesh.dependencies(), material_guids);
+        let mut materials = assets.materials.into_iter();
+
+        let red = MaterialAsset::from_asset_file(materials.next().unwrap()).unwrap();
+        assert_eq!(red.base_color_factor, [1.0, 0.0, 0.0, 1.0]);
+        assert!((red.roughness_factor - 0.1).abs() < 1e-6);
+        assert_eq!(red.alpha_mode, AlphaMode::Opaque);
+
+        let glass = MaterialAsset::from_asset_file(materials.next().unwrap()).unwrap();
+        assert_eq!(glass.base_color_factor[3], 0.25);
+        assert_eq!(glass.alpha_mode, AlphaMode::Blend);
+        assert_eq!(glass.render_state.blend_mode, crate::BlendMode::Alpha);
+        assert_eq!(glass.metallic_factor, 1.0);
+
+        // The quad shares two of its four vertices between the triangles.
+        let mesh = Mesh::from_asset_file(assets.mesh).unwrap();
+        assert_eq!(mesh.vertices.len(), 4 + 3);
+        assert_eq!(mesh.indices.len(), 6 + 3);
+        assert_eq!(mesh.metadata.submeshes.len(), 2);
+        assert_eq!(mesh.metadata.submeshes[0].index_count, 6);
+        assert_eq!(mesh.metadata.submeshes[1].material, Some(material_guids[1]));
+        assert_eq!(mesh.vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(mesh.vertices[0].uv, [0.0, 1.0]);
+        assert_eq!(mesh.vertices[4].normal, [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn import_obj_with_several_libraries() {
+        const SOURCE_DIRECTORY: &str = "src/test_asset_files/obj_libraries";
+
+        let directory = Path::new(SOURCE_DIRECTORY);
+        std::fs::create_dir_all(directory).unwrap();
+        let (red, glass) = MTL.split_at(MTL.find("newmtl glass").unwrap());
+        std::fs::write(directory.join("red.mtl"), red).unwrap();
+        std::fs::write(directory.join("glass.mtl"), glass).unwrap();
+        let obj = OBJ.replace("mtllib materials.mtl", "mtllib red.mtl  glass.mtl");
+        std::fs::write(directory.join("shapes.obj"), obj).unwrap();
+
+        let assets = ObjImporter::new().import(&directory.join("shapes.obj"), "shapes", "assets");
+        std::fs::remove_dir_all(directory).unwrap();
+
+        let assets = assets.unwrap();
+        assert_eq!(
+            assets.files,
+            [directory.join("red.mtl"), directory.join("glass.mtl")]
+        );
+        let alpha_modes = assets
+            .materials
+            .into_iter()
+            .map(|material| MaterialAsset::from_asset_file(material).unwrap().alpha_mode)
+            .collect::<Vec<_>>();
+        assert_eq!(alpha_modes, [AlphaMode::Opaque, AlphaMode::Blend]);
+    }
+}
@@ -0,0 +1,228 @@
+use super::{
+    BlockCompression, GltfImporter, MeshProcessing, ObjImporter, TextureImporter, TextureUsage,
+    UpAxis,
+};
+use crate::{AssetError, ColorSpace, CompressionMode, TextureFormat};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Settings of a source are stored next to it in `<source file name>.import.ron`.
+pub const IMPORT_SETTINGS_EXTENSION: &str = "import.ron";
+// Defaults for the sources of a folder and its subfolders.
+pub const FOLDER_IMPORT_SETTINGS_FILE_NAME: &str = "import_defaults.ron";
+
+// How a source is imported. Missing values are taken from the settings of the enclosing
+// folders, then from the importer. Files may start with `#![enable(implicit_some)]` to leave
+// out the `Some` around values.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportSettings {
+    pub compression_mode: Option<CompressionMode>,
+    pub texture: TextureSettings,
+    pub mesh: MeshSettings,
+}
+
+// Used for texture sources and for the textures of glTF and OBJ sources, whose usage is
+// decided by the material slots.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextureSettings {
+    pub usage: Option<TextureUsage>,
+    // sRGB or linear.
+    pub color_space: Option<ColorSpace>,
+    pub generate_mipmaps: Option<bool>,
+    pub block_compression: Option<BlockCompression>,
+    pub hdr_format: Option<TextureFormat>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeshSettings {
+    pub scale: Option<f32>,
+    pub up_axis: Option<UpAxis>,
+    pub optimize: Option<bool>,
+    pub lod_count: Option<u32>,
+    pub lod_ratio: Option<f32>,
+    pub lod_target_error: Option<f32>,
+    pub build_meshlets: Option<bool>,
+}
+
+impl ImportSettings {
+    pub fn sidecar_path<T: AsRef<Path> + ?Sized>(source: &T) -> PathBuf {
+        let mut file_name = source.as_ref().as_os_str().to_owned();
+        file_name.push(format!(".{IMPORT_SETTINGS_EXTENSION}"));
+
+        PathBuf::from(file_name)
+    }
+
+    // `None` if there is no such file.
+    pub fn load<T: AsRef<Path> + ?Sized>(path: &T) -> Result<Option<Self>, AssetError> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(Some(ron::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AssetError::Io(e)),
+        }
+    }
+
+    pub fn save<T: AsRef<Path> + ?Sized>(&self, path: &T) -> Result<(), AssetError> {
+        let serialized = ron::ser::to_string_pretty(self, Default::default())?;
+        std::fs::write(path, serialized)?;
+
+        Ok(())
+    }
+
+    // Combines the folder defaults from `root` down to the folder of the source with the
+    // sidecar of the source. Only the folder of the source is searched when it's outside of
+    // `root`.
+    pub fn for_source<S, R>(source: &S, root: &R) -> Result<Self, AssetError>
+    where
+        S: AsRef<Path> + ?Sized,
+        R: AsRef<Path> + ?Sized,
+    {
+        let source = source.as_ref();
+        let folder = source.parent().unwrap_or(Path::new(""));
+        let mut folders = match folder.strip_prefix(root) {
+            Ok(_) => folder
+                .ancestors()
+                .take_while(|ancestor| ancestor.starts_with(root))
+                .collect::<Vec<_>>(),
+            Err(_) => vec![folder],
+        };
+        folders.reverse();
+
+        let mut settings = Self::default();
+        for folder in folders {
+            if let Some(defaults) = Self::load(&folder.join(FOLDER_IMPORT_SETTINGS_FILE_NAME))? {
+                settings = settings.merge(defaults);
+            }
+        }
+        match Self::load(&Self::sidecar_path(source))? {
+            Some(own) => Ok(settings.merge(own)),
+            None => Ok(settings),
+        }
+    }
+
+    // Values set in `overrides` win.
+    pub fn merge(self, overrides: Self) -> Self {
+        let texture = overrides.texture;
+        let mesh = overrides.mesh;
+
+        Self {
+            compression_mode: overrides.compression_mode.or(self.compression_mode),
+            texture: TextureSettings {
+                usage: texture.usage.or(self.texture.usage),
+                color_space: texture.color_space.or(self.texture.color_space),
+                generate_mipmaps: texture.generate_mipmaps.or(self.texture.generate_mipmaps),
+                block_compression: texture.block_compression.or(self.texture.block_compression),
+                hdr_format: texture.hdr_format.or(self.texture.hdr_format),
+            },
+            mesh: MeshSettings {
+                scale: mesh.scale.or(self.mesh.scale),
+                up_axis: mesh.up_axis.or(self.mesh.up_axis),
+                optimize: mesh.optimize.or(self.mesh.optimize),
+                lod_count: mesh.lod_count.or(self.mesh.lod_count),
+                lod_ratio: mesh.lod_ratio.or(self.mesh.lod_ratio),
+                lod_target_error: mesh.lod_target_error.or(self.mesh.lod_target_error),
+                build_meshlets: mesh.build_meshlets.or(self.mesh.build_meshlets),
+            },
+        }
+    }
+
+    pub fn texture_importer(&self, importer: &TextureImporter) -> TextureImporter {
+        let texture = &self.texture;
+
+        TextureImporter {
+            compression_mode: self.compression_mode.unwrap_or(importer.compression_mode),
+            usage: texture.usage.unwrap_or(importer.usage),
+            block_compression: texture
+                .block_compression
+                .unwrap_or(importer.block_compression),
+            generate_mipmaps: texture
+                .generate_mipmaps
+                .unwrap_or(importer.generate_mipmaps),
+            color_space: texture.color_space.or(importer.color_space),
+            hdr_format: texture.hdr_format.unwrap_or(importer.hdr_format),
+        }
+    }
+
+    pub fn mesh_processing(&self, processing: &MeshProcessing) -> MeshProcessing {
+        let mesh = &self.mesh;
+
+        MeshProcessing {
+            scale: mesh.scale.unwrap_or(processing.scale),
+            up_axis: mesh.up_axis.unwrap_or(processing.up_axis),
+            optimize: mesh.optimize.unwrap_or(processing.optimize),
+            lod_count: mesh.lod_count.unwrap_or(processing.lod_count),
+            lod_ratio: mesh.lod_ratio.unwrap_or(processing.lod_ratio),
+            lod_target_error: mesh.lod_target_error.unwrap_or(processing.lod_target_error),
+            build_meshlets: mesh.build_meshlets.unwrap_or(processing.build_meshlets),
+            ..processing.clone()
+        }
+    }
+
+    pub fn gltf_importer(&self, importer: &GltfImporter) -> GltfImporter {
+        GltfImporter {
+            compression_mode: self.compression_mode.unwrap_or(importer.compression_mode),
+            texture_importer: self.texture_importer(&importer.texture_importer),
+            mesh_processing: self.mesh_processing(&importer.mesh_processing),
+        }
+    }
+
+    pub fn obj_importer(&self, importer: &ObjImporter) -> ObjImporter {
+        ObjImporter {
+            compression_mode: self.compression_mode.unwrap_or(importer.compression_mode),
+            texture_importer: self.texture_importer(&importer.texture_importer),
+            mesh_processing: self.mesh_processing(&importer.mesh_processing),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn merge_folder_defaults_with_sidecars() {
+        let root = Path::new("src/test_asset_files/import_settings");
+        let folder = root.join("textures/normals");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(
+            root.join(FOLDER_IMPORT_SETTINGS_FILE_NAME),
+            "(compression_mode: Some(Fast), texture: (block_compression: Some(Standard)))",
+        )
+        .unwrap();
+        std::fs::write(
+            folder.join(FOLDER_IMPORT_SETTINGS_FILE_NAME),
+            "#![enable(implicit_some)]\n(texture: (usage: NormalMap, generate_mipmaps: false))",
+        )
+        .unwrap();
+        let source = folder.join("brick.png");
+        ImportSettings {
+            texture: TextureSettings {
+                generate_mipmaps: Some(true),
+                color_space: Some(ColorSpace::Linear),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .save(&ImportSettings::sidecar_path(&source))
+        .unwrap();
+        assert!(folder.join("brick.png.import.ron").is_file());
+
+        let settings = ImportSettings::for_source(&source, root).unwrap();
+        let importer = settings.texture_importer(&TextureImporter::default());
+        assert_eq!(importer.compression_mode, CompressionMode::Fast);
+        assert_eq!(importer.block_compression, BlockCompression::Standard);
+        assert_eq!(importer.usage, TextureUsage::NormalMap);
+        assert!(importer.generate_mipmaps);
+        assert_eq!(importer.color_space, Some(ColorSpace::Linear));
+
+        // Defaults of folders above the root don't apply.
+        let settings = ImportSettings::for_source(&source, &folder).unwrap();
+        assert_eq!(settings.compression_mode, None);
+        assert_eq!(settings.texture.usage, Some(TextureUsage::NormalMap));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,551 @@
+use crate::{AssetError, ShaderStage};
+use naga::back::spv;
+use naga::front::glsl;
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+pub const GLSL_SOURCE_EXTENSIONS: &[&str] = &["vert", "frag", "comp", "glsl"];
+// Every keyword doubles the count of variants.
+const MAX_PERMUTATION_KEYWORDS: usize = 16;
+
+// The GLSL source of a shader file with its includes expanded, and the file and line every line
+// of it came from, to report errors against the files that were written.
+#[derive(Clone, Debug)]
+pub struct ShaderSource {
+    source: String,
+    // Index into `files` and line number of every line of `source`.
+    lines: Vec<(usize, usize)>,
+    // The shader file followed by its includes.
+    files: Vec<PathBuf>,
+    keywords: Vec<String>,
+}
+
+impl ShaderSource {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // The shader file and every file it includes, e.g. to rebuild when one of them changes.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    // Keywords declared with `#pragma permutation NAME`, in declaration order.
+    pub fn keywords(&self) -> &[String] {
+        &self.keywords
+    }
+
+    // Every combination of keywords to compile a variant with, starting with none.
+    pub fn permutations(&self) -> Vec<Vec<&str>> {
+        (0..1usize << self.keywords.len())
+            .map(|mask| {
+                self.keywords
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, keyword)| keyword.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    // `line` counts from 1 in the expanded source.
+    fn location(&self, line: usize) -> (&Path, usize) {
+        match self.lines.get(line.saturating_sub(1)) {
+            Some(&(file, line)) => (&self.files[file], line),
+            None => (&self.files[0], line),
+        }
+    }
+
+    fn error(&self, span: naga::Span, message: &str) -> String {
+        match span.is_defined() {
+            true => {
+                let location = span.location(&self.source);
+                let (file, line) = self.location(location.line_number as usize);
+                format!(
+                    "{}:{line}:{}: {message}",
+                    file.display(),
+                    location.line_position
+                )
+            }
+            false => format!("{}: {message}", self.files[0].display()),
+        }
+    }
+}
+
+// Compiles GLSL shaders for Vulkan to SPIR-V with naga. On top of the preprocessor of GLSL it
+// expands `#include "file"` relative to the including file or the include directories,
+// `#include <file>` from the include directories only and skips files with `#pragma once` that
+// were already included. `#pragma permutation NAME` declares a keyword that is defined as `1`
+// in the variants compiled with it. These directives are applied before the conditionals of the
+// GLSL preprocessor are evaluated, so they take effect inside `#if` blocks that turn out
+// inactive too, the lines of an include still only compile where the block is active. They're
+// ignored inside block comments. HLSL isn't supported.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ShaderCompiler {
+    pub include_directories: Vec<PathBuf>,
+    // Defined in every variant.
+    pub defines: BTreeMap<String, String>,
+}
+
+impl ShaderCompiler {
+    // Bumped whenever the output for the same source and settings changes.
+    pub const VERSION: u32 = 2;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The stage of a shader file by its extension, `None` for includes.
+    pub fn stage<T: AsRef<Path> + ?Sized>(path: &T) -> Option<ShaderStage> {
+        match path.as_ref().extension()?.to_str()? {
+            "vert" => Some(ShaderStage::Vertex),
+            "frag" => Some(ShaderStage::Fragment),
+            "comp" => Some(ShaderStage::Compute),
+            _ => None,
+        }
+    }
+
+    // Compiles the variant of a shader file without keywords for the stage of its extension.
+    pub fn compile_file<T: AsRef<Path> + ?Sized>(&self, path: &T) -> Result<Vec<u32>, AssetError> {
+        let stage = Self::stage(path).ok_or_else(|| {
+            AssetError::InvalidShader(format!(
+                "The stage of {} is unknown.",
+                path.as_ref().display()
+            ))
+        })?;
+
+        self.compile(&self.preprocess(path)?, stage, &[])
+    }
+
+    pub fn preprocess<T: AsRef<Path> + ?Sized>(
+        &self,
+        path: &T,
+    ) -> Result<ShaderSource, AssetError> {
+        let mut source = ShaderSource {
+            source: String::new(),
+            lines: Vec::new(),
+            files: Vec::new(),
+            keywords: Vec::new(),
+        };
+        let mut include_once = HashSet::new();
+        self.expand(
+            path.as_ref(),
+            &mut source,
+            &mut include_once,
+            &mut Vec::new(),
+        )?;
+
+        Ok(source)
+    }
+
+    pub fn compile(
+        &self,
+        source: &ShaderSource,
+        stage: ShaderStage,
+        keywords: &[&str],
+    ) -> Result<Vec<u32>, AssetError> {
+        let stage = match stage {
+            ShaderStage::Vertex => naga::ShaderStage::Vertex,
+            ShaderStage::Fragment => naga::ShaderStage::Fragment,
+            ShaderStage::Compute => naga::ShaderStage::Compute,
+            stage => {
+                return Err(AssetError::InvalidShader(format!(
+                    "{stage:?} shaders can't be compiled from GLSL."
+                )))
+            }
+        };
+
+        let mut options = glsl::Options::from(stage);
+        options.defines.extend(self.defines.clone());
+        for &keyword in keywords {
+            if !source.keywords.iter().any(|declared| declared == keyword) {
+                return Err(AssetError::InvalidShader(format!(
+                    "{} has no permutation keyword {keyword}.",
+                    source.files[0].display()
+                )));
+            }
+            options.defines.insert(keyword.to_string(), "1".to_string());
+        }
+
+        let module = glsl::Frontend::default()
+            .parse(&options, &source.source)
+            .map_err(|e| {
+                AssetError::InvalidShader(
+                    e.errors
+                        .iter()
+                        .map(|error| source.error(error.meta, &error.kind.to_string()))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                )
+            })?;
+        let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+            .validate(&module)
+            .map_err(|e| {
+                let message = e.as_inner().to_string();
+                let errors = e
+                    .spans()
+                    .map(|(span, label)| source.error(*span, &format!("{message}: {label}")))
+                    .collect::<Vec<_>>();
+                AssetError::InvalidShader(match errors.is_empty() {
+                    true => source.error(naga::Span::default(), &message),
+                    false => errors.join("\n"),
+                })
+            })?;
+
+        // The GLSL is written for Vulkan already, so the coordinate space stays as it is. Names
+        // are kept in every build, as materials bind to the samplers and block members by name.
+        let mut options = spv::Options::default();
+        options
+            .flags
+            .remove(spv::WriterFlags::ADJUST_COORDINATE_SPACE);
+        options.flags.insert(spv::WriterFlags::DEBUG);
+
+        spv::write_vec(&module, &info, &options, None).map_err(|e| {
+            AssetError::InvalidShader(source.error(naga::Span::default(), &e.to_string()))
+        })
+    }
+
+    // Appends the lines of `path` to `source`. `stack` holds the files being expanded to reject
+    // include cycles.
+    fn expand(
+        &self,
+        path: &Path,
+        source: &mut ShaderSource,
+        include_once: &mut HashSet<PathBuf>,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<(), AssetError> {
+        let identity = path.canonicalize()?;
+        if include_once.contains(&identity) {
+            return Ok(());
+        }
+        if stack.contains(&identity) {
+            return Err(AssetError::InvalidShader(format!(
+                "{} includes itself.",
+                path.display()
+            )));
+        }
+
+        let text = std::fs::read_to_string(path)?;
+        let file = source.files.len();
+        source.files.push(path.to_path_buf());
+        stack.push(identity.clone());
+
+        let mut in_comment = false;
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let error = |message: String| {
+                AssetError::InvalidShader(format!("{}:{line_number}: {message}", path.display()))
+            };
+
+            let directive = match in_comment {
+                true => None,
+                false => Directive::parse(line),
+            };
+            in_comment = ends_in_block_comment(line, in_comment);
+            match directive {
+                Some(Directive::Include { name, quoted }) => {
+                    let include = self
+                        .resolve(path, name, quoted)
+                        .ok_or_else(|| error(format!("The include {name} can't be found.")))?;
+                    self.expand(&include, source, include_once, stack)?;
+                }
+                Some(Directive::Once) => {
+                    include_once.insert(identity.clone());
+                }
+                Some(Directive::Permutation(keyword)) => {
+                    if !is_identifier(keyword) {
+                        return Err(error(format!("{keyword} isn't a valid keyword.")));
+                    }
+                    if !source.keywords.iter().any(|declared| declared == keyword) {
+                        if source.keywords.len() == MAX_PERMUTATION_KEYWORDS {
+                            return Err(error(format!(
+                                "A shader can't have more than {MAX_PERMUTATION_KEYWORDS} \
+                                 permutation keywords."
+                            )));
+                        }
+                        source.keywords.push(keyword.to_string());
+                    }
+                }
+                None => {
+                    source.source.push_str(line);
+                    source.source.push('\n');
+                    source.lines.push((file, line_number));
+                }
+            }
+        }
+        stack.pop();
+
+        Ok(())
+    }
+
+    fn resolve(&self, includer: &Path, name: &str, quoted: bool) -> Option<PathBuf> {
+        let relative = includer
+            .parent()
+            .filter(|_| quoted)
+            .map(|directory| directory.join(name));
+
+        relative
+            .into_iter()
+            .chain(
+                self.include_directories
+                    .iter()
+                    .map(|directory| directory.join(name)),
+            )
+            .find(|path| path.is_file())
+    }
+}
+
+// Directives handled before the source is handed to the GLSL preprocessor.
+enum Directive<'a> {
+    Include { name: &'a str, quoted: bool },
+    Once,
+    Permutation(&'a str),
+}
+
+impl<'a> Directive<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let directive = line.trim().strip_prefix('#')?.trim_start();
+
+        if let Some(name) = directive.strip_prefix("include") {
+            let name = name.trim();
+            return match (name.chars().next(), name.chars().last()) {
+                (Some('"'), Some('"')) if name.len() > 1 => Some(Directive::Include {
+                    name: &name[1..name.len() - 1],
+                    quoted: true,
+                }),
+                (Some('<'), Some('>')) => Some(Directive::Include {
+                    name: &name[1..name.len() - 1],
+                    quoted: false,
+                }),
+                _ => None,
+            };
+        }
+
+        let mut pragma = directive.strip_prefix("pragma")?.split_whitespace();
+        match (pragma.next(), pragma.next(), pragma.next()) {
+            (Some("once"), None, _) => Some(Directive::Once),
+            (Some("permutation"), Some(keyword), None) => Some(Directive::Permutation(keyword)),
+            _ => None,
+        }
+    }
+}
+
+// Whether a block comment is still open at the end of the line.
+fn ends_in_block_comment(line: &str, mut in_comment: bool) -> bool {
+    let mut rest = line;
+    loop {
+        match in_comment {
+            true => match rest.find("*/") {
+                Some(end) => {
+                    in_comment = false;
+                    rest = &rest[end + 2..];
+                }
+                None => return true,
+            },
+            false => {
+                let line_comment = rest.find("//").unwrap_or(rest.len());
+                match rest.find("/*") {
+                    Some(start) if start < line_comment => {
+                        in_comment = true;
+                        rest = &rest[start + 2..];
+                    }
+                    _ => return false,
+                }
+            }
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Shader;
+
+    const SHADER_DIRECTORY: &str = "src/test_asset_files/shaders";
+
+    fn write(name: &str, source: &str) -> PathBuf {
+        let path = Path::new(SHADER_DIRECTORY).join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, source).unwrap();
+
+        path
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn compile_shaders_with_includes_and_permutations() {
+        write(
+            "common/color.glsl",
+            "#pragma once\nvec4 tint(vec3 color) {\n    return vec4(color, 1.0);\n}\n",
+        );
+        write(
+            "lighting.glsl",
+            "#include \"common/color.glsl\"\n#include <common/color.glsl>\n",
+        );
+        let fragment = write(
+            "lit.frag",
+            "#version 450\n#pragma permutation DARKEN\n#include \"lighting.glsl\"\n\
+             layout(location = 0) in vec3 fragColor;\nlayout(location = 0) out vec4 outColor;\n\
+             layout(set = 0, binding = 0) uniform Material {\n    vec3 emission;\n\
+                 float strength;\n} material;\n\
+             void main() {\n#if DARKEN\n    outColor = tint(fragColor * 0.5);\n#else\n\
+                 outColor = tint(fragColor);\n#endif\n\
+                 outColor.rgb += material.emission * material.strength;\n}\n",
+        );
+        let vertex = write(
+            "lit.vert",
+            "#version 450\nlayout(location = 0) in vec3 position;\n\
+             layout(location = 0) out vec3 fragColor;\nvoid main() {\n\
+                 gl_Position = vec4(position, 1.0);\n    fragColor = position;\n}\n",
+        );
+
+        let mut compiler = ShaderCompiler::new();
+        compiler.include_directories.push(SHADER_DIRECTORY.into());
+        let source = compiler.preprocess(&fragment);
+        let vertex = compiler.compile_file(&vertex);
+        std::fs::remove_dir_all(SHADER_DIRECTORY).unwrap();
+
+        // The second include of the color functions is skipped.
+        let source = source.unwrap();
+        assert_eq!(source.files().len(), 3);
+        assert_eq!(source.source().matches("vec4 tint").count(), 1);
+        assert_eq!(source.keywords(), ["DARKEN"]);
+        assert_eq!(source.permutations(), [vec![], vec!["DARKEN"]]);
+
+        let variants = source
+            .permutations()
+            .iter()
+            .map(|keywords| {
+                compiler
+                    .compile(&source, ShaderStage::Fragment, keywords)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_ne!(variants[0], variants[1]);
+
+        let shader = Shader::from_spirv(vec![vertex.unwrap(), variants[1].clone()]).unwrap();
+        assert_eq!(shader.metadata.entry_points.len(), 2);
+        assert_eq!(shader.metadata.vertex_inputs[0].location, 0);
+        assert_eq!(shader.metadata.output_attachments[0].location, 0);
+        assert_eq!(
+            shader.metadata.output_attachments[0]
+                .attribute_type
+                .components,
+            4
+        );
+        // Materials find their parameters by the names of the block members.
+        let members = shader.metadata.descriptor_bindings[0]
+            .members
+            .iter()
+            .map(|member| (member.name.as_str(), member.offset, member.size))
+            .collect::<Vec<_>>();
+        assert_eq!(members, [("emission", 0, 12), ("strength", 12, 4)]);
+
+        assert!(matches!(
+            compiler.compile(&source, ShaderStage::Fragment, &["BRIGHTEN"]),
+            Err(AssetError::InvalidShader(_))
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn apply_directives_outside_of_block_comments() {
+        const DIRECTORY: &str = "src/test_asset_files/shader_directives";
+
+        let directory = Path::new(DIRECTORY);
+        std::fs::create_dir_all(directory).unwrap();
+        std::fs::write(directory.join("inactive.glsl"), "float broken = missing;\n").unwrap();
+        let shader = directory.join("directives.frag");
+        std::fs::write(
+            &shader,
+            "#version 450\n/* Disabled:\n#include \"missing.glsl\"\n\
+             #pragma permutation HIDDEN\n*/\n#if 0\n#pragma permutation UNCONDITIONAL\n#include \"inactive.glsl\"\n#endif\n\
+             void main() {}\n",
+        )
+        .unwrap();
+        let keywords = (0..=MAX_PERMUTATION_KEYWORDS)
+            .map(|i| format!("#pragma permutation KEYWORD_{i}\n"))
+            .collect::<String>();
+        let too_many = directory.join("too_many.frag");
+        std::fs::write(
+            &too_many,
+            format!("#version 450\n{keywords}void main() {{}}\n"),
+        )
+        .unwrap();
+
+        let compiler = ShaderCompiler::new();
+        let source = compiler.preprocess(&shader);
+        let too_many = compiler.preprocess(&too_many);
+        std::fs::remove_dir_all(directory).unwrap();
+
+        // Directives in comments stay comments, those in inactive blocks are applied anyway, while
+        // the included lines are left out of the compilation by the block.
+        let source = source.unwrap();
+        assert!(source.source().contains("#include \"missing.glsl\""));
+        assert_eq!(source.keywords(), ["UNCONDITIONAL"]);
+        assert_eq!(source.files().len(), 2);
+        compiler
+            .compile(&source, ShaderStage::Fragment, &["UNCONDITIONAL"])
+            .unwrap();
+
+        assert!(
+            matches!(too_many, Err(AssetError::InvalidShader(e)) if e.contains("more than 16"))
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn map_errors_to_source_lines() {
+        const DIRECTORY: &str = "src/test_asset_files/shader_errors";
+
+        let directory = Path::new(DIRECTORY);
+        std::fs::create_dir_all(directory).unwrap();
+        let include = directory.join("broken.glsl");
+        std::fs::write(
+            &include,
+            "// A comment.\nfloat broken() {\n    return missing;\n}\n",
+        )
+        .unwrap();
+        let shader = directory.join("broken.frag");
+        std::fs::write(
+            &shader,
+            "#version 450\n#include \"broken.glsl\"\nvoid main() {}\n",
+        )
+        .unwrap();
+        let cyclic = directory.join("cyclic.frag");
+        std::fs::write(&cyclic, "#version 450\n#include \"cyclic.frag\"\n").unwrap();
+        let missing = directory.join("missing.frag");
+        std::fs::write(&missing, "#version 450\n\n#include \"missing.glsl\"\n").unwrap();
+
+        let compiler = ShaderCompiler::new();
+        let broken = compiler.compile_file(&shader);
+        let cyclic = compiler.preprocess(&cyclic);
+        let missing = compiler.preprocess(&missing);
+        std::fs::remove_dir_all(directory).unwrap();
+
+        let Err(AssetError::InvalidShader(error)) = broken else {
+            panic!("{broken:?}");
+        };
+        assert!(
+            error.starts_with(&format!("{}:3:12: ", include.display())),
+            "{error}"
+        );
+        assert!(error.contains("missing"), "{error}");
+        assert!(
+            matches!(cyclic, Err(AssetError::InvalidShader(e)) if e.contains("includes itself"))
+        );
+        assert!(
+            matches!(missing, Err(AssetError::InvalidShader(e)) if e.contains("missing.frag:3:"))
+        );
+    }
+}
@@ -0,0 +1,599 @@
+use crate::texture::full_mip_chain_length;
+use crate::{
+    AssetError, AssetFile, AssetGuid, ColorSpace, CompressionMode, CubeFace, TextureAsset,
+    TextureDimension, TextureFormat, CUBE_FACES,
+};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageBuffer, Pixel};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const TEXTURE_SOURCE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "tga", "hdr"];
+
+// What the texture is sampled for, which decides its color space and block-compressed format.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TextureUsage {
+    #[default]
+    Albedo,
+    NormalMap,
+    // Single channel data like roughness or ambient occlusion.
+    Mask,
+    // Several linear channels packed into one texture, like glTF metallic-roughness.
+    Packed,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlockCompression {
+    #[default]
+    None,
+    // BC1 or BC3 for albedo and packed textures, BC5 for normal maps, BC4 for masks.
+    Standard,
+    // BC7 for albedo and packed textures, the same as `Standard` otherwise.
+    HighQuality,
+}
+
+impl TextureUsage {
+    pub fn color_space(self) -> ColorSpace {
+        match self {
+            TextureUsage::Albedo => ColorSpace::Srgb,
+            TextureUsage::NormalMap | TextureUsage::Mask | TextureUsage::Packed => {
+                ColorSpace::Linear
+            }
+        }
+    }
+
+    // The UNORM variant of the format, `None` if the texture stays uncompressed.
+    pub fn block_format(
+        self,
+        block_compression: BlockCompression,
+        has_alpha: bool,
+    ) -> Option<TextureFormat> {
+        let format = match (self, block_compression) {
+            (_, BlockCompression::None) => return None,
+            (TextureUsage::NormalMap, _) => TextureFormat::BC5Unorm,
+            (TextureUsage::Mask, _) => TextureFormat::BC4Unorm,
+            (_, BlockCompression::HighQuality) => TextureFormat::BC7Unorm,
+            (_, _) if has_alpha => TextureFormat::BC3Unorm,
+            (_, _) => TextureFormat::BC1RgbUnorm,
+        };
+
+        Some(format)
+    }
+}
+
+// Decodes PNG, JPEG, TGA and Radiance HDR images into texture asset files. LDR images become
+// RGB8 or RGBA8 depending on their alpha channel and color space, or BC blocks when
+// `block_compression` is enabled, HDR images become `hdr_format`. Cube maps are imported from
+// six faces or an equirectangular image, arrays from a list of images.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TextureImporter {
+    pub compression_mode: CompressionMode,
+    pub usage: TextureUsage,
+    pub block_compression: BlockCompression,
+    pub generate_mipmaps: bool,
+    // Overrides the color space, otherwise it's decided by the usage and HDR images are linear.
+    pub color_space: Option<ColorSpace>,
+    // Either RGBA16F or RGBA32F.
+    pub hdr_format: TextureFormat,
+}
+
+impl Default for TextureImporter {
+    fn default() -> Self {
+        Self {
+            compression_mode: CompressionMode::Default,
+            usage: TextureUsage::default(),
+            block_compression: BlockCompression::default(),
+            generate_mipmaps: true,
+            color_space: None,
+            hdr_format: TextureFormat::RGBA16F,
+        }
+    }
+}
+
+impl TextureImporter {
+    // Bumped whenever the output for the same source and settings changes.
+    pub const VERSION: u32 = 1;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn import<T: AsRef<Path> + ?Sized>(
+        &self,
+        source: &T,
+        name: &str,
+        path: &str,
+    ) -> Result<AssetFile, AssetError> {
+        let image = image::ImageReader::open(source)?
+            .with_guessed_format()?
+            .decode()?;
+
+        self.pack(image, name, path)
+    }
+
+    // TGA has no signature, so the extension of the source is used when the format can't be
+    // recognized from the content.
+    pub fn import_from_memory(
+        &self,
+        source: &[u8],
+        extension: &str,
+        name: &str,
+        path: &str,
+    ) -> Result<AssetFile, AssetError> {
+        let format = match image::guess_format(source) {
+            Ok(format) => format,
+            Err(_) => image::ImageFormat::from_extension(extension).ok_or_else(|| {
+                AssetError::Import(format!("Unknown image format of a .{extension} file."))
+            })?,
+        };
+
+        self.pack(
+            image::load_from_memory_with_format(source, format)?,
+            name,
+            path,
+        )
+    }
+
+    // Faces are given in the order of `CUBE_FACES` and have to be squares of the same size.
+    pub fn import_cubemap<T: AsRef<Path>>(
+        &self,
+        faces: &[T; 6],
+        name: &str,
+        path: &str,
+    ) -> Result<AssetFile, AssetError> {
+        let faces = faces
+            .iter()
+            .map(|face| {
+                Ok(image::ImageReader::open(face)?
+                    .with_guessed_format()?
+                    .decode()?)
+            })
+            .collect::<Result<Vec<_>, AssetError>>()?;
+
+        self.pack_layers(faces, TextureDimension::Cube, name, path)
+    }
+
+    // Projects an equirectangular (latitude-longitude) image, usually an HDR environment, onto
+    // the faces of a cube with sides of `face_size` texels.
+    pub fn import_equirectangular<T: AsRef<Path> + ?Sized>(
+        &self,
+        source: &T,
+        face_size: u32,
+        name: &str,
+        path: &str,
+    ) -> Result<AssetFile, AssetError> {
+        let image = image::ImageReader::open(source)?
+            .with_guessed_format()?
+            .decode()?;
+
+        self.pack_layers(
+            equirectangular_to_cube(image, face_size),
+            TextureDimension::Cube,
+            name,
+            path,
+        )
+    }
+
+    // Layers have to be of the same size.
+    pub fn import_array<T: AsRef<Path>>(
+        &self,
+        layers: &[T],
+        name: &str,
+        path: &str,
+    ) -> Result<AssetFile, AssetError> {
+        let layers = layers
+            .iter()
+            .map(|layer| {
+                Ok(image::ImageReader::open(layer)?
+                    .with_guessed_format()?
+                    .decode()?)
+            })
+            .collect::<Result<Vec<_>, AssetError>>()?;
+
+        self.pack_layers(layers, TextureDimension::D2Array, name, path)
+    }
+
+    fn pack(&self, image: DynamicImage, name: &str, path: &str) -> Result<AssetFile, AssetError> {
+        let (texture_asset, texels) = self.convert(image)?;
+
+        Ok(
+            AssetFile::new(texture_asset, name, path, texels, self.compression_mode)?
+                .with_guid(AssetGuid::from_name(path)),
+        )
+    }
+
+    fn pack_layers(
+        &self,
+        layers: Vec<DynamicImage>,
+        dimension: TextureDimension,
+        name: &str,
+        path: &str,
+    ) -> Result<AssetFile, AssetError> {
+        let (texture_asset, texels) = self.convert_layers(layers, dimension)?;
+
+        Ok(
+            AssetFile::new(texture_asset, name, path, texels, self.compression_mode)?
+                .with_guid(AssetGuid::from_name(path)),
+        )
+    }
+
+    pub(crate) fn convert(
+        &self,
+        image: DynamicImage,
+    ) -> Result<(TextureAsset, Vec<u8>), AssetError> {
+        self.convert_layers(vec![image], TextureDimension::D2)
+    }
+
+    // Every mip level holds the layers one after another.
+    pub(crate) fn convert_layers(
+        &self,
+        layers: Vec<DynamicImage>,
+        dimension: TextureDimension,
+    ) -> Result<(TextureAsset, Vec<u8>), AssetError> {
+        let (width, height) = match layers.first() {
+            Some(layer) => (layer.width(), layer.height()),
+            None => return Err(AssetError::Import("No images to import.".to_string())),
+        };
+        if let Some(layer) = layers
+            .iter()
+            .find(|layer| (layer.width(), layer.height()) != (width, height))
+        {
+            return Err(AssetError::Import(format!(
+                "Layers of a texture differ in size: {width}x{height} and {}x{}.",
+                layer.width(),
+                layer.height()
+            )));
+        }
+
+        let mip_levels = match self.generate_mipmaps {
+            true => full_mip_chain_length(width, height),
+            false => 1,
+        };
+        let is_hdr = layers.iter().any(|layer| {
+            matches!(
+                layer,
+                DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+            )
+        });
+        let color_space = match is_hdr {
+            true => self.color_space.unwrap_or(ColorSpace::Linear),
+            false => self.color_space.unwrap_or(self.usage.color_space()),
+        };
+        let has_alpha = layers.iter().any(|layer| layer.color().has_alpha());
+
+        // HDR images aren't block-compressed, BC6H isn't supported by the encoder.
+        let texture_format = if is_hdr {
+            match self.hdr_format {
+                TextureFormat::RGBA16F | TextureFormat::RGBA32F => self.hdr_format,
+                format => {
+                    return Err(AssetError::Import(format!(
+                        "{format:?} can't store HDR images."
+                    )))
+                }
+            }
+        } else if let Some(format) = self.usage.block_format(self.block_compression, has_alpha) {
+            format
+        } else if has_alpha {
+            TextureFormat::RGBA8Unorm
+        } else {
+            TextureFormat::RGB8Unorm
+        };
+
+        let array_layers = layers.len() as u32;
+        let mut mips = vec![Vec::new(); mip_levels as usize];
+        for layer in layers {
+            let layer_mips = match texture_format {
+                TextureFormat::RGBA32F => mip_chain(layer.into_rgba32f(), mip_levels)
+                    .into_iter()
+                    .map(|mip| {
+                        mip.into_raw()
+                            .into_iter()
+                            .flat_map(f32::to_le_bytes)
+                            .collect()
+                    })
+                    .collect(),
+                TextureFormat::RGBA16F => mip_chain(layer.into_rgba32f(), mip_levels)
+                    .into_iter()
+                    .map(|mip| {
+                        mip.into_raw()
+                            .into_iter()
+                            .flat_map(|texel| half::f16::from_f32(texel).to_le_bytes())
+                            .collect()
+                    })
+                    .collect(),
+                TextureFormat::RGBA8Unorm => mip_chain(layer.into_rgba8(), mip_levels)
+                    .into_iter()
+                    .map(|mip| mip.into_raw())
+                    .collect(),
+                TextureFormat::RGB8Unorm => mip_chain(layer.into_rgb8(), mip_levels)
+                    .into_iter()
+                    .map(|mip| mip.into_raw())
+                    .collect(),
+                format => mip_chain(layer.into_rgba8(), mip_levels)
+                    .iter()
+                    .map(|mip| crate::block_compression::compress(format, mip))
+                    .collect::<Result<Vec<_>, _>>()?,
+            };
+
+            for (mip, texels) in mips.iter_mut().zip(layer_mips) {
+                mip.extend(texels);
+            }
+        }
+
+        let texture_format = match color_space {
+            ColorSpace::Srgb => texture_format.to_srgb(),
+            ColorSpace::Linear => texture_format.to_unorm(),
+        };
+        let texture_asset = TextureAsset::new(texture_format, width, height)
+            .with_color_space(color_space)
+            .with_mip_levels(mip_levels)
+            .with_layers(dimension, array_layers);
+        texture_asset.validate()?;
+
+        Ok((texture_asset, mips.concat()))
+    }
+}
+
+// Samples the direction through every texel of the faces, in the Vulkan cube map convention.
+fn equirectangular_to_cube(image: DynamicImage, face_size: u32) -> Vec<DynamicImage> {
+    use std::f32::consts::{FRAC_1_PI, PI};
+
+    let is_hdr = matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    );
+    let has_alpha = image.color().has_alpha();
+    let source = image.into_rgba32f();
+
+    CUBE_FACES
+        .iter()
+        .map(|&face| {
+            let face = image::Rgba32FImage::from_fn(face_size, face_size, |x, y| {
+                let s = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                let t = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+                let [dx, dy, dz] = match face {
+                    CubeFace::PositiveX => [1.0, -t, -s],
+                    CubeFace::NegativeX => [-1.0, -t, s],
+                    CubeFace::PositiveY => [s, 1.0, t],
+                    CubeFace::NegativeY => [s, -1.0, -t],
+                    CubeFace::PositiveZ => [s, -t, 1.0],
+                    CubeFace::NegativeZ => [-s, -t, -1.0],
+                };
+                let length = (dx * dx + dy * dy + dz * dz).sqrt();
+
+                // Longitude wraps around, latitude goes from the top row at +Y to the bottom one.
+                let u = (0.5 + dz.atan2(dx) * 0.5 * FRAC_1_PI).rem_euclid(1.0);
+                let v = 0.5 - (dy / length).asin() / PI;
+
+                image::imageops::sample_bilinear(&source, u, v.clamp(0.0, 1.0))
+                    .unwrap_or(image::Rgba([0.0, 0.0, 0.0, 1.0]))
+            });
+
+            match (is_hdr, has_alpha) {
+                (true, _) => DynamicImage::ImageRgba32F(face),
+                (false, true) => DynamicImage::ImageRgba8(DynamicImage::from(face).into_rgba8()),
+                (false, false) => DynamicImage::ImageRgb8(DynamicImage::from(face).into_rgb8()),
+            }
+        })
+        .collect()
+}
+
+// Halves the image until the requested count of mip levels, the first one is the image itself.
+fn mip_chain<P>(
+    image: ImageBuffer<P, Vec<P::Subpixel>>,
+    mip_levels: u32,
+) -> Vec<ImageBuffer<P, Vec<P::Subpixel>>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: 'static,
+{
+    let mut mips = vec![image];
+    for _ in 1..mip_levels {
+        let previous = mips.last().unwrap();
+        let (width, height) = (
+            (previous.width() / 2).max(1),
+            (previous.height() / 2).max(1),
+        );
+        mips.push(image::imageops::resize(
+            previous,
+            width,
+            height,
+            FilterType::Triangle,
+        ));
+    }
+
+    mips
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Asset, Texture};
+
+    fn encode(image: DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut encoded = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut encoded, format).unwrap();
+
+        encoded.into_inner()
+    }
+
+    #[test]
+    fn import_ldr_images() {
+        let rgba = image::RgbaImage::from_fn(2, 2, |x, y| image::Rgba([x as u8, y as u8, 7, 128]));
+        let png = encode(rgba.clone().into(), image::ImageFormat::Png);
+        let tga = encode(
+            DynamicImage::from(rgba.clone()).to_rgb8().into(),
+            image::ImageFormat::Tga,
+        );
+
+        let asset_file = TextureImporter::new()
+            .import_from_memory(&png, "png", "png", "png.bin")
+            .unwrap();
+        let texture = Texture::from_asset_file(asset_file).unwrap();
+        assert_eq!(texture.metadata.texture_format, TextureFormat::RGBA8Srgb);
+        assert_eq!(texture.metadata.color_space, ColorSpace::Srgb);
+        assert_eq!((texture.metadata.width, texture.metadata.height), (2, 2));
+        assert_eq!(texture.metadata.mip_levels, 2);
+        assert_eq!(
+            texture.data[texture.metadata.mip_level_range(0)],
+            rgba.into_raw()
+        );
+        assert_eq!(texture.data.len(), (2 * 2 + 1) * 4);
+
+        let importer = TextureImporter {
+            color_space: Some(ColorSpace::Linear),
+            ..TextureImporter::new()
+        };
+        let texture = Texture::from_asset_file(
+            importer
+                .import_from_memory(&tga, "tga", "tga", "tga.bin")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(texture.metadata.texture_format, TextureFormat::RGB8Unorm);
+        assert_eq!(texture.metadata.color_space, ColorSpace::Linear);
+        assert_eq!(texture.data.len(), (2 * 2 + 1) * 3);
+    }
+
+    #[test]
+    fn import_hdr_image() {
+        let rgb = image::Rgb32FImage::from_pixel(2, 1, image::Rgb([1.5, 0.5, 4.0]));
+        let hdr = encode(rgb.into(), image::ImageFormat::Hdr);
+
+        let importer = TextureImporter {
+            generate_mipmaps: false,
+            ..TextureImporter::new()
+        };
+        let (texture_asset, texels) = importer
+            .convert(image::load_from_memory(&hdr).unwrap())
+            .unwrap();
+        assert_eq!(texture_asset.texture_format, TextureFormat::RGBA16F);
+        assert_eq!(texture_asset.color_space, ColorSpace::Linear);
+        assert_eq!(texels.len(), 2 * 4 * 2);
+        assert_eq!(
+            half::f16::from_le_bytes([texels[6], texels[7]]),
+            half::f16::ONE
+        );
+
+        let importer = TextureImporter {
+            hdr_format: TextureFormat::RGBA32F,
+            generate_mipmaps: false,
+            ..TextureImporter::new()
+        };
+        let (texture_asset, texels) = importer
+            .convert(image::load_from_memory(&hdr).unwrap())
+            .unwrap();
+        assert_eq!(texture_asset.texture_format, TextureFormat::RGBA32F);
+        assert_eq!(texels.len(), 2 * 4 * 4);
+        assert_eq!(f32::from_le_bytes(texels[..4].try_into().unwrap()), 1.5);
+
+        let importer = TextureImporter {
+            hdr_format: TextureFormat::RGBA8Srgb,
+            ..TextureImporter::new()
+        };
+        assert!(matches!(
+            importer.convert(image::load_from_memory(&hdr).unwrap()),
+            Err(AssetError::Import(_))
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn import_block_compressed_textures() {
+        let rgb =
+            image::RgbImage::from_fn(8, 8, |x, y| image::Rgb([x as u8 * 30, y as u8 * 30, 60]));
+        let png = encode(rgb.into(), image::ImageFormat::Png);
+
+        let importer = TextureImporter {
+            block_compression: BlockCompression::Standard,
+            ..TextureImporter::new()
+        };
+        let albedo = Texture::from_asset_file(
+            importer
+                .import_from_memory(&png, "png", "albedo", "albedo.bin")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(albedo.metadata.texture_format, TextureFormat::BC1RgbSrgb);
+        assert_eq!(albedo.metadata.mip_levels, 4);
+        assert_eq!(albedo.data.len(), albedo.metadata.data_size());
+
+        let importer = TextureImporter {
+            usage: TextureUsage::NormalMap,
+            block_compression: BlockCompression::HighQuality,
+            ..TextureImporter::new()
+        };
+        let normal_map = Texture::from_asset_file(
+            importer
+                .import_from_memory(&png, "png", "normal_map", "normal_map.bin")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(normal_map.metadata.texture_format, TextureFormat::BC5Unorm);
+        assert_eq!(normal_map.metadata.color_space, ColorSpace::Linear);
+
+        // The fallback for devices without BC support.
+        let decoded = albedo.to_rgba8().unwrap();
+        assert_eq!(decoded.metadata.texture_format, TextureFormat::RGBA8Srgb);
+        assert_eq!(decoded.metadata.mip_levels, 4);
+        assert_eq!(decoded.data.len(), (64 + 16 + 4 + 1) * 4);
+    }
+
+    #[test]
+    fn import_cubemap_faces() {
+        let faces = (0..6u8)
+            .map(|face| {
+                image::RgbaImage::from_pixel(4, 4, image::Rgba([face * 40, 0, 0, 255])).into()
+            })
+            .collect::<Vec<DynamicImage>>();
+
+        let (texture_asset, texels) = TextureImporter::new()
+            .convert_layers(faces.clone(), TextureDimension::Cube)
+            .unwrap();
+        assert_eq!(texture_asset.dimension, TextureDimension::Cube);
+        assert_eq!(texture_asset.array_layers, 6);
+        assert_eq!(texture_asset.mip_levels, 3);
+        assert_eq!(texels.len(), texture_asset.data_size());
+
+        // Layers follow each other inside every mip level.
+        for mip_level in 0..texture_asset.mip_levels {
+            let mip = &texels[texture_asset.mip_level_range(mip_level)];
+            for (face, layer) in mip.chunks(mip.len() / 6).enumerate() {
+                assert_eq!(layer[0], face as u8 * 40);
+            }
+        }
+
+        let mut faces = faces;
+        faces[3] = image::RgbaImage::new(2, 2).into();
+        assert!(matches!(
+            TextureImporter::new().convert_layers(faces, TextureDimension::Cube),
+            Err(AssetError::Import(_))
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn project_equirectangular_image() {
+        // A bright sky above a dark ground.
+        let sky = image::Rgb32FImage::from_fn(16, 8, |_, y| match y < 4 {
+            true => image::Rgb([4.0, 4.0, 4.0]),
+            false => image::Rgb([0.0, 0.0, 0.0]),
+        });
+
+        let faces = equirectangular_to_cube(sky.into(), 4);
+        let top = faces[2].to_rgba32f();
+        let bottom = faces[3].to_rgba32f();
+        assert!(top.pixels().all(|texel| texel[0] > 3.9));
+        assert!(bottom.pixels().all(|texel| texel[0] < 0.1));
+
+        let importer = TextureImporter {
+            generate_mipmaps: false,
+            ..TextureImporter::new()
+        };
+        let (texture_asset, texels) = importer
+            .convert_layers(faces, TextureDimension::Cube)
+            .unwrap();
+        assert_eq!(texture_asset.texture_format, TextureFormat::RGBA16F);
+        assert_eq!(texels.len(), 6 * 4 * 4 * 8);
+    }
+}
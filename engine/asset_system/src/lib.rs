@@ -3,11 +3,71 @@
 
 use serde::{Deserialize, Serialize};
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
+mod block_compression;
+mod cache;
+mod error;
+mod guid;
+mod import;
+mod loader;
+mod mapped;
+mod material;
+mod mesh;
+mod pack;
+mod registry;
+mod scene;
+mod server;
+mod shader;
+mod stream;
 mod texture;
+mod watcher;
+
+pub use cache::{BuildCache, BuildKey, BUILD_CACHE_FILE_NAME};
+pub use error::AssetError;
+pub use guid::AssetGuid;
+pub use import::{
+    BlockCompression, GltfAssets, GltfImporter, ImportSettings, MeshProcessing, MeshSettings,
+    ObjAssets, ObjImporter, ShaderCompiler, ShaderSource, TextureImporter, TextureSettings,
+    TextureUsage, UpAxis, FOLDER_IMPORT_SETTINGS_FILE_NAME, GLSL_SOURCE_EXTENSIONS,
+    GLTF_SOURCE_EXTENSIONS, IMPORT_SETTINGS_EXTENSION, OBJ_SOURCE_EXTENSIONS,
+    TEXTURE_SOURCE_EXTENSIONS,
+};
+pub use loader::{AsyncAssetLoader, CompletedLoad, LoadPriority, LoadTicket, LoadedAsset};
+pub use mapped::MappedAssetFile;
+pub use material::{
+    AlphaMode, BlendMode, CompareOp, CullMode, MaterialAsset, MaterialParameter, RenderState,
+};
+pub use mesh::{
+    Bounds, IndexFormat, Lod, Mesh, MeshAsset, Meshlet, Submesh, Vertex, MESHLET_SIZE, VERTEX_SIZE,
+};
+pub use pack::{AssetPack, AssetPackBuilder, PackEntry, ASSET_PACK_EXTENSION};
+pub use registry::{AssetRegistry, ScanReport, ASSET_REGISTRY_FILE_NAME};
+pub use scene::{SceneAsset, SceneNode};
+pub use server::{Asset, AssetEvent, AssetId, AssetServer, Handle, LoadState, WeakHandle};
+pub use shader::{
+    AttributeType, BlockMember, DescriptorBinding, DescriptorType, EntryPoint, InterfaceVariable,
+    PushConstantRange, ScalarType, Shader, ShaderAsset, ShaderStage,
+};
+pub use stream::{AssetReader, LazyAssetFile, STREAM_CHUNK_SIZE};
+pub use texture::{
+    ColorSpace, CubeFace, Texture, TextureAsset, TextureDimension, TextureFormat, CUBE_FACES,
+};
+pub use watcher::{AssetWatcher, DEFAULT_DEBOUNCE_DURATION};
 
 const CURRENT_ASSET_SYSTEM_VERSION: &str = "0.1.0";
+pub const ASSET_FILE_EXTENSION: &str = "bin";
+
+// Asset files start with the magic, followed by the size of the RON header, the size of the
+// uncompressed blob, the header itself and the blob aligned to `RAW_DATA_ALIGNMENT`. The blob is
+// stored as is or as an LZ4 frame, depending on the compression mode.
+pub const ASSET_FILE_MAGIC: &[u8; 4] = b"RAST";
+const RAW_DATA_ALIGNMENT: usize = 16;
+// Headers only hold metadata, so a larger size means the asset file is corrupted. It caps what
+// is allocated before the header could be read.
+const MAX_HEADER_SIZE: usize = 64 * 1024 * 1024;
 
 // TODO: Rename in the future, name of the trait looks not so good.
 pub trait Packaging {
@@ -17,12 +77,14 @@ pub trait Packaging {
         path: &str,
         raw_data: Vec<u8>,
         compression_mode: CompressionMode,
-    ) -> Result<AssetFile, String>;
+    ) -> Result<AssetFile, AssetError>;
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CompressionMode {
+    // The blob is stored as is, so it can be memory-mapped without copies.
+    None = 0,
     Default = 4,
     Fast = 2,
     HighCompression = 7,
@@ -30,21 +92,32 @@ pub enum CompressionMode {
 }
 
 #[repr(u8)]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AssetType {
     Mesh = 0,
     Texture = 1,
+    Material = 2,
+    Scene = 3,
+    Shader = 4,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AssetFile {
+    guid: AssetGuid,
     name: String,
     path: String,
     asset_type: AssetType,
     compression_mode: CompressionMode,
     version: String,
     metadata: String,
+    // Assets that have to be loaded before this one, e.g. textures of a material.
+    #[serde(default)]
+    dependencies: Vec<AssetGuid>,
+    // Missing in headers of uncompressed asset files, the blob follows the header.
+    #[serde(default)]
     raw_data: Vec<u8>,
+    // xxHash64 of `metadata` and `raw_data`, verified on every load.
+    checksum: u64,
 }
 
 impl AssetFile {
@@ -54,64 +127,398 @@ impl AssetFile {
         path: &str,
         raw_data: Vec<u8>,
         compression_mode: CompressionMode,
-    ) -> Result<AssetFile, String> {
+    ) -> Result<AssetFile, AssetError> {
         asset.pack(name, path, raw_data, compression_mode)
     }
 
-    fn save_content(&self, asset_file: File) -> Result<(), String> {
-        let serialized = ron::to_string(self).map_err(|e| e.to_string())?;
-        let mut serialized_data_encoder = lz4::EncoderBuilder::new()
-            .level(self.compression_mode as u32)
-            .build(asset_file)
-            .map_err(|e| e.to_string())?;
-        std::io::copy(&mut serialized.as_bytes(), &mut serialized_data_encoder)
-            .map_err(|e| e.to_string())?;
-        let (_output, result) = serialized_data_encoder.finish();
-
-        result.map_err(|e| e.to_string())
-    }
-
-    pub fn save_asset_file(&self) -> Result<(), String> {
-        match File::options().write(true).truncate(false).open(&self.path) {
-            Ok(asset_file) => self.save_content(asset_file),
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::NotFound => {
-                    let asset_file = File::create(&self.path)
-                        .map_err(|e| format!("Error: Failed to create an asset file {e:?}"))?;
-
-                    self.save_content(asset_file)
-                }
-                e => {
-                    let e = format!("Error: Failed to save an asset file: {e:?}");
-                    Err(e)
-                }
-            },
+    pub(crate) fn from_raw_parts(
+        name: &str,
+        path: &str,
+        asset_type: AssetType,
+        compression_mode: CompressionMode,
+        metadata: String,
+        raw_data: Vec<u8>,
+    ) -> Self {
+        let checksum = checksum(&metadata, &raw_data);
+
+        Self {
+            guid: AssetGuid::new(),
+            name: name.to_string(),
+            path: path.to_string(),
+            asset_type,
+            compression_mode,
+            version: CURRENT_ASSET_SYSTEM_VERSION.to_string(),
+            metadata,
+            dependencies: Vec::new(),
+            raw_data,
+            checksum,
+        }
+    }
+
+    pub fn with_guid(mut self, guid: AssetGuid) -> Self {
+        self.guid = guid;
+        self
+    }
+
+    pub fn with_dependencies(mut self, dependencies: Vec<AssetGuid>) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    // Writes the asset file in the same layout as it's saved to the disk. Returns the size of the
+    // asset file before compression of the blob.
+    pub fn write_to<W: Write>(&self, mut asset_file: W) -> Result<u64, AssetError> {
+        let header = ron::to_string(&AssetFileHeader::from(self))?;
+        if header.len() > MAX_HEADER_SIZE {
+            return Err(AssetError::Serialization(format!(
+                "The header of the asset file has {} bytes, more than it can be read with.",
+                header.len()
+            )));
         }
+        let padding = raw_data_offset(header.len()) - header_end(header.len());
+
+        asset_file.write_all(ASSET_FILE_MAGIC)?;
+        asset_file.write_all(&(header.len() as u64).to_le_bytes())?;
+        asset_file.write_all(&(self.raw_data.len() as u64).to_le_bytes())?;
+        asset_file.write_all(header.as_bytes())?;
+        asset_file.write_all(&[0; RAW_DATA_ALIGNMENT][..padding])?;
+
+        match self.compression_mode {
+            CompressionMode::None => asset_file.write_all(&self.raw_data)?,
+            compression_mode => {
+                let mut raw_data_encoder = lz4::EncoderBuilder::new()
+                    .level(compression_mode as u32)
+                    .build(asset_file)?;
+                raw_data_encoder.write_all(&self.raw_data)?;
+                let (_output, result) = raw_data_encoder.finish();
+
+                result.map_err(AssetError::Io)?;
+            }
+        }
+
+        Ok((raw_data_offset(header.len()) + self.raw_data.len()) as u64)
+    }
+
+    // Saves the asset file to its `path`.
+    pub fn save_asset_file(&self) -> Result<(), AssetError> {
+        self.save_to(&self.path)
     }
 
-    pub fn load_asset_file<T: AsRef<std::path::Path> + ?Sized>(
-        path: &T,
-    ) -> Result<AssetFile, String> {
-        let asset_file = File::options()
-            .write(false)
-            .read(true)
-            .open(path)
-            .map_err(|e| format!("Error: Failed to load an asset file: {:?}", e.kind()))?;
+    // Readers never see a partially written file, see `save_atomically`. Missing parent
+    // directories are created.
+    pub fn save_to<T: AsRef<Path> + ?Sized>(&self, path: &T) -> Result<(), AssetError> {
+        save_atomically(path.as_ref(), |writer| self.write_to(writer).map(|_| ()))
+    }
+
+    // Saves the asset file to its `path` resolved inside the project `root`, see
+    // `project_path`. Returns the path the asset file was saved to.
+    pub fn save_to_project<T: AsRef<Path> + ?Sized>(
+        &self,
+        root: &T,
+    ) -> Result<PathBuf, AssetError> {
+        let path = project_path(root, &self.path)?;
+        self.save_to(&path)?;
+
+        Ok(path)
+    }
+
+    pub fn load_asset_file<T: AsRef<Path> + ?Sized>(path: &T) -> Result<AssetFile, AssetError> {
+        let asset_file = File::options().write(false).read(true).open(path)?;
+
+        Self::read_from(asset_file)
+    }
+
+    // Reads an asset file from anything it was written to with `write_to`, e.g. memory or a
+    // pipe. `LazyAssetFile` reads the header only and leaves the blob for later.
+    pub fn read_from<R: Read>(mut asset_file: R) -> Result<AssetFile, AssetError> {
+        let mut magic = [0; 4];
+        asset_file.read_exact(&mut magic)?;
+        match &magic == ASSET_FILE_MAGIC {
+            true => AssetReader::new(magic.as_slice().chain(asset_file))?.into_asset_file(),
+            false => Self::load_legacy_content(magic.as_slice().chain(asset_file)),
+        }
+    }
 
+    // Asset files saved before the header was introduced are a single LZ4 frame of RON.
+    fn load_legacy_content<R: Read>(asset_file: R) -> Result<AssetFile, AssetError> {
         let mut decompressed_raw_data = vec![];
         lz4::Decoder::new(asset_file)
-            .map_err(|e| e.to_string())?
+            .map_err(|e| AssetError::Compression(e.to_string()))?
             .read_to_end(&mut decompressed_raw_data)
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| AssetError::Compression(e.to_string()))?;
 
-        let decompressed_data =
-            std::str::from_utf8(&decompressed_raw_data).map_err(|e| e.to_string())?;
+        let decompressed_data = std::str::from_utf8(&decompressed_raw_data)
+            .map_err(|e| AssetError::Serialization(e.to_string()))?;
 
-        let asset_file =
-            ron::de::from_bytes(decompressed_data.as_bytes()).map_err(|e| e.to_string())?;
+        let asset_file: AssetFile = ron::de::from_bytes(decompressed_data.as_bytes())?;
+        asset_file.verify_checksum()?;
 
         Ok(asset_file)
     }
+
+    pub(crate) fn parse_header(header: &[u8]) -> Result<AssetFile, AssetError> {
+        let asset_file: AssetFile = ron::de::from_bytes(header)?;
+        match asset_file.raw_data.is_empty() {
+            true => Ok(asset_file),
+            false => Err(AssetError::Serialization(
+                "Header of an asset file contains raw data.".to_string(),
+            )),
+        }
+    }
+
+    pub fn guid(&self) -> AssetGuid {
+        self.guid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn asset_type(&self) -> AssetType {
+        self.asset_type
+    }
+
+    pub fn compression_mode(&self) -> CompressionMode {
+        self.compression_mode
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn metadata(&self) -> &str {
+        &self.metadata
+    }
+
+    pub fn dependencies(&self) -> &[AssetGuid] {
+        &self.dependencies
+    }
+
+    pub fn raw_data(&self) -> &[u8] {
+        &self.raw_data
+    }
+
+    pub fn into_raw_data(self) -> Vec<u8> {
+        self.raw_data
+    }
+
+    pub(crate) fn expect_asset_type(&self, expected: AssetType) -> Result<(), AssetError> {
+        match self.asset_type == expected {
+            true => Ok(()),
+            false => Err(AssetError::UnexpectedAssetType {
+                expected,
+                actual: self.asset_type,
+            }),
+        }
+    }
+
+    pub fn verify_checksum(&self) -> Result<(), AssetError> {
+        self.verify_checksum_of(&self.raw_data)
+    }
+
+    // Verifies the checksum against a blob stored outside of the asset file, e.g. in a mapping.
+    pub(crate) fn verify_checksum_of(&self, raw_data: &[u8]) -> Result<(), AssetError> {
+        let mut hasher = checksum_hasher(&self.metadata);
+        hasher.update(raw_data);
+
+        self.verify_digest(hasher.digest())
+    }
+
+    pub(crate) fn verify_digest(&self, actual: u64) -> Result<(), AssetError> {
+        match actual == self.checksum {
+            true => Ok(()),
+            false => Err(AssetError::ChecksumMismatch {
+                expected: self.checksum,
+                actual,
+            }),
+        }
+    }
+}
+
+// The same fields as `AssetFile` without `raw_data`, written before the blob.
+#[derive(Serialize)]
+struct AssetFileHeader<'a> {
+    guid: AssetGuid,
+    name: &'a str,
+    path: &'a str,
+    asset_type: AssetType,
+    compression_mode: CompressionMode,
+    version: &'a str,
+    metadata: &'a str,
+    dependencies: &'a [AssetGuid],
+    checksum: u64,
+}
+
+impl<'a> From<&'a AssetFile> for AssetFileHeader<'a> {
+    fn from(asset_file: &'a AssetFile) -> Self {
+        Self {
+            guid: asset_file.guid,
+            name: &asset_file.name,
+            path: &asset_file.path,
+            asset_type: asset_file.asset_type,
+            compression_mode: asset_file.compression_mode,
+            version: &asset_file.version,
+            metadata: &asset_file.metadata,
+            dependencies: &asset_file.dependencies,
+            checksum: asset_file.checksum,
+        }
+    }
+}
+
+// Reads the sizes of the header and of the uncompressed blob that follow the magic.
+pub(crate) fn parse_sizes(sizes: &[u8]) -> Result<(usize, u64), AssetError> {
+    let header_size = u64::from_le_bytes(sizes[..8].try_into().unwrap());
+    let raw_data_size = u64::from_le_bytes(sizes[8..16].try_into().unwrap());
+
+    match usize::try_from(header_size) {
+        Ok(header_size) if header_size <= MAX_HEADER_SIZE => Ok((header_size, raw_data_size)),
+        _ => Err(AssetError::Serialization(format!(
+            "The header of the asset file has an invalid size of {header_size} bytes."
+        ))),
+    }
+}
+
+// Offset of the end of the RON header of an asset file.
+pub(crate) fn header_end(header_size: usize) -> usize {
+    ASSET_FILE_MAGIC.len() + 2 * std::mem::size_of::<u64>() + header_size
+}
+
+pub(crate) fn raw_data_offset(header_size: usize) -> usize {
+    header_end(header_size).next_multiple_of(RAW_DATA_ALIGNMENT)
+}
+
+fn checksum(metadata: &str, raw_data: &[u8]) -> u64 {
+    let mut hasher = checksum_hasher(metadata);
+    hasher.update(raw_data);
+
+    hasher.digest()
+}
+
+// The blob is fed into the hasher separately, so it can be hashed chunk by chunk.
+pub(crate) fn checksum_hasher(metadata: &str) -> xxhash_rust::xxh64::Xxh64 {
+    let mut hasher = xxhash_rust::xxh64::Xxh64::new(0);
+    hasher.update(metadata.as_bytes());
+
+    hasher
+}
+
+// Loads every asset file under `directory` (recursively) and returns the ones that failed to load,
+// e.g. truncated or corrupted files.
+pub fn verify<T: AsRef<Path> + ?Sized>(
+    directory: &T,
+) -> Result<Vec<(PathBuf, AssetError)>, AssetError> {
+    let corrupted = asset_file_paths(directory)?
+        .into_iter()
+        .filter_map(|path| match AssetFile::load_asset_file(&path) {
+            Ok(_) => None,
+            Err(e) => Some((path, e)),
+        })
+        .collect();
+
+    Ok(corrupted)
+}
+
+// Writes to a temporary file next to `path`, which then replaces `path`. Missing parent
+// directories are created.
+pub(crate) fn save_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), AssetError>,
+) -> Result<(), AssetError> {
+    static SAVE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| AssetError::InvalidPath(path.to_path_buf()))?;
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(directory)?;
+
+    // Unique per process and save, so concurrent saves of the same path don't collide.
+    let temporary_path = directory.join(format!(
+        ".{}.{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        SAVE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+        write(&mut writer)?;
+        let file = writer
+            .into_inner()
+            .map_err(|e| AssetError::Io(e.into_error()))?;
+        file.sync_all()?;
+        std::fs::rename(&temporary_path, path)?;
+
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary_path);
+    }
+
+    result
+}
+
+// Recursively collects paths of asset files under `directory`, sorted for a deterministic order.
+pub(crate) fn asset_file_paths<T: AsRef<Path> + ?Sized>(
+    directory: &T,
+) -> Result<Vec<PathBuf>, AssetError> {
+    let mut paths = Vec::new();
+    let mut directories = vec![directory.as_ref().to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.extension().is_some_and(|e| e == ASSET_FILE_EXTENSION) {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
+// Resolves an asset path inside the project `root`. Relative paths are relative to `root`, even
+// if they start with the name of `root`. Absolute ones have to start with `root` as given, so it
+// has to be absolute for them. `..` must not leave `root`.
+pub fn project_path<R, T>(root: &R, path: &T) -> Result<PathBuf, AssetError>
+where
+    R: AsRef<Path> + ?Sized,
+    T: AsRef<Path> + ?Sized,
+{
+    let root = root.as_ref();
+    let path = path.as_ref();
+    let relative_path = match path.is_absolute() {
+        true => path
+            .strip_prefix(root)
+            .map_err(|_| AssetError::InvalidPath(path.to_path_buf()))?,
+        false => path,
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in relative_path.components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::CurDir => (),
+            Component::ParentDir if resolved.pop() => (),
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(AssetError::InvalidPath(path.to_path_buf()))
+            }
+        }
+    }
+    if resolved.as_os_str().is_empty() {
+        return Err(AssetError::InvalidPath(path.to_path_buf()));
+    }
+
+    Ok(root.join(resolved))
 }
 
 #[cfg(test)]
@@ -119,21 +526,22 @@ mod tests {
     use super::*;
 
     const PREPARED_ASSET_FILE_PATH: &str = "src/test_asset_files/asset_file.bin";
+    const ASSET_GUID: AssetGuid = AssetGuid::from_u128(0x6a1f3c2e_8d4b_4e7a_9b0c_5f2d1e3a4b6c);
     const CONTENT_OF_ASSET_FILE: &str =
-        "(name:\"asset_file\",path:\"src/test_asset_files/asset_file.bin\",asset_type:Mesh,compression_mode:VeryHighCompression,version:\"0.1.0\",metadata:\"HI\",raw_data:[1,2,3])";
+        "(guid:\"6a1f3c2e-8d4b-4e7a-9b0c-5f2d1e3a4b6c\",name:\"asset_file\",path:\"src/test_asset_files/asset_file.bin\",asset_type:Mesh,compression_mode:VeryHighCompression,version:\"0.1.0\",metadata:\"HI\",dependencies:[],raw_data:[1,2,3],checksum:8168166387236505387)";
 
     #[test]
     #[cfg_attr(miri, ignore)]
     fn save_asset_file() {
-        let asset_file = AssetFile {
-            name: "asset_file".to_string(),
-            path: PREPARED_ASSET_FILE_PATH.to_string(),
-            asset_type: AssetType::Mesh,
-            compression_mode: CompressionMode::VeryHighCompression,
-            version: CURRENT_ASSET_SYSTEM_VERSION.to_string(),
-            metadata: "HI".to_string(),
-            raw_data: vec![1, 2, 3],
-        };
+        let mut asset_file = AssetFile::from_raw_parts(
+            "asset_file",
+            PREPARED_ASSET_FILE_PATH,
+            AssetType::Mesh,
+            CompressionMode::VeryHighCompression,
+            "HI".to_string(),
+            vec![1, 2, 3],
+        );
+        asset_file.guid = ASSET_GUID;
 
         asset_file.save_asset_file().unwrap();
     }
@@ -150,23 +558,36 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn load_legacy_asset_file() {
+        let asset_file =
+            AssetFile::load_asset_file("src/test_asset_files/legacy_asset_file.bin").unwrap();
+
+        assert_eq!(
+            &ron::to_string(&asset_file).unwrap(),
+            CONTENT_OF_ASSET_FILE,
+            "Asset files saved as a single LZ4 frame must still be loaded."
+        );
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn create_new_asset_and_read_it() {
         const NEW_ASSET_FILE_PATH: &str = "src/test_asset_files/new_asset_file.bin";
         const NEW_ASSET_NAME: &str = "new_asset_file";
         const CONTENT_NEW_ASSET_FILE: &str =
-        "(name:\"new_asset_file\",path:\"src/test_asset_files/new_asset_file.bin\",asset_type:Mesh,compression_mode:VeryHighCompression,version:\"0.1.0\",metadata:\"HI\",raw_data:[1,2,3])";
+        "(guid:\"6a1f3c2e-8d4b-4e7a-9b0c-5f2d1e3a4b6c\",name:\"new_asset_file\",path:\"src/test_asset_files/new_asset_file.bin\",asset_type:Mesh,compression_mode:VeryHighCompression,version:\"0.1.0\",metadata:\"HI\",dependencies:[],raw_data:[1,2,3],checksum:8168166387236505387)";
 
-        let asset_file = AssetFile {
-            name: NEW_ASSET_NAME.to_string(),
-            path: NEW_ASSET_FILE_PATH.to_string(),
-            asset_type: AssetType::Mesh,
-            compression_mode: CompressionMode::VeryHighCompression,
-            version: CURRENT_ASSET_SYSTEM_VERSION.to_string(),
-            metadata: "HI".to_string(),
-            raw_data: vec![1, 2, 3],
-        };
+        let mut asset_file = AssetFile::from_raw_parts(
+            NEW_ASSET_NAME,
+            NEW_ASSET_FILE_PATH,
+            AssetType::Mesh,
+            CompressionMode::VeryHighCompression,
+            "HI".to_string(),
+            vec![1, 2, 3],
+        );
+        asset_file.guid = ASSET_GUID;
 
         asset_file.save_asset_file().unwrap();
         let asset_file = AssetFile::load_asset_file(NEW_ASSET_FILE_PATH).unwrap();
@@ -179,4 +600,151 @@ mod tests {
 
         std::fs::remove_file(NEW_ASSET_FILE_PATH).unwrap();
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn write_and_read_asset_file_in_memory() {
+        for compression_mode in [CompressionMode::None, CompressionMode::Default] {
+            let asset_file = AssetFile::from_raw_parts(
+                "in_memory",
+                "in_memory.bin",
+                AssetType::Texture,
+                compression_mode,
+                "HI".to_string(),
+                vec![4; 1000],
+            )
+            .with_dependencies(vec![AssetGuid::from_name("dependency")]);
+
+            let mut stored = Vec::new();
+            let size = asset_file.write_to(&mut stored).unwrap();
+            if compression_mode == CompressionMode::None {
+                assert_eq!(size, stored.len() as u64);
+            }
+
+            let read = AssetFile::read_from(stored.as_slice()).unwrap();
+            assert_eq!(read.name(), "in_memory");
+            assert_eq!(read.compression_mode(), compression_mode);
+            assert_eq!(read.dependencies(), asset_file.dependencies());
+            assert_eq!(read.raw_data(), asset_file.raw_data());
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn overwrite_asset_file_atomically() {
+        const DIRECTORY_PATH: &str = "src/test_asset_files/overwrite";
+        const ASSET_FILE_PATH: &str = "src/test_asset_files/overwrite/nested/asset_file.bin";
+
+        let asset_file = |raw_data: Vec<u8>| {
+            AssetFile::from_raw_parts(
+                "asset_file",
+                ASSET_FILE_PATH,
+                AssetType::Mesh,
+                CompressionMode::None,
+                "HI".to_string(),
+                raw_data,
+            )
+        };
+        asset_file(vec![7; 1024]).save_asset_file().unwrap();
+        let smaller = asset_file(vec![1, 2, 3]);
+        smaller.save_asset_file().unwrap();
+
+        // Nothing of the larger asset file is left behind, neither are temporary files.
+        let mut saved = Vec::new();
+        smaller.write_to(&mut saved).unwrap();
+        assert_eq!(std::fs::read(ASSET_FILE_PATH).unwrap(), saved);
+        assert_eq!(
+            AssetFile::load_asset_file(ASSET_FILE_PATH)
+                .unwrap()
+                .raw_data(),
+            [1, 2, 3]
+        );
+        assert_eq!(
+            std::fs::read_dir("src/test_asset_files/overwrite/nested")
+                .unwrap()
+                .count(),
+            1
+        );
+
+        std::fs::remove_dir_all(DIRECTORY_PATH).unwrap();
+    }
+
+    #[test]
+    fn resolve_project_paths() {
+        let root = Path::new("project");
+        assert_eq!(
+            project_path(root, "textures/./brick.bin").unwrap(),
+            root.join("textures/brick.bin")
+        );
+        assert_eq!(
+            project_path(root, "project/meshes/../brick.bin").unwrap(),
+            root.join("project/brick.bin")
+        );
+        let absolute_root = Path::new(match cfg!(windows) {
+            true => "C:\\project",
+            false => "/project",
+        });
+        assert_eq!(
+            project_path(absolute_root, &absolute_root.join("meshes/../brick.bin")).unwrap(),
+            absolute_root.join("brick.bin")
+        );
+        assert!(project_path(root, &absolute_root.join("brick.bin")).is_err());
+        assert!(project_path(root, "../brick.bin").is_err());
+        assert!(project_path(root, "textures/../../brick.bin").is_err());
+        assert!(project_path(root, "/elsewhere/brick.bin").is_err());
+        assert!(project_path(root, "").is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn load_corrupted_asset_file() {
+        const CORRUPTED_ASSET_FILE_PATH: &str = "src/test_asset_files/corrupted_asset_file.bin";
+
+        let mut asset_file = AssetFile::from_raw_parts(
+            "corrupted_asset_file",
+            CORRUPTED_ASSET_FILE_PATH,
+            AssetType::Mesh,
+            CompressionMode::Fast,
+            "HI".to_string(),
+            vec![1, 2, 3],
+        );
+        asset_file.raw_data[0] = 42;
+        asset_file.save_asset_file().unwrap();
+
+        let result = AssetFile::load_asset_file(CORRUPTED_ASSET_FILE_PATH);
+        std::fs::remove_file(CORRUPTED_ASSET_FILE_PATH).unwrap();
+
+        assert!(
+            matches!(result, Err(AssetError::ChecksumMismatch { .. })),
+            "Corrupted asset file must fail the checksum verification."
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn verify_directory_of_asset_files() {
+        const DIRECTORY_PATH: &str = "src/test_asset_files/verify";
+        const VALID_ASSET_FILE_PATH: &str = "src/test_asset_files/verify/valid.bin";
+        const TRUNCATED_ASSET_FILE_PATH: &str = "src/test_asset_files/verify/truncated.bin";
+
+        std::fs::create_dir_all(DIRECTORY_PATH).unwrap();
+        AssetFile::from_raw_parts(
+            "valid",
+            VALID_ASSET_FILE_PATH,
+            AssetType::Mesh,
+            CompressionMode::Fast,
+            "HI".to_string(),
+            vec![1, 2, 3],
+        )
+        .save_asset_file()
+        .unwrap();
+        let content = std::fs::read(VALID_ASSET_FILE_PATH).unwrap();
+        std::fs::write(TRUNCATED_ASSET_FILE_PATH, &content[..content.len() / 2]).unwrap();
+
+        let corrupted = verify(DIRECTORY_PATH).unwrap();
+        std::fs::remove_dir_all(DIRECTORY_PATH).unwrap();
+
+        assert_eq!(corrupted.len(), 1);
+        assert_eq!(corrupted[0].0, Path::new(TRUNCATED_ASSET_FILE_PATH));
+    }
 }
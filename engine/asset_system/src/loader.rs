@@ -0,0 +1,433 @@
+use crate::{Asset, AssetError, AssetFile, AssetGuid};
+use std::any::Any;
+use std::collections::BinaryHeap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+pub(crate) type Convert =
+    Arc<dyn Fn(AssetFile) -> Result<Arc<dyn Any + Send + Sync>, AssetError> + Send + Sync>;
+
+pub(crate) fn converter<T: Asset>() -> Convert {
+    Arc::new(|asset_file| {
+        T::from_asset_file(asset_file).map(|asset| Arc::new(asset) as Arc<dyn Any + Send + Sync>)
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LoadPriority {
+    Low = 0,
+    Normal = 1,
+    High = 2,
+}
+
+#[derive(Default)]
+struct Progress {
+    cancelled: AtomicBool,
+    read_bytes: AtomicU64,
+    total_bytes: AtomicU64,
+    finished: AtomicBool,
+}
+
+// Tracks a queued load, dropping the ticket doesn't cancel the load.
+#[derive(Clone)]
+pub struct LoadTicket {
+    id: u64,
+    progress: Arc<Progress>,
+}
+
+impl LoadTicket {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    // The load is skipped if it hasn't been started yet, otherwise it's interrupted during reading.
+    pub fn cancel(&self) {
+        self.progress.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.progress.cancelled.load(Ordering::Acquire)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress.finished.load(Ordering::Acquire)
+    }
+
+    // Fraction of the asset file that was read, in the range from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.is_finished() {
+            return 1.0;
+        }
+
+        match self.progress.total_bytes.load(Ordering::Acquire) {
+            0 => 0.0,
+            total_bytes => {
+                self.progress.read_bytes.load(Ordering::Acquire) as f32 / total_bytes as f32
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for LoadTicket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoadTicket")
+            .field("id", &self.id)
+            .field("progress", &self.progress())
+            .finish()
+    }
+}
+
+pub struct LoadedAsset {
+    pub guid: AssetGuid,
+    pub dependencies: Vec<AssetGuid>,
+    asset: Arc<dyn Any + Send + Sync>,
+}
+
+impl LoadedAsset {
+    pub fn downcast<T: Asset>(&self) -> Option<Arc<T>> {
+        Arc::clone(&self.asset).downcast::<T>().ok()
+    }
+
+    pub(crate) fn into_any(self) -> Arc<dyn Any + Send + Sync> {
+        self.asset
+    }
+}
+
+pub struct CompletedLoad {
+    pub ticket_id: u64,
+    pub path: PathBuf,
+    pub result: Result<LoadedAsset, AssetError>,
+}
+
+struct Request {
+    priority: LoadPriority,
+    id: u64,
+    path: PathBuf,
+    progress: Arc<Progress>,
+    convert: Convert,
+}
+
+// Higher priority first, then first in first out.
+impl Ord for Request {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl PartialOrd for Request {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Request {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Request {}
+
+#[derive(Default)]
+struct Queue {
+    requests: BinaryHeap<Request>,
+    is_shutdown: bool,
+}
+
+// Reads, decompresses and deserializes asset files on a pool of worker threads,
+// completed loads are handed back to the owner's thread through `poll`.
+pub struct AsyncAssetLoader {
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+    workers: Vec<JoinHandle<()>>,
+    completed: mpsc::Receiver<CompletedLoad>,
+    next_id: u64,
+    in_flight: usize,
+}
+
+impl AsyncAssetLoader {
+    pub fn new(worker_count: usize) -> Self {
+        let queue = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        let (sender, completed) = mpsc::channel();
+
+        let workers = (0..worker_count.max(1))
+            .map(|i| {
+                let queue = Arc::clone(&queue);
+                let sender = sender.clone();
+
+                std::thread::Builder::new()
+                    .name(format!("asset-loader-{i}"))
+                    .spawn(move || Self::work(&queue, &sender))
+                    .expect("Error: Failed to spawn an asset loader thread.")
+            })
+            .collect();
+
+        Self {
+            queue,
+            workers,
+            completed,
+            next_id: 0,
+            in_flight: 0,
+        }
+    }
+
+    pub fn load<T: Asset, P: AsRef<Path> + ?Sized>(
+        &mut self,
+        path: &P,
+        priority: LoadPriority,
+    ) -> LoadTicket {
+        self.load_with(path.as_ref().to_path_buf(), priority, converter::<T>())
+    }
+
+    // Returns loads that were completed, failed or cancelled since the last call, never blocks.
+    pub fn poll(&mut self) -> Vec<CompletedLoad> {
+        let completed = self.completed.try_iter().collect::<Vec<_>>();
+        self.in_flight -= completed.len();
+
+        completed
+    }
+
+    // Count of loads that weren't handed back by `poll` yet.
+    pub fn pending(&self) -> usize {
+        self.in_flight
+    }
+
+    pub(crate) fn load_with(
+        &mut self,
+        path: PathBuf,
+        priority: LoadPriority,
+        convert: Convert,
+    ) -> LoadTicket {
+        let ticket = LoadTicket {
+            id: self.next_id,
+            progress: Arc::default(),
+        };
+        self.next_id += 1;
+        self.in_flight += 1;
+
+        let (queue, condvar) = &*self.queue;
+        queue.lock().unwrap().requests.push(Request {
+            priority,
+            id: ticket.id,
+            path,
+            progress: Arc::clone(&ticket.progress),
+            convert,
+        });
+        condvar.notify_one();
+
+        ticket
+    }
+
+    fn work(queue: &(Mutex<Queue>, Condvar), sender: &mpsc::Sender<CompletedLoad>) {
+        let (queue, condvar) = queue;
+
+        loop {
+            let request = {
+                let mut queue = queue.lock().unwrap();
+                loop {
+                    if queue.is_shutdown {
+                        return;
+                    }
+                    match queue.requests.pop() {
+                        Some(request) => break request,
+                        None => queue = condvar.wait(queue).unwrap(),
+                    }
+                }
+            };
+
+            let result = Self::load_request(&request.path, &request.progress)
+                .and_then(|asset_file| {
+                    let guid = asset_file.guid();
+                    let dependencies = asset_file.dependencies().to_vec();
+                    (request.convert)(asset_file).map(|asset| LoadedAsset {
+                        guid,
+                        dependencies,
+                        asset,
+                    })
+                })
+                .map_err(
+                    |e| match request.progress.cancelled.load(Ordering::Acquire) {
+                        true => AssetError::Cancelled,
+                        false => e,
+                    },
+                );
+            request.progress.finished.store(true, Ordering::Release);
+
+            let completed = CompletedLoad {
+                ticket_id: request.id,
+                path: request.path,
+                result,
+            };
+            if sender.send(completed).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn load_request(path: &Path, progress: &Arc<Progress>) -> Result<AssetFile, AssetError> {
+        if progress.cancelled.load(Ordering::Acquire) {
+            return Err(AssetError::Cancelled);
+        }
+
+        let asset_file = std::fs::File::open(path)?;
+        progress
+            .total_bytes
+            .store(asset_file.metadata()?.len(), Ordering::Release);
+
+        AssetFile::read_from(ProgressReader {
+            reader: asset_file,
+            progress,
+        })
+    }
+}
+
+impl Default for AsyncAssetLoader {
+    fn default() -> Self {
+        Self::new(std::thread::available_parallelism().map_or(1, |count| count.get()))
+    }
+}
+
+impl Drop for AsyncAssetLoader {
+    fn drop(&mut self) {
+        let (queue, condvar) = &*self.queue;
+        queue.lock().unwrap().is_shutdown = true;
+        condvar.notify_all();
+
+        self.workers.drain(..).for_each(|worker| {
+            let _ = worker.join();
+        });
+    }
+}
+
+struct ProgressReader<'a, R> {
+    reader: R,
+    progress: &'a Progress,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.progress.cancelled.load(Ordering::Acquire) {
+            return Err(std::io::Error::other("Loading was cancelled."));
+        }
+
+        let read_bytes = self.reader.read(buf)?;
+        self.progress
+            .read_bytes
+            .fetch_add(read_bytes as u64, Ordering::AcqRel);
+
+        Ok(read_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssetServer, AssetType, CompressionMode, LoadState};
+
+    fn request(id: u64, priority: LoadPriority) -> Request {
+        Request {
+            priority,
+            id,
+            path: PathBuf::new(),
+            progress: Arc::default(),
+            convert: converter::<AssetFile>(),
+        }
+    }
+
+    #[test]
+    fn requests_are_ordered_by_priority() {
+        let mut requests = BinaryHeap::from([
+            request(0, LoadPriority::Low),
+            request(1, LoadPriority::Normal),
+            request(2, LoadPriority::High),
+            request(3, LoadPriority::Normal),
+        ]);
+
+        let order =
+            std::iter::from_fn(|| requests.pop().map(|request| request.id)).collect::<Vec<_>>();
+
+        assert_eq!(order, [2, 1, 3, 0]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn load_in_background() {
+        const ASSET_FILE_PATH: &str = "src/test_asset_files/async_asset_file.bin";
+
+        let asset_file = AssetFile::from_raw_parts(
+            "async_asset_file",
+            ASSET_FILE_PATH,
+            AssetType::Mesh,
+            CompressionMode::Fast,
+            String::new(),
+            vec![1, 2, 3],
+        );
+        asset_file.save_asset_file().unwrap();
+
+        let mut asset_server = AssetServer::new();
+        let handle = asset_server.load_async::<AssetFile, _>(ASSET_FILE_PATH, LoadPriority::High);
+        let missing_handle = asset_server
+            .load_async::<AssetFile, _>("src/test_asset_files/missing.bin", LoadPriority::Low);
+        assert_ne!(asset_server.load_state(handle.id()), LoadState::NotLoaded);
+
+        let mut updated = Vec::new();
+        while updated.len() < 2 {
+            updated.extend(asset_server.update());
+            std::thread::yield_now();
+        }
+        std::fs::remove_file(ASSET_FILE_PATH).unwrap();
+
+        assert_eq!(asset_server.load_state(handle.id()), LoadState::Loaded);
+        assert_eq!(asset_server.load_progress(handle.id()), 1.0);
+        assert_eq!(asset_server.get(&handle).unwrap().guid(), asset_file.guid());
+        assert_eq!(
+            asset_server.load_state(missing_handle.id()),
+            LoadState::Failed
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn cancel_queued_load() {
+        const ASSET_FILE_PATH: &str = "src/test_asset_files/asset_file.bin";
+
+        // The only worker is held by the first load, so the second one is still queued when it's
+        // cancelled.
+        let gate = Arc::new(Mutex::new(()));
+        let guard = gate.lock().unwrap();
+        let blocking_convert: Convert = {
+            let gate = Arc::clone(&gate);
+            Arc::new(move |asset_file| {
+                let _guard = gate.lock().unwrap();
+                converter::<AssetFile>()(asset_file)
+            })
+        };
+        let mut loader = AsyncAssetLoader::new(1);
+        let blocking_ticket = loader.load_with(
+            ASSET_FILE_PATH.into(),
+            LoadPriority::Normal,
+            blocking_convert,
+        );
+        let ticket = loader.load::<AssetFile, _>(ASSET_FILE_PATH, LoadPriority::Normal);
+        ticket.cancel();
+        drop(guard);
+
+        let started_at = std::time::Instant::now();
+        let mut completed = Vec::new();
+        while completed.len() < 2 && started_at.elapsed() < std::time::Duration::from_secs(10) {
+            completed.extend(loader.poll());
+            std::thread::yield_now();
+        }
+
+        assert!(ticket.is_finished());
+        assert_eq!(loader.pending(), 0);
+        assert_eq!(completed[0].ticket_id, blocking_ticket.id());
+        assert!(completed[0].result.is_ok());
+        assert_eq!(completed[1].ticket_id, ticket.id());
+        assert!(matches!(completed[1].result, Err(AssetError::Cancelled)));
+    }
+}
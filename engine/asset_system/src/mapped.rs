@@ -0,0 +1,185 @@
+use crate::{AssetError, AssetFile, AssetGuid, AssetType, CompressionMode, ASSET_FILE_MAGIC};
+use std::fs::File;
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+
+// An asset file whose blob is borrowed straight from a memory mapping of the file, so it can be
+// copied into staging buffers without intermediate heap copies. Only uncompressed asset files
+// are mapped, compressed ones are decompressed into memory as usual.
+pub struct MappedAssetFile {
+    // `raw_data` of the header is empty when the file is mapped.
+    header: AssetFile,
+    mapping: Option<(memmap2::Mmap, Range<usize>)>,
+}
+
+impl MappedAssetFile {
+    pub fn open<T: AsRef<Path> + ?Sized>(path: &T) -> Result<Self, AssetError> {
+        let mut asset_file = File::open(path)?;
+        let mut magic = [0; 4];
+        asset_file.read_exact(&mut magic)?;
+        if &magic != ASSET_FILE_MAGIC {
+            return Ok(Self {
+                header: AssetFile::load_asset_file(path)?,
+                mapping: None,
+            });
+        }
+
+        // SAFETY: Asset files are replaced as a whole rather than modified in place, and the blob
+        // is verified by the checksum once mapped.
+        #[allow(unsafe_code)]
+        let mapping = unsafe { memmap2::Mmap::map(&asset_file)? };
+
+        let truncated = || AssetError::Serialization("The asset file is truncated.".to_string());
+        let sizes = mapping
+            .get(magic.len()..crate::header_end(0))
+            .ok_or_else(truncated)?;
+        let (header_size, raw_data_size) = crate::parse_sizes(sizes)?;
+        let header = mapping
+            .get(crate::header_end(0)..crate::header_end(header_size))
+            .ok_or_else(truncated)?;
+        let header = AssetFile::parse_header(header)?;
+
+        if header.compression_mode() != CompressionMode::None {
+            return Ok(Self {
+                header: AssetFile::read_from(&mapping[..])?,
+                mapping: None,
+            });
+        }
+
+        let raw_data_offset = crate::raw_data_offset(header_size);
+        let raw_data_end = usize::try_from(raw_data_size)
+            .ok()
+            .and_then(|raw_data_size| raw_data_offset.checked_add(raw_data_size))
+            .ok_or_else(truncated)?;
+        let raw_data = raw_data_offset..raw_data_end;
+        header.verify_checksum_of(mapping.get(raw_data.clone()).ok_or_else(truncated)?)?;
+
+        Ok(Self {
+            header,
+            mapping: Some((mapping, raw_data)),
+        })
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        self.mapping.is_some()
+    }
+
+    pub fn guid(&self) -> AssetGuid {
+        self.header.guid()
+    }
+
+    pub fn name(&self) -> &str {
+        self.header.name()
+    }
+
+    pub fn asset_type(&self) -> AssetType {
+        self.header.asset_type()
+    }
+
+    pub fn compression_mode(&self) -> CompressionMode {
+        self.header.compression_mode()
+    }
+
+    pub fn metadata(&self) -> &str {
+        self.header.metadata()
+    }
+
+    pub fn dependencies(&self) -> &[AssetGuid] {
+        self.header.dependencies()
+    }
+
+    // Borrowed from the mapping, which lives as long as `self`.
+    pub fn raw_data(&self) -> &[u8] {
+        match &self.mapping {
+            Some((mapping, raw_data)) => &mapping[raw_data.clone()],
+            None => self.header.raw_data(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn map_uncompressed_asset_file() {
+        const UNCOMPRESSED_ASSET_FILE_PATH: &str = "src/test_asset_files/uncompressed.bin";
+        const COMPRESSED_ASSET_FILE_PATH: &str = "src/test_asset_files/compressed.bin";
+
+        let raw_data = (0..=255).collect::<Vec<u8>>();
+        for (path, compression_mode) in [
+            (UNCOMPRESSED_ASSET_FILE_PATH, CompressionMode::None),
+            (COMPRESSED_ASSET_FILE_PATH, CompressionMode::Fast),
+        ] {
+            AssetFile::from_raw_parts(
+                "mapped",
+                path,
+                AssetType::Texture,
+                compression_mode,
+                "HI".to_string(),
+                raw_data.clone(),
+            )
+            .save_asset_file()
+            .unwrap();
+        }
+
+        let uncompressed = MappedAssetFile::open(UNCOMPRESSED_ASSET_FILE_PATH).unwrap();
+        let compressed = MappedAssetFile::open(COMPRESSED_ASSET_FILE_PATH).unwrap();
+        let loaded = AssetFile::load_asset_file(UNCOMPRESSED_ASSET_FILE_PATH).unwrap();
+        std::fs::remove_file(COMPRESSED_ASSET_FILE_PATH).unwrap();
+
+        assert!(uncompressed.is_mapped());
+        assert!(!compressed.is_mapped());
+        assert_eq!(uncompressed.raw_data(), raw_data);
+        assert_eq!(uncompressed.raw_data().as_ptr() as usize % 16, 0);
+        assert_eq!(uncompressed.metadata(), "HI");
+        assert_eq!(compressed.raw_data(), raw_data);
+        assert_eq!(loaded.raw_data(), raw_data);
+        assert_eq!(loaded.guid(), uncompressed.guid());
+        drop(uncompressed);
+
+        let mut content = std::fs::read(UNCOMPRESSED_ASSET_FILE_PATH).unwrap();
+        *content.last_mut().unwrap() ^= 0xff;
+        std::fs::write(UNCOMPRESSED_ASSET_FILE_PATH, content).unwrap();
+        let result = MappedAssetFile::open(UNCOMPRESSED_ASSET_FILE_PATH);
+        std::fs::remove_file(UNCOMPRESSED_ASSET_FILE_PATH).unwrap();
+
+        assert!(matches!(result, Err(AssetError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn reject_invalid_sizes() {
+        const ASSET_FILE_PATH: &str = "src/test_asset_files/invalid_sizes.bin";
+
+        let asset_file = AssetFile::from_raw_parts(
+            "invalid_sizes",
+            ASSET_FILE_PATH,
+            AssetType::Texture,
+            CompressionMode::None,
+            String::new(),
+            vec![1, 2, 3],
+        );
+        let mut content = Vec::new();
+        asset_file.write_to(&mut content).unwrap();
+
+        // Sizes of the header and of the blob that overflow or lie past the end of the file.
+        let mut results = Vec::new();
+        for (offset, size) in [(4, u64::MAX), (4, 1 << 20), (12, u64::MAX)] {
+            let mut corrupted = content.clone();
+            corrupted[offset..offset + 8].copy_from_slice(&size.to_le_bytes());
+            std::fs::write(ASSET_FILE_PATH, corrupted).unwrap();
+            results.push(MappedAssetFile::open(ASSET_FILE_PATH).map(|_| ()));
+        }
+        std::fs::remove_file(ASSET_FILE_PATH).unwrap();
+
+        for result in results {
+            assert!(
+                matches!(result, Err(AssetError::Serialization(_))),
+                "{result:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use crate::{Asset, AssetError, AssetFile, AssetGuid, AssetType, BlockMember, CompressionMode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    // Texels with alpha below `alpha_cutoff` are discarded.
+    Mask,
+    Blend,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    // Source over destination by the source alpha.
+    Alpha,
+    // Colors are premultiplied by their alpha.
+    Premultiplied,
+    Additive,
+    Multiply,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CullMode {
+    None,
+    Front,
+    #[default]
+    Back,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CompareOp {
+    Never,
+    Less,
+    Equal,
+    #[default]
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+// Fixed-function state of the pipeline a material is drawn with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderState {
+    pub blend_mode: BlendMode,
+    pub cull_mode: CullMode,
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub depth_compare: CompareOp,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            blend_mode: BlendMode::default(),
+            cull_mode: CullMode::default(),
+            depth_test: true,
+            depth_write: true,
+            depth_compare: CompareOp::default(),
+        }
+    }
+}
+
+impl RenderState {
+    // The state glTF prescribes for its alpha modes, blended surfaces don't write depth.
+    pub fn from_alpha_mode(alpha_mode: AlphaMode, double_sided: bool) -> Self {
+        let blend_mode = match alpha_mode {
+            AlphaMode::Opaque | AlphaMode::Mask => BlendMode::Opaque,
+            AlphaMode::Blend => BlendMode::Alpha,
+        };
+
+        Self {
+            blend_mode,
+            cull_mode: match double_sided {
+                true => CullMode::None,
+                false => CullMode::Back,
+            },
+            depth_write: blend_mode == BlendMode::Opaque,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MaterialParameter {
+    Scalar(f32),
+    Vector2([f32; 2]),
+    Vector3([f32; 3]),
+    Vector4([f32; 4]),
+    // Linear RGBA.
+    Color([f32; 4]),
+}
+
+impl MaterialParameter {
+    // Unused components are zero.
+    pub fn to_vec4(self) -> [f32; 4] {
+        match self {
+            MaterialParameter::Scalar(x) => [x, 0.0, 0.0, 0.0],
+            MaterialParameter::Vector2([x, y]) => [x, y, 0.0, 0.0],
+            MaterialParameter::Vector3([x, y, z]) => [x, y, z, 0.0],
+            MaterialParameter::Vector4(vector) | MaterialParameter::Color(vector) => vector,
+        }
+    }
+}
+
+// PBR metallic-roughness parameters, textures are referenced by the GUIDs of texture assets.
+// Factors multiply the texels of the corresponding textures. Materials drawn with a custom
+// shader describe their inputs by named parameters and texture slots instead.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialAsset {
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<AssetGuid>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    // Metalness is read from the blue channel, roughness from the green one.
+    pub metallic_roughness_texture: Option<AssetGuid>,
+    pub normal_texture: Option<AssetGuid>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<AssetGuid>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<AssetGuid>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+    // Shader asset the pipeline is built from. If `None`, the engine's PBR shader draws the
+    // fields above.
+    pub shader: Option<AssetGuid>,
+    pub parameters: BTreeMap<String, MaterialParameter>,
+    // Texture assets bound to the samplers of the shader, by slot name.
+    pub texture_slots: BTreeMap<String, AssetGuid>,
+    pub render_state: RenderState,
+}
+
+impl Default for MaterialAsset {
+    fn default() -> Self {
+        Self {
+            base_color_factor: [1.0; 4],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_factor: [0.0; 3],
+            emissive_texture: None,
+            alpha_mode: AlphaMode::default(),
+            alpha_cutoff: 0.5,
+            double_sided: false,
+            shader: None,
+            parameters: BTreeMap::new(),
+            texture_slots: BTreeMap::new(),
+            render_state: RenderState::default(),
+        }
+    }
+}
+
+impl MaterialAsset {
+    // Derives the render state from `alpha_mode` and `double_sided`.
+    pub fn with_pbr_render_state(mut self) -> Self {
+        self.render_state = RenderState::from_alpha_mode(self.alpha_mode, self.double_sided);
+        self
+    }
+
+    // Referenced textures, without repetitions.
+    pub fn textures(&self) -> Vec<AssetGuid> {
+        let mut textures = Vec::new();
+        for texture in [
+            self.base_color_texture,
+            self.metallic_roughness_texture,
+            self.normal_texture,
+            self.occlusion_texture,
+            self.emissive_texture,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(self.texture_slots.values().copied())
+        {
+            if !textures.contains(&texture) {
+                textures.push(texture);
+            }
+        }
+
+        textures
+    }
+
+    // The shader followed by the textures.
+    pub fn dependencies(&self) -> Vec<AssetGuid> {
+        self.shader.into_iter().chain(self.textures()).collect()
+    }
+
+    // The uniform block of the shader with every parameter at the offset of the member of the
+    // same name, components that don't fit into the member are left out. Every parameter needs a
+    // member and every member a parameter.
+    pub fn parameter_block(&self, members: &[BlockMember]) -> Result<Vec<u8>, AssetError> {
+        if let Some(name) = self
+            .parameters
+            .keys()
+            .find(|name| !members.iter().any(|member| &&member.name == name))
+        {
+            return Err(AssetError::InvalidShader(format!(
+                "The shader of the material has no parameter {name}."
+            )));
+        }
+
+        let size = members
+            .iter()
+            .map(|member| member.offset as usize + member.size as usize)
+            .max()
+            .unwrap_or(0);
+        let mut block = vec![0; size];
+        for member in members {
+            let parameter = self.parameters.get(&member.name).ok_or_else(|| {
+                AssetError::InvalidShader(format!(
+                    "The material has no value for the parameter {} of its shader.",
+                    member.name
+                ))
+            })?;
+            let bytes = parameter
+                .to_vec4()
+                .iter()
+                .flat_map(|component| component.to_le_bytes())
+                .take(member.size as usize)
+                .collect::<Vec<_>>();
+            let offset = member.offset as usize;
+            block[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        }
+
+        Ok(block)
+    }
+
+    // Materials have no blob, the shader and the textures become dependencies of the asset file.
+    pub fn into_asset_file(
+        self,
+        name: &str,
+        path: &str,
+        compression_mode: CompressionMode,
+    ) -> Result<AssetFile, AssetError> {
+        let dependencies = self.dependencies();
+
+        Ok(
+            AssetFile::new(self, name, path, Vec::new(), compression_mode)?
+                .with_dependencies(dependencies),
+        )
+    }
+}
+
+impl super::Packaging for MaterialAsset {
+    fn pack(
+        &self,
+        name: &str,
+        path: &str,
+        raw_data: Vec<u8>,
+        compression_mode: super::CompressionMode,
+    ) -> Result<AssetFile, AssetError> {
+        let serialized = ron::to_string(self)?;
+
+        Ok(AssetFile::from_raw_parts(
+            name,
+            path,
+            AssetType::Material,
+            compression_mode,
+            serialized,
+            raw_data,
+        ))
+    }
+}
+
+impl Asset for MaterialAsset {
+    fn from_asset_file(asset_file: AssetFile) -> Result<Self, AssetError> {
+        asset_file.expect_asset_type(AssetType::Material)?;
+
+        Ok(ron::from_str(asset_file.metadata())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_custom_material() {
+        let shader = AssetGuid::from_name("shaders/water.bin");
+        let noise = AssetGuid::from_name("textures/noise.bin");
+        let material = MaterialAsset {
+            shader: Some(shader),
+            parameters: BTreeMap::from([
+                ("speed".to_string(), MaterialParameter::Scalar(2.0)),
+                (
+                    "deep_color".to_string(),
+                    MaterialParameter::Color([0.0, 0.1, 0.3, 1.0]),
+                ),
+                (
+                    "direction".to_string(),
+                    MaterialParameter::Vector2([1.0, 0.5]),
+                ),
+            ]),
+            texture_slots: BTreeMap::from([("noise".to_string(), noise)]),
+            render_state: RenderState {
+                blend_mode: BlendMode::Alpha,
+                cull_mode: CullMode::None,
+                depth_write: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let member = |name: &str, offset, size| BlockMember {
+            name: name.to_string(),
+            offset,
+            size,
+        };
+        let mut members = vec![
+            member("deep_color", 0, 16),
+            member("speed", 16, 4),
+            member("direction", 24, 8),
+        ];
+        let floats = |block: Vec<u8>| {
+            block
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            floats(material.parameter_block(&members).unwrap()),
+            [0.0, 0.1, 0.3, 1.0, 2.0, 0.0, 1.0, 0.5]
+        );
+        // Parameters and members have to match by name.
+        members[1].name = "velocity".to_string();
+        assert!(material.parameter_block(&members).is_err());
+        members.push(member("speed", 32, 4));
+        assert!(material.parameter_block(&members).is_err());
+
+        let asset_file = material
+            .clone()
+            .into_asset_file("water", "water.bin", CompressionMode::None)
+            .unwrap();
+        assert_eq!(asset_file.dependencies(), [shader, noise]);
+        assert_eq!(
+            MaterialAsset::from_asset_file(asset_file).unwrap(),
+            material
+        );
+
+        // Materials saved before the shader and render state existed still load.
+        let legacy: MaterialAsset = ron::from_str("(alpha_mode: Blend)").unwrap();
+        assert_eq!(legacy.render_state, RenderState::default());
+        assert_eq!(
+            legacy.with_pbr_render_state().render_state.blend_mode,
+            BlendMode::Alpha
+        );
+    }
+}
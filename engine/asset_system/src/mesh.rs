@@ -0,0 +1,538 @@
+use crate::{Asset, AssetError, AssetFile, AssetGuid, AssetType, CompressionMode};
+use serde::{Deserialize, Serialize};
+
+// Size of a vertex in the blob, its fields are stored as little-endian `f32`s in their order.
+pub const VERTEX_SIZE: usize = std::mem::size_of::<Vertex>();
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    // The sign of `w` is the handedness of the bitangent.
+    pub tangent: [f32; 4],
+    pub uv: [f32; 2],
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IndexFormat {
+    U16,
+    #[default]
+    U32,
+}
+
+// A range of indices drawn with one material.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Submesh {
+    pub index_offset: u32,
+    pub index_count: u32,
+    pub material: Option<AssetGuid>,
+    // Simplified versions of the submesh, from the most detailed one.
+    #[serde(default)]
+    pub lods: Vec<Lod>,
+    // Range of `Mesh::meshlets` covering the full detail submesh.
+    #[serde(default)]
+    pub meshlet_offset: u32,
+    #[serde(default)]
+    pub meshlet_count: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lod {
+    pub index_offset: u32,
+    pub index_count: u32,
+    // Deviation from the full detail submesh, in the units of the positions.
+    pub error: f32,
+}
+
+// A cluster of triangles whose vertices are indices into `Mesh::meshlet_vertices` and whose
+// triangles are triples of indices into its vertices in `Mesh::meshlet_triangles`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Meshlet {
+    pub vertex_offset: u32,
+    pub vertex_count: u32,
+    pub triangle_offset: u32,
+    pub triangle_count: u32,
+    // Bounding sphere and the normal cone used for culling.
+    pub center: [f32; 3],
+    pub radius: f32,
+    pub cone_axis: [f32; 3],
+    pub cone_cutoff: f32,
+}
+
+// Size of a meshlet in the blob, its fields are stored in their order as little-endian numbers.
+pub const MESHLET_SIZE: usize = std::mem::size_of::<Meshlet>();
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+// The blob holds the vertices, the indices in `index_format` including the ones of LODs, then
+// the meshlets with their `u32` vertices and `u8` triangles.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MeshAsset {
+    pub vertex_count: u32,
+    pub index_count: u32,
+    pub index_format: IndexFormat,
+    pub submeshes: Vec<Submesh>,
+    pub bounds: Bounds,
+    #[serde(default)]
+    pub meshlet_count: u32,
+    #[serde(default)]
+    pub meshlet_vertex_count: u32,
+    #[serde(default)]
+    pub meshlet_triangle_count: u32,
+}
+
+// A loaded mesh: its metadata together with the vertices, indices and meshlets.
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    pub metadata: MeshAsset,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub meshlets: Vec<Meshlet>,
+    pub meshlet_vertices: Vec<u32>,
+    pub meshlet_triangles: Vec<u8>,
+}
+
+impl Vertex {
+    fn write_to(&self, bytes: &mut Vec<u8>) {
+        let components = self
+            .position
+            .iter()
+            .chain(&self.normal)
+            .chain(&self.tangent)
+            .chain(&self.uv);
+        components.for_each(|component| bytes.extend(component.to_le_bytes()));
+    }
+
+    fn read_from(bytes: &[u8]) -> Self {
+        let mut components = bytes
+            .chunks_exact(4)
+            .map(|component| f32::from_le_bytes(component.try_into().unwrap()));
+        let mut next = || components.next().unwrap_or_default();
+
+        Self {
+            position: [next(), next(), next()],
+            normal: [next(), next(), next()],
+            tangent: [next(), next(), next(), next()],
+            uv: [next(), next()],
+        }
+    }
+}
+
+impl Meshlet {
+    fn write_to(&self, bytes: &mut Vec<u8>) {
+        [
+            self.vertex_offset,
+            self.vertex_count,
+            self.triangle_offset,
+            self.triangle_count,
+        ]
+        .iter()
+        .for_each(|value| bytes.extend(value.to_le_bytes()));
+        self.center
+            .iter()
+            .chain(&[self.radius])
+            .chain(&self.cone_axis)
+            .chain(&[self.cone_cutoff])
+            .for_each(|value| bytes.extend(value.to_le_bytes()));
+    }
+
+    fn read_from(bytes: &[u8]) -> Self {
+        let mut values = bytes
+            .chunks_exact(4)
+            .map(|value| <[u8; 4]>::try_from(value).unwrap());
+        let mut next = || values.next().unwrap_or_default();
+        let mut next_u32 = || u32::from_le_bytes(next());
+        let [vertex_offset, vertex_count, triangle_offset, triangle_count] =
+            [(); 4].map(|_| next_u32());
+        let mut next_f32 = || f32::from_le_bytes(next());
+
+        Self {
+            vertex_offset,
+            vertex_count,
+            triangle_offset,
+            triangle_count,
+            center: [next_f32(), next_f32(), next_f32()],
+            radius: next_f32(),
+            cone_axis: [next_f32(), next_f32(), next_f32()],
+            cone_cutoff: next_f32(),
+        }
+    }
+}
+
+impl Bounds {
+    pub fn from_positions<'a>(positions: impl IntoIterator<Item = &'a [f32; 3]>) -> Self {
+        let mut positions = positions.into_iter().peekable();
+        let Some(&&first) = positions.peek() else {
+            return Self::default();
+        };
+
+        positions.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |bounds, position| Self {
+                min: std::array::from_fn(|i| bounds.min[i].min(position[i])),
+                max: std::array::from_fn(|i| bounds.max[i].max(position[i])),
+            },
+        )
+    }
+}
+
+impl Mesh {
+    // Without submeshes the whole index buffer is drawn with the default material.
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>, mut submeshes: Vec<Submesh>) -> Self {
+        if submeshes.is_empty() {
+            submeshes.push(Submesh {
+                index_offset: 0,
+                index_count: indices.len() as u32,
+                material: None,
+                lods: Vec::new(),
+                meshlet_offset: 0,
+                meshlet_count: 0,
+            });
+        }
+        let mut mesh = Self {
+            metadata: MeshAsset {
+                vertex_count: 0,
+                index_count: 0,
+                index_format: IndexFormat::U32,
+                submeshes,
+                bounds: Bounds::default(),
+                meshlet_count: 0,
+                meshlet_vertex_count: 0,
+                meshlet_triangle_count: 0,
+            },
+            vertices,
+            indices,
+            meshlets: Vec::new(),
+            meshlet_vertices: Vec::new(),
+            meshlet_triangles: Vec::new(),
+        };
+        mesh.update_metadata();
+
+        mesh
+    }
+
+    // Keeps the counts in the metadata in sync after the buffers were replaced.
+    pub fn update_metadata(&mut self) {
+        let metadata = &mut self.metadata;
+        metadata.vertex_count = self.vertices.len() as u32;
+        metadata.index_count = self.indices.len() as u32;
+        metadata.index_format = match self.vertices.len() <= u16::MAX as usize + 1 {
+            true => IndexFormat::U16,
+            false => IndexFormat::U32,
+        };
+        metadata.bounds =
+            Bounds::from_positions(self.vertices.iter().map(|vertex| &vertex.position));
+        metadata.meshlet_count = self.meshlets.len() as u32;
+        metadata.meshlet_vertex_count = self.meshlet_vertices.len() as u32;
+        metadata.meshlet_triangle_count = (self.meshlet_triangles.len() / 3) as u32;
+    }
+
+    // Materials of the submeshes, without repetitions.
+    pub fn materials(&self) -> Vec<AssetGuid> {
+        let mut materials = Vec::new();
+        for material in self
+            .metadata
+            .submeshes
+            .iter()
+            .filter_map(|submesh| submesh.material)
+        {
+            if !materials.contains(&material) {
+                materials.push(material);
+            }
+        }
+
+        materials
+    }
+
+    // Area-weighted normals of the triangles around every vertex.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![[0.0; 3]; self.vertices.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize].position);
+            let normal = cross(sub(b, a), sub(c, a));
+            for &index in triangle {
+                normals[index as usize] = add(normals[index as usize], normal);
+            }
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            vertex.normal = normalize(normal).unwrap_or([0.0, 0.0, 1.0]);
+        }
+    }
+
+    // Tangents along the U direction of the texture coordinates, orthogonalized against the
+    // normals, which have to be computed first.
+    pub fn compute_tangents(&mut self) {
+        let mut tangents = vec![[0.0; 3]; self.vertices.len()];
+        let mut bitangents = vec![[0.0; 3]; self.vertices.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize]);
+            let (edge_1, edge_2) = (sub(b.position, a.position), sub(c.position, a.position));
+            let (du_1, dv_1) = (b.uv[0] - a.uv[0], b.uv[1] - a.uv[1]);
+            let (du_2, dv_2) = (c.uv[0] - a.uv[0], c.uv[1] - a.uv[1]);
+            let determinant = du_1 * dv_2 - du_2 * dv_1;
+            if determinant.abs() <= f32::EPSILON {
+                continue;
+            }
+
+            let r = determinant.recip();
+            let tangent = std::array::from_fn(|i| (edge_1[i] * dv_2 - edge_2[i] * dv_1) * r);
+            let bitangent = std::array::from_fn(|i| (edge_2[i] * du_1 - edge_1[i] * du_2) * r);
+            for &index in triangle {
+                tangents[index as usize] = add(tangents[index as usize], tangent);
+                bitangents[index as usize] = add(bitangents[index as usize], bitangent);
+            }
+        }
+
+        for ((vertex, tangent), bitangent) in self.vertices.iter_mut().zip(tangents).zip(bitangents)
+        {
+            let normal = vertex.normal;
+            let tangent = sub(tangent, scale(normal, dot(normal, tangent)));
+            let [x, y, z] = normalize(tangent).unwrap_or_else(|| any_orthogonal(normal));
+            let w = match dot(cross(normal, [x, y, z]), bitangent) < 0.0 {
+                true => -1.0,
+                false => 1.0,
+            };
+
+            vertex.tangent = [x, y, z, w];
+        }
+    }
+
+    pub fn into_asset_file(
+        self,
+        name: &str,
+        path: &str,
+        compression_mode: CompressionMode,
+    ) -> Result<AssetFile, AssetError> {
+        let materials = self.materials();
+        let mut raw_data = Vec::with_capacity(
+            self.vertices.len() * VERTEX_SIZE + self.indices.len() * std::mem::size_of::<u32>(),
+        );
+        self.vertices
+            .iter()
+            .for_each(|vertex| vertex.write_to(&mut raw_data));
+        match self.metadata.index_format {
+            IndexFormat::U16 => self
+                .indices
+                .iter()
+                .for_each(|&index| raw_data.extend((index as u16).to_le_bytes())),
+            IndexFormat::U32 => self
+                .indices
+                .iter()
+                .for_each(|&index| raw_data.extend(index.to_le_bytes())),
+        }
+        self.meshlets
+            .iter()
+            .for_each(|meshlet| meshlet.write_to(&mut raw_data));
+        self.meshlet_vertices
+            .iter()
+            .for_each(|&vertex| raw_data.extend(vertex.to_le_bytes()));
+        raw_data.extend(&self.meshlet_triangles);
+
+        Ok(
+            AssetFile::new(self.metadata, name, path, raw_data, compression_mode)?
+                .with_dependencies(materials),
+        )
+    }
+}
+
+impl super::Packaging for MeshAsset {
+    fn pack(
+        &self,
+        name: &str,
+        path: &str,
+        raw_data: Vec<u8>,
+        compression_mode: super::CompressionMode,
+    ) -> Result<AssetFile, AssetError> {
+        let serialized = ron::to_string(self)?;
+
+        Ok(AssetFile::from_raw_parts(
+            name,
+            path,
+            AssetType::Mesh,
+            compression_mode,
+            serialized,
+            raw_data,
+        ))
+    }
+}
+
+impl Asset for Mesh {
+    fn from_asset_file(asset_file: AssetFile) -> Result<Self, AssetError> {
+        asset_file.expect_asset_type(AssetType::Mesh)?;
+        let metadata: MeshAsset = ron::from_str(asset_file.metadata())?;
+
+        let raw_data = asset_file.raw_data();
+        let index_size = match metadata.index_format {
+            IndexFormat::U16 => std::mem::size_of::<u16>(),
+            IndexFormat::U32 => std::mem::size_of::<u32>(),
+        };
+        let sizes = [
+            metadata.vertex_count as usize * VERTEX_SIZE,
+            metadata.index_count as usize * index_size,
+            metadata.meshlet_count as usize * MESHLET_SIZE,
+            metadata.meshlet_vertex_count as usize * std::mem::size_of::<u32>(),
+            metadata.meshlet_triangle_count as usize * 3,
+        ];
+        if raw_data.len() != sizes.iter().sum::<usize>() {
+            return Err(AssetError::Serialization(
+                "Size of the mesh data doesn't match the mesh metadata.".to_string(),
+            ));
+        }
+
+        let (vertices, rest) = raw_data.split_at(sizes[0]);
+        let (indices, rest) = rest.split_at(sizes[1]);
+        let (meshlets, rest) = rest.split_at(sizes[2]);
+        let (meshlet_vertices, meshlet_triangles) = rest.split_at(sizes[3]);
+
+        let vertices = vertices
+            .chunks_exact(VERTEX_SIZE)
+            .map(Vertex::read_from)
+            .collect();
+        let indices = match metadata.index_format {
+            IndexFormat::U16 => indices
+                .chunks_exact(index_size)
+                .map(|index| u16::from_le_bytes([index[0], index[1]]) as u32)
+                .collect(),
+            IndexFormat::U32 => indices
+                .chunks_exact(index_size)
+                .map(|index| u32::from_le_bytes(index.try_into().unwrap()))
+                .collect(),
+        };
+        let meshlets = meshlets
+            .chunks_exact(MESHLET_SIZE)
+            .map(Meshlet::read_from)
+            .collect();
+        let meshlet_vertices = meshlet_vertices
+            .chunks_exact(4)
+            .map(|vertex| u32::from_le_bytes(vertex.try_into().unwrap()))
+            .collect();
+
+        Ok(Self {
+            metadata,
+            vertices,
+            indices,
+            meshlets,
+            meshlet_vertices,
+            meshlet_triangles: meshlet_triangles.to_vec(),
+        })
+    }
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn scale(a: [f32; 3], factor: f32) -> [f32; 3] {
+    a.map(|component| component * factor)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: [f32; 3]) -> Option<[f32; 3]> {
+    let length = dot(a, a).sqrt();
+    match length > f32::EPSILON {
+        true => Some(scale(a, length.recip())),
+        false => None,
+    }
+}
+
+fn any_orthogonal(normal: [f32; 3]) -> [f32; 3] {
+    let axis = match normal[0].abs() < 0.9 {
+        true => [1.0, 0.0, 0.0],
+        false => [0.0, 1.0, 0.0],
+    };
+
+    normalize(cross(axis, normal)).unwrap_or([1.0, 0.0, 0.0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad() -> Mesh {
+        let vertices = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]
+            .map(|[x, y]| Vertex {
+                position: [x, y, 0.0],
+                uv: [x, 1.0 - y],
+                ..Default::default()
+            })
+            .to_vec();
+
+        Mesh::new(vertices, vec![0, 1, 2, 0, 2, 3], vec![])
+    }
+
+    #[test]
+    fn compute_normals_and_tangents() {
+        let mut mesh = quad();
+        mesh.compute_normals();
+        mesh.compute_tangents();
+
+        for vertex in &mesh.vertices {
+            assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+            assert_eq!(vertex.tangent, [1.0, 0.0, 0.0, -1.0]);
+        }
+        assert_eq!(
+            mesh.metadata.bounds,
+            Bounds {
+                min: [0.0; 3],
+                max: [1.0, 1.0, 0.0]
+            }
+        );
+    }
+
+    #[test]
+    fn pack_and_unpack_mesh() {
+        let material = AssetGuid::new();
+        let mut mesh = quad();
+        mesh.metadata.submeshes = vec![
+            Submesh {
+                index_offset: 0,
+                index_count: 3,
+                material: Some(material),
+                ..mesh.metadata.submeshes[0].clone()
+            },
+            Submesh {
+                index_offset: 3,
+                index_count: 3,
+                material: Some(material),
+                ..mesh.metadata.submeshes[0].clone()
+            },
+        ];
+
+        let asset_file = mesh
+            .clone()
+            .into_asset_file("quad", "quad.bin", CompressionMode::Fast)
+            .unwrap();
+        assert_eq!(asset_file.dependencies(), [material]);
+        assert_eq!(asset_file.raw_data().len(), 4 * VERTEX_SIZE + 6 * 2);
+
+        let unpacked = Mesh::from_asset_file(asset_file).unwrap();
+        assert_eq!(unpacked.metadata, mesh.metadata);
+        assert_eq!(unpacked.metadata.index_format, IndexFormat::U16);
+        assert_eq!(unpacked.vertices, mesh.vertices);
+        assert_eq!(unpacked.indices, mesh.indices);
+    }
+}
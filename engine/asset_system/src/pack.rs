@@ -0,0 +1,387 @@
+use crate::{AssetError, AssetFile, AssetGuid, AssetType, CompressionMode};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Mutex;
+
+pub const ASSET_PACK_EXTENSION: &str = "pak";
+
+const PACK_MAGIC: &[u8; 4] = b"RPAK";
+const PACK_FORMAT_VERSION: u32 = 1;
+// Magic, format version, offset and size of the table of contents.
+const PACK_HEADER_SIZE: u64 = 4 + 4 + 8 + 8;
+
+// An entry of the table of contents, `offset` and `size` describe the stored asset file,
+// which is exactly what `AssetFile::save_asset_file` writes to the disk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackEntry {
+    pub guid: AssetGuid,
+    pub name: String,
+    pub asset_type: AssetType,
+    pub codec: CompressionMode,
+    pub offset: u64,
+    pub size: u64,
+    pub uncompressed_size: u64,
+    // xxHash64 of the stored bytes.
+    pub checksum: u64,
+}
+
+// Bundles many asset files into one pack for shipping builds.
+#[derive(Default)]
+pub struct AssetPackBuilder {
+    asset_files: Vec<AssetFile>,
+}
+
+impl AssetPackBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, asset_file: AssetFile) -> &mut Self {
+        self.asset_files.push(asset_file);
+        self
+    }
+
+    pub fn add_file<T: AsRef<Path> + ?Sized>(&mut self, path: &T) -> Result<&mut Self, AssetError> {
+        Ok(self.add(AssetFile::load_asset_file(path)?))
+    }
+
+    // Adds every asset file under the directory, recursively.
+    pub fn add_directory<T: AsRef<Path> + ?Sized>(
+        &mut self,
+        directory: &T,
+    ) -> Result<&mut Self, AssetError> {
+        for path in crate::asset_file_paths(directory)? {
+            self.add_file(&path)?;
+        }
+
+        Ok(self)
+    }
+
+    pub fn build<T: AsRef<Path> + ?Sized>(&self, path: &T) -> Result<Vec<PackEntry>, AssetError> {
+        self.write_to(File::create(path)?)
+    }
+
+    pub fn write_to<W: Write + Seek>(&self, mut writer: W) -> Result<Vec<PackEntry>, AssetError> {
+        let mut guids = std::collections::HashSet::new();
+        if let Some(asset_file) = self
+            .asset_files
+            .iter()
+            .find(|asset_file| !guids.insert(asset_file.guid()))
+        {
+            return Err(AssetError::DuplicateGuid(asset_file.guid()));
+        }
+
+        // The header is patched once the offset of the table of contents is known.
+        writer.write_all(&[0; PACK_HEADER_SIZE as usize])?;
+
+        let mut offset = PACK_HEADER_SIZE;
+        let mut entries = Vec::with_capacity(self.asset_files.len());
+        for asset_file in &self.asset_files {
+            let mut stored = Vec::new();
+            let uncompressed_size = asset_file.write_to(&mut stored)?;
+            writer.write_all(&stored)?;
+
+            entries.push(PackEntry {
+                guid: asset_file.guid(),
+                name: asset_file.name().to_string(),
+                asset_type: asset_file.asset_type(),
+                codec: asset_file.compression_mode(),
+                offset,
+                size: stored.len() as u64,
+                uncompressed_size,
+                checksum: xxhash_rust::xxh64::xxh64(&stored, 0),
+            });
+            offset += stored.len() as u64;
+        }
+
+        let table_of_contents = ron::to_string(&entries)?;
+        writer.write_all(table_of_contents.as_bytes())?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        writer.write_all(PACK_MAGIC)?;
+        writer.write_all(&PACK_FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&(table_of_contents.len() as u64).to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(entries)
+    }
+}
+
+enum PackStorage {
+    File(Mutex<File>),
+    Mapped(memmap2::Mmap),
+}
+
+// Random access to the asset files of a pack.
+pub struct AssetPack {
+    storage: PackStorage,
+    // Size of the whole pack, nothing is read past it.
+    size: u64,
+    entries: Vec<PackEntry>,
+}
+
+impl AssetPack {
+    pub fn open<T: AsRef<Path> + ?Sized>(path: &T) -> Result<Self, AssetError> {
+        let mut pack_file = File::open(path)?;
+        let size = pack_file.metadata()?.len();
+
+        let mut header = [0; PACK_HEADER_SIZE as usize];
+        pack_file.read_exact(&mut header)?;
+        let (table_of_contents_offset, table_of_contents_size) = parse_header(&header)?;
+
+        // The sizes are checked against the pack before anything is allocated for them.
+        let range = stored_range(table_of_contents_offset, table_of_contents_size, size)
+            .ok_or_else(|| AssetError::InvalidPack("The pack is truncated.".to_string()))?;
+        let mut table_of_contents = vec![0; range.len()];
+        pack_file.seek(SeekFrom::Start(table_of_contents_offset))?;
+        pack_file.read_exact(&mut table_of_contents)?;
+
+        Self::new(
+            PackStorage::File(Mutex::new(pack_file)),
+            size,
+            parse_table_of_contents(&table_of_contents)?,
+        )
+    }
+
+    // Maps the whole pack into memory, entries are decompressed straight from the mapping.
+    pub fn open_mapped<T: AsRef<Path> + ?Sized>(path: &T) -> Result<Self, AssetError> {
+        let pack_file = File::open(path)?;
+        // SAFETY: Packs are read-only shipping data, so the file isn't expected to be modified
+        // while it's mapped; entries are still verified by their checksums on every read.
+        #[allow(unsafe_code)]
+        let mapping = unsafe { memmap2::Mmap::map(&pack_file)? };
+
+        let header = mapping
+            .get(..PACK_HEADER_SIZE as usize)
+            .ok_or_else(|| AssetError::InvalidPack("The pack is truncated.".to_string()))?;
+        let (table_of_contents_offset, table_of_contents_size) = parse_header(header)?;
+        let size = mapping.len() as u64;
+        let table_of_contents =
+            stored_range(table_of_contents_offset, table_of_contents_size, size)
+                .map(|range| &mapping[range])
+                .ok_or_else(|| AssetError::InvalidPack("The pack is truncated.".to_string()))?;
+        let entries = parse_table_of_contents(table_of_contents)?;
+
+        Self::new(PackStorage::Mapped(mapping), size, entries)
+    }
+
+    // Rejects entries that lie outside of the pack.
+    fn new(storage: PackStorage, size: u64, entries: Vec<PackEntry>) -> Result<Self, AssetError> {
+        let pack = Self {
+            storage,
+            size,
+            entries,
+        };
+        pack.entries
+            .iter()
+            .try_for_each(|entry| pack.entry_range(entry).map(|_| ()))?;
+
+        Ok(pack)
+    }
+
+    fn entry_range(&self, entry: &PackEntry) -> Result<Range<usize>, AssetError> {
+        stored_range(entry.offset, entry.size, self.size).ok_or_else(|| {
+            AssetError::InvalidPack(format!("The entry {} is truncated.", entry.guid))
+        })
+    }
+
+    pub fn entries(&self) -> &[PackEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, guid: AssetGuid) -> Option<&PackEntry> {
+        self.entries.iter().find(|entry| entry.guid == guid)
+    }
+
+    pub fn entry_by_name(&self, name: &str) -> Option<&PackEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn contains(&self, guid: AssetGuid) -> bool {
+        self.entry(guid).is_some()
+    }
+
+    pub fn load(&self, guid: AssetGuid) -> Result<AssetFile, AssetError> {
+        let entry = self.entry(guid).ok_or(AssetError::UnknownGuid(guid))?;
+
+        self.load_entry(entry)
+    }
+
+    pub fn load_entry(&self, entry: &PackEntry) -> Result<AssetFile, AssetError> {
+        let range = self.entry_range(entry)?;
+        match &self.storage {
+            PackStorage::File(pack_file) => {
+                let mut stored = vec![0; range.len()];
+                {
+                    let mut pack_file = pack_file.lock().unwrap();
+                    pack_file.seek(SeekFrom::Start(entry.offset))?;
+                    pack_file.read_exact(&mut stored)?;
+                }
+
+                Self::decode_entry(entry, &stored)
+            }
+            PackStorage::Mapped(mapping) => Self::decode_entry(entry, &mapping[range]),
+        }
+    }
+
+    fn decode_entry(entry: &PackEntry, stored: &[u8]) -> Result<AssetFile, AssetError> {
+        let actual = xxhash_rust::xxh64::xxh64(stored, 0);
+        if actual != entry.checksum {
+            return Err(AssetError::ChecksumMismatch {
+                expected: entry.checksum,
+                actual,
+            });
+        }
+
+        AssetFile::read_from(stored)
+    }
+}
+
+// `None` if the bytes at `offset` don't fit into a pack of `pack_size` bytes.
+fn stored_range(offset: u64, size: u64, pack_size: u64) -> Option<Range<usize>> {
+    let end = offset.checked_add(size).filter(|&end| end <= pack_size)?;
+
+    Some(usize::try_from(offset).ok()?..usize::try_from(end).ok()?)
+}
+
+fn parse_header(header: &[u8]) -> Result<(u64, u64), AssetError> {
+    if &header[..4] != PACK_MAGIC {
+        return Err(AssetError::InvalidPack("Unknown file format.".to_string()));
+    }
+
+    let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    if version != PACK_FORMAT_VERSION {
+        return Err(AssetError::InvalidPack(format!(
+            "Unsupported format version {version}."
+        )));
+    }
+
+    let table_of_contents_offset = u64::from_le_bytes(header[8..16].try_into().unwrap());
+    let table_of_contents_size = u64::from_le_bytes(header[16..24].try_into().unwrap());
+
+    Ok((table_of_contents_offset, table_of_contents_size))
+}
+
+fn parse_table_of_contents(table_of_contents: &[u8]) -> Result<Vec<PackEntry>, AssetError> {
+    Ok(ron::de::from_bytes(table_of_contents)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_asset_file(name: &str, raw_data: Vec<u8>) -> AssetFile {
+        AssetFile::from_raw_parts(
+            name,
+            &format!("{name}.bin"),
+            AssetType::Mesh,
+            CompressionMode::Default,
+            String::new(),
+            raw_data,
+        )
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn build_and_read_pack() {
+        const PACK_PATH: &str = "src/test_asset_files/pack.pak";
+
+        let first = create_asset_file("first", vec![1, 2, 3]);
+        let second = create_asset_file("second", vec![4; 1024]);
+        let (first_guid, second_guid) = (first.guid(), second.guid());
+        let entries = AssetPackBuilder::new()
+            .add(first)
+            .add(second)
+            .build(PACK_PATH)
+            .unwrap();
+
+        let pack = AssetPack::open(PACK_PATH).unwrap();
+        let mapped_pack = AssetPack::open_mapped(PACK_PATH).unwrap();
+
+        for pack in [&pack, &mapped_pack] {
+            assert_eq!(pack.entries(), entries);
+            assert_eq!(pack.load(first_guid).unwrap().raw_data(), [1, 2, 3]);
+            assert_eq!(pack.load(second_guid).unwrap().raw_data(), [4; 1024]);
+            let entry = pack.entry_by_name("second").unwrap();
+            assert_eq!(entry.codec, CompressionMode::Default);
+            assert!(entry.size < entry.uncompressed_size);
+            assert!(matches!(
+                pack.load(AssetGuid::new()),
+                Err(AssetError::UnknownGuid(_))
+            ));
+        }
+        drop(mapped_pack);
+
+        // Corrupt a byte of the first entry.
+        let mut content = std::fs::read(PACK_PATH).unwrap();
+        content[PACK_HEADER_SIZE as usize + 8] ^= 0xff;
+        std::fs::write(PACK_PATH, content).unwrap();
+        let pack = AssetPack::open(PACK_PATH).unwrap();
+        std::fs::remove_file(PACK_PATH).unwrap();
+
+        assert!(matches!(
+            pack.load(first_guid),
+            Err(AssetError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn reject_duplicate_guids() {
+        let asset_file = create_asset_file("asset", vec![1, 2, 3]);
+        let mut duplicate = create_asset_file("duplicate", vec![]);
+        duplicate.guid = asset_file.guid();
+
+        let result = AssetPackBuilder::new()
+            .add(asset_file)
+            .add(duplicate)
+            .write_to(std::io::Cursor::new(Vec::new()));
+
+        assert!(matches!(result, Err(AssetError::DuplicateGuid(_))));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn reject_out_of_bounds_sizes() {
+        const PACK_PATH: &str = "src/test_asset_files/out_of_bounds.pak";
+
+        let asset_file = create_asset_file("asset", vec![1, 2, 3]);
+        let guid = asset_file.guid();
+        AssetPackBuilder::new()
+            .add(asset_file)
+            .build(PACK_PATH)
+            .unwrap();
+        let content = std::fs::read(PACK_PATH).unwrap();
+
+        let pack = AssetPack::open(PACK_PATH).unwrap();
+        let mut entry = pack.entry(guid).unwrap().clone();
+        entry.offset = u64::MAX;
+        let out_of_bounds_entry = pack.load_entry(&entry);
+
+        // The table of contents overflows or lies past the end of the pack.
+        let mut results = Vec::new();
+        for (offset, size) in [(u64::MAX, u64::MAX), (PACK_HEADER_SIZE, u64::MAX / 2)] {
+            let mut corrupted = content.clone();
+            corrupted[8..16].copy_from_slice(&offset.to_le_bytes());
+            corrupted[16..24].copy_from_slice(&size.to_le_bytes());
+            std::fs::write(PACK_PATH, corrupted).unwrap();
+            results.push(AssetPack::open(PACK_PATH).map(|_| ()));
+            results.push(AssetPack::open_mapped(PACK_PATH).map(|_| ()));
+        }
+        std::fs::remove_file(PACK_PATH).unwrap();
+
+        assert!(matches!(
+            out_of_bounds_entry,
+            Err(AssetError::InvalidPack(_))
+        ));
+        for result in results {
+            assert!(
+                matches!(result, Err(AssetError::InvalidPack(_))),
+                "{result:?}"
+            );
+        }
+    }
+}
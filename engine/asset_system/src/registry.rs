@@ -0,0 +1,402 @@
+use crate::{AssetError, AssetFile, AssetGuid, ImportSettings, LazyAssetFile};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+pub const ASSET_REGISTRY_FILE_NAME: &str = "asset_registry.ron";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AssetIndex {
+    version: String,
+    // Paths are relative to the root of the project.
+    assets: BTreeMap<AssetGuid, PathBuf>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    dependencies: BTreeMap<AssetGuid, Vec<AssetGuid>>,
+}
+
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub added: Vec<AssetGuid>,
+    pub moved: Vec<(AssetGuid, PathBuf)>,
+    // Assets that were indexed before, but aren't found anymore in the project.
+    pub missing: Vec<(AssetGuid, PathBuf)>,
+    // Files that share a GUID with an already indexed file, the first file (by path) wins.
+    pub duplicates: Vec<(AssetGuid, PathBuf)>,
+    // Files whose header failed to load, e.g. truncated ones.
+    pub failed: Vec<(PathBuf, AssetError)>,
+    // Dependencies on GUIDs that aren't in the project, as (dependent, dependency).
+    pub unresolved_dependencies: Vec<(AssetGuid, AssetGuid)>,
+    pub dependency_cycles: Vec<Vec<AssetGuid>>,
+}
+
+#[derive(Debug)]
+pub struct AssetRegistry {
+    root: PathBuf,
+    index: AssetIndex,
+}
+
+impl AssetRegistry {
+    pub fn new<T: AsRef<Path> + ?Sized>(root: &T) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            index: AssetIndex {
+                version: crate::CURRENT_ASSET_SYSTEM_VERSION.to_string(),
+                assets: BTreeMap::new(),
+                dependencies: BTreeMap::new(),
+            },
+        }
+    }
+
+    // Opens a registry of the project, reading the persisted index if there is one.
+    pub fn open<T: AsRef<Path> + ?Sized>(root: &T) -> Result<Self, AssetError> {
+        let mut registry = Self::new(root);
+
+        match std::fs::read_to_string(registry.index_path()) {
+            Ok(content) => registry.index = ron::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(AssetError::Io(e)),
+        }
+
+        Ok(registry)
+    }
+
+    pub fn save(&self) -> Result<(), AssetError> {
+        let serialized = ron::ser::to_string_pretty(&self.index, Default::default())?;
+
+        crate::save_atomically(&self.index_path(), |writer| {
+            Ok(writer.write_all(serialized.as_bytes())?)
+        })
+    }
+
+    // Rebuilds the index from the headers of the asset files found in the project.
+    pub fn scan(&mut self) -> Result<ScanReport, AssetError> {
+        let mut report = ScanReport::default();
+        let mut assets = BTreeMap::new();
+        let mut dependencies = BTreeMap::new();
+
+        for path in crate::asset_file_paths(&self.root)? {
+            let relative_path = path.strip_prefix(&self.root).unwrap_or(&path).to_path_buf();
+            let asset_file = match read_header(&path) {
+                Ok(asset_file) => asset_file,
+                Err(e) => {
+                    report.failed.push((relative_path, e));
+                    continue;
+                }
+            };
+
+            let guid = asset_file.guid();
+            if assets.contains_key(&guid) {
+                report.duplicates.push((guid, relative_path));
+                continue;
+            }
+
+            match self.index.assets.get(&guid) {
+                None => report.added.push(guid),
+                Some(previous_path) if *previous_path != relative_path => {
+                    report.moved.push((guid, relative_path.clone()))
+                }
+                _ => (),
+            }
+            assets.insert(guid, relative_path);
+            if !asset_file.dependencies().is_empty() {
+                dependencies.insert(guid, asset_file.dependencies().to_vec());
+            }
+        }
+
+        report.missing = self
+            .index
+            .assets
+            .iter()
+            .filter(|(guid, _)| !assets.contains_key(guid))
+            .map(|(&guid, path)| (guid, path.clone()))
+            .collect();
+
+        self.index.assets = assets;
+        self.index.dependencies = dependencies;
+
+        report.unresolved_dependencies = self
+            .index
+            .dependencies
+            .iter()
+            .flat_map(|(&guid, dependencies)| {
+                dependencies
+                    .iter()
+                    .filter(|dependency| !self.index.assets.contains_key(dependency))
+                    .map(move |&dependency| (guid, dependency))
+            })
+            .collect();
+        report.dependency_cycles = self.find_cycles();
+
+        Ok(report)
+    }
+
+    pub fn register(&mut self, asset_file: &AssetFile) -> Option<PathBuf> {
+        let path = Path::new(&asset_file.path);
+        let relative_path = path.strip_prefix(&self.root).unwrap_or(path).to_path_buf();
+
+        match asset_file.dependencies().is_empty() {
+            true => self.index.dependencies.remove(&asset_file.guid()),
+            false => self
+                .index
+                .dependencies
+                .insert(asset_file.guid(), asset_file.dependencies().to_vec()),
+        };
+
+        self.index.assets.insert(asset_file.guid(), relative_path)
+    }
+
+    pub fn resolve(&self, guid: AssetGuid) -> Option<PathBuf> {
+        self.index
+            .assets
+            .get(&guid)
+            .map(|relative_path| self.root.join(relative_path))
+    }
+
+    pub fn load(&self, guid: AssetGuid) -> Result<AssetFile, AssetError> {
+        let path = self.resolve(guid).ok_or(AssetError::UnknownGuid(guid))?;
+
+        AssetFile::load_asset_file(&path)
+    }
+
+    pub fn contains(&self, guid: AssetGuid) -> bool {
+        self.index.assets.contains_key(&guid)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (AssetGuid, &Path)> {
+        self.index
+            .assets
+            .iter()
+            .map(|(&guid, path)| (guid, path.as_path()))
+    }
+
+    pub fn dependencies(&self, guid: AssetGuid) -> &[AssetGuid] {
+        self.index
+            .dependencies
+            .get(&guid)
+            .map_or(&[], |dependencies| dependencies.as_slice())
+    }
+
+    // Assets that directly depend on the given one, e.g. materials that use a texture.
+    pub fn dependents(&self, guid: AssetGuid) -> Vec<AssetGuid> {
+        self.index
+            .dependencies
+            .iter()
+            .filter(|(_, dependencies)| dependencies.contains(&guid))
+            .map(|(&dependent, _)| dependent)
+            .collect()
+    }
+
+    // Every asset that uses the given one, directly or through other assets.
+    pub fn dependents_recursive(&self, guid: AssetGuid) -> Vec<AssetGuid> {
+        let mut dependents = Vec::new();
+        let mut queue = vec![guid];
+
+        while let Some(current) = queue.pop() {
+            for dependent in self.dependents(current) {
+                if dependent != guid && !dependents.contains(&dependent) {
+                    dependents.push(dependent);
+                    queue.push(dependent);
+                }
+            }
+        }
+
+        dependents
+    }
+
+    // Import settings of a source of the project, with the defaults of its folders.
+    pub fn import_settings<T: AsRef<Path> + ?Sized>(
+        &self,
+        source: &T,
+    ) -> Result<ImportSettings, AssetError> {
+        ImportSettings::for_source(&self.root.join(source), &self.root)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.root.join(ASSET_REGISTRY_FILE_NAME)
+    }
+
+    // Depth-first search over the dependency graph, every cycle is reported once.
+    fn find_cycles(&self) -> Vec<Vec<AssetGuid>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            InProgress,
+            Done,
+        }
+
+        let mut cycles = Vec::new();
+        let mut marks = BTreeMap::new();
+
+        for &root in self.index.dependencies.keys() {
+            if marks.contains_key(&root) {
+                continue;
+            }
+
+            let mut stack = vec![(root, 0)];
+            marks.insert(root, Mark::InProgress);
+            while let Some(&(current, next_dependency)) = stack.last() {
+                match self.dependencies(current).get(next_dependency) {
+                    Some(&dependency) => {
+                        stack.last_mut().unwrap().1 += 1;
+                        match marks.get(&dependency) {
+                            None => {
+                                marks.insert(dependency, Mark::InProgress);
+                                stack.push((dependency, 0));
+                            }
+                            Some(Mark::InProgress) => {
+                                let start = stack
+                                    .iter()
+                                    .position(|&(guid, _)| guid == dependency)
+                                    .unwrap();
+                                let mut cycle = stack[start..]
+                                    .iter()
+                                    .map(|&(guid, _)| guid)
+                                    .collect::<Vec<_>>();
+                                cycle.push(dependency);
+                                cycles.push(cycle);
+                            }
+                            Some(Mark::Done) => (),
+                        }
+                    }
+                    None => {
+                        marks.insert(current, Mark::Done);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        cycles
+    }
+}
+
+// Blobs are skipped, except for legacy asset files, which are a single LZ4 frame.
+fn read_header(path: &Path) -> Result<AssetFile, AssetError> {
+    let mut asset_file = File::open(path)?;
+    let mut magic = [0; 4];
+    asset_file.read_exact(&mut magic)?;
+    asset_file.rewind()?;
+
+    match &magic == crate::ASSET_FILE_MAGIC {
+        true => Ok(LazyAssetFile::new(asset_file)?.header().clone()),
+        false => AssetFile::read_from(asset_file),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssetType, CompressionMode};
+
+    fn create_asset_file(path: &str) -> AssetFile {
+        let asset_file = AssetFile::from_raw_parts(
+            "registry_asset",
+            path,
+            AssetType::Mesh,
+            CompressionMode::Fast,
+            String::new(),
+            vec![1, 2, 3],
+        );
+        asset_file.save_asset_file().unwrap();
+
+        asset_file
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn scan_project_and_resolve_by_guid() {
+        const PROJECT_PATH: &str = "src/test_asset_files/registry_project";
+
+        std::fs::create_dir_all(format!("{PROJECT_PATH}/textures")).unwrap();
+        let first = create_asset_file(&format!("{PROJECT_PATH}/first.bin"));
+        let second = create_asset_file(&format!("{PROJECT_PATH}/textures/second.bin"));
+        // Only headers are read, so a corrupted blob is indexed.
+        let corrupted_path = format!("{PROJECT_PATH}/corrupted.bin");
+        let corrupted = create_asset_file(&corrupted_path);
+        let mut content = std::fs::read(&corrupted_path).unwrap();
+        *content.last_mut().unwrap() ^= 0xFF;
+        std::fs::write(&corrupted_path, content).unwrap();
+
+        let mut registry = AssetRegistry::new(PROJECT_PATH);
+        let report = registry.scan().unwrap();
+        assert_eq!(report.added.len(), 3);
+        assert!(report.failed.is_empty());
+        assert!(registry.resolve(corrupted.guid()).is_some());
+        registry.save().unwrap();
+
+        // Moving a file must keep its GUID resolvable, deleting one must be reported as missing.
+        std::fs::rename(
+            format!("{PROJECT_PATH}/textures/second.bin"),
+            format!("{PROJECT_PATH}/second.bin"),
+        )
+        .unwrap();
+        std::fs::remove_file(format!("{PROJECT_PATH}/first.bin")).unwrap();
+        std::fs::copy(
+            format!("{PROJECT_PATH}/second.bin"),
+            format!("{PROJECT_PATH}/textures/copy.bin"),
+        )
+        .unwrap();
+
+        let mut registry = AssetRegistry::open(PROJECT_PATH).unwrap();
+        let report = registry.scan().unwrap();
+        let resolved = registry.resolve(second.guid());
+        let loaded = registry
+            .load(second.guid())
+            .map(|asset_file| asset_file.guid());
+        std::fs::remove_dir_all(PROJECT_PATH).unwrap();
+
+        assert_eq!(report.moved, [(second.guid(), PathBuf::from("second.bin"))]);
+        assert_eq!(report.missing, [(first.guid(), PathBuf::from("first.bin"))]);
+        assert_eq!(
+            report.duplicates,
+            [(second.guid(), PathBuf::from("textures/copy.bin"))]
+        );
+        assert_eq!(resolved, Some(Path::new(PROJECT_PATH).join("second.bin")));
+        assert_eq!(loaded.unwrap(), second.guid());
+        assert!(matches!(
+            registry.load(first.guid()),
+            Err(AssetError::UnknownGuid(_))
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn query_dependents_and_cycles() {
+        const PROJECT_PATH: &str = "src/test_asset_files/registry_dependencies";
+
+        std::fs::create_dir_all(PROJECT_PATH).unwrap();
+        let texture = create_asset_file(&format!("{PROJECT_PATH}/texture.bin"));
+        let material = create_asset_file(&format!("{PROJECT_PATH}/material.bin"))
+            .with_dependencies(vec![texture.guid()]);
+        material.save_asset_file().unwrap();
+        let mesh = create_asset_file(&format!("{PROJECT_PATH}/mesh.bin"))
+            .with_dependencies(vec![material.guid()]);
+        mesh.save_asset_file().unwrap();
+        let missing = AssetGuid::new();
+        let mut first = create_asset_file(&format!("{PROJECT_PATH}/first.bin"));
+        let mut second = create_asset_file(&format!("{PROJECT_PATH}/second.bin"));
+        first.dependencies = vec![second.guid(), missing];
+        second.dependencies = vec![first.guid()];
+        first.save_asset_file().unwrap();
+        second.save_asset_file().unwrap();
+
+        let mut registry = AssetRegistry::new(PROJECT_PATH);
+        let report = registry.scan().unwrap();
+        std::fs::remove_dir_all(PROJECT_PATH).unwrap();
+
+        assert_eq!(registry.dependents(texture.guid()), [material.guid()]);
+        let mut dependents = registry.dependents_recursive(texture.guid());
+        dependents.sort();
+        let mut expected = vec![material.guid(), mesh.guid()];
+        expected.sort();
+        assert_eq!(dependents, expected);
+        assert_eq!(report.unresolved_dependencies, [(first.guid(), missing)]);
+        assert_eq!(report.dependency_cycles.len(), 1);
+        assert_eq!(report.dependency_cycles[0].len(), 3);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{Asset, AssetError, AssetFile, AssetGuid, AssetType, CompressionMode};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneNode {
+    pub name: String,
+    // Indices of the children in `SceneAsset::nodes`.
+    #[serde(default)]
+    pub children: Vec<u32>,
+    // The transform relative to the parent, the rotation is a quaternion in XYZW order.
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    #[serde(default)]
+    pub mesh: Option<AssetGuid>,
+}
+
+// A node hierarchy placing mesh assets, used as a scene or a prefab.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneAsset {
+    pub nodes: Vec<SceneNode>,
+    pub roots: Vec<u32>,
+}
+
+impl Default for SceneNode {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            children: Vec::new(),
+            translation: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0; 3],
+            mesh: None,
+        }
+    }
+}
+
+impl SceneAsset {
+    // Meshes placed by the nodes, without repetitions.
+    pub fn meshes(&self) -> Vec<AssetGuid> {
+        let mut meshes = Vec::new();
+        for mesh in self.nodes.iter().filter_map(|node| node.mesh) {
+            if !meshes.contains(&mesh) {
+                meshes.push(mesh);
+            }
+        }
+
+        meshes
+    }
+
+    // Scenes have no blob, the meshes become dependencies of the asset file.
+    pub fn into_asset_file(
+        self,
+        name: &str,
+        path: &str,
+        compression_mode: CompressionMode,
+    ) -> Result<AssetFile, AssetError> {
+        let meshes = self.meshes();
+
+        Ok(
+            AssetFile::new(self, name, path, Vec::new(), compression_mode)?
+                .with_dependencies(meshes),
+        )
+    }
+}
+
+impl super::Packaging for SceneAsset {
+    fn pack(
+        &self,
+        name: &str,
+        path: &str,
+        raw_data: Vec<u8>,
+        compression_mode: super::CompressionMode,
+    ) -> Result<AssetFile, AssetError> {
+        let serialized = ron::to_string(self)?;
+
+        Ok(AssetFile::from_raw_parts(
+            name,
+            path,
+            AssetType::Scene,
+            compression_mode,
+            serialized,
+            raw_data,
+        ))
+    }
+}
+
+impl Asset for SceneAsset {
+    fn from_asset_file(asset_file: AssetFile) -> Result<Self, AssetError> {
+        asset_file.expect_asset_type(AssetType::Scene)?;
+
+        Ok(ron::from_str(asset_file.metadata())?)
+    }
+}
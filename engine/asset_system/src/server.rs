@@ -0,0 +1,1019 @@
+use crate::loader::{converter, Convert};
+use crate::watcher::{AssetWatcher, DEFAULT_DEBOUNCE_DURATION};
+use crate::{
+    AssetError, AssetFile, AssetGuid, AssetRegistry, AssetType, AsyncAssetLoader, LoadPriority,
+    LoadTicket, MaterialAsset, Shader, Texture,
+};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+
+// A runtime representation of an asset, built from a loaded `AssetFile`.
+pub trait Asset: Send + Sync + Sized + 'static {
+    fn from_asset_file(asset_file: AssetFile) -> Result<Self, AssetError>;
+}
+
+impl Asset for AssetFile {
+    fn from_asset_file(asset_file: AssetFile) -> Result<Self, AssetError> {
+        Ok(asset_file)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssetId(u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetEvent {
+    Loaded(AssetId),
+    // A loaded asset was reloaded after its file had changed, the new version is returned by `get`.
+    Modified(AssetId),
+    Failed(AssetId),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadState {
+    NotLoaded,
+    Loading,
+    Loaded,
+    Failed,
+}
+
+// A strong reference to an asset, the asset stays loaded while at least one strong handle exists.
+pub struct Handle<T> {
+    id: Arc<AssetId>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn id(&self) -> AssetId {
+        *self.id
+    }
+
+    pub fn downgrade(&self) -> WeakHandle<T> {
+        WeakHandle {
+            id: Arc::downgrade(&self.id),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.id)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: Arc::clone(&self.id),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Handle").field(&*self.id).finish()
+    }
+}
+
+// A reference to an asset that doesn't keep it loaded.
+pub struct WeakHandle<T> {
+    id: Weak<AssetId>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> WeakHandle<T> {
+    pub fn upgrade(&self) -> Option<Handle<T>> {
+        self.id.upgrade().map(|id| Handle {
+            id,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: Weak::clone(&self.id),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for WeakHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WeakHandle")
+            .field(&self.id.upgrade().map(|id| *id))
+            .finish()
+    }
+}
+
+struct AssetEntry {
+    handle: Weak<AssetId>,
+    path: PathBuf,
+    guid: Option<AssetGuid>,
+    state: LoadState,
+    asset: Option<Arc<dyn Any + Send + Sync>>,
+    error: Option<AssetError>,
+    ticket: Option<LoadTicket>,
+    priority: LoadPriority,
+    dependencies: Vec<AssetGuid>,
+    // Keep dependencies loaded while this asset is cached.
+    dependency_handles: Vec<Arc<AssetId>>,
+    // The asset itself is loaded, but it waits for its dependencies.
+    pending_asset: Option<Arc<dyn Any + Send + Sync>>,
+    // Used to reload the asset with the same runtime type when its file changes.
+    convert: Option<Convert>,
+    // The previous version of the asset stays available until the reload is finished.
+    is_reloading: bool,
+    // The runtime type the asset is cached as, `None` until a dependency is loaded.
+    type_id: Option<TypeId>,
+}
+
+// Caches loaded assets, deduplicating loads of the same file by its path or GUID.
+pub struct AssetServer {
+    registry: Option<AssetRegistry>,
+    entries: HashMap<AssetId, AssetEntry>,
+    paths: HashMap<PathBuf, AssetId>,
+    guids: HashMap<AssetGuid, AssetId>,
+    next_id: u64,
+    // Created on the first asynchronous load.
+    loader: Option<AsyncAssetLoader>,
+    tickets: HashMap<u64, AssetId>,
+    // Used for dependencies, whose runtime type is known only by their `AssetType`.
+    converters: HashMap<AssetType, Convert>,
+    watcher: Option<AssetWatcher>,
+    // Reported by the next `update`, including the events of synchronous loads.
+    events: Vec<AssetEvent>,
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        let mut asset_server = Self {
+            registry: None,
+            entries: HashMap::new(),
+            paths: HashMap::new(),
+            guids: HashMap::new(),
+            next_id: 0,
+            loader: None,
+            tickets: HashMap::new(),
+            converters: HashMap::new(),
+            watcher: None,
+            events: Vec::new(),
+        };
+        asset_server.register_asset_type::<Texture>(AssetType::Texture);
+        asset_server.register_asset_type::<MaterialAsset>(AssetType::Material);
+        asset_server.register_asset_type::<Shader>(AssetType::Shader);
+
+        asset_server
+    }
+}
+
+impl AssetServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A server with a registry can load assets by their GUIDs.
+    pub fn with_registry(registry: AssetRegistry) -> Self {
+        Self {
+            registry: Some(registry),
+            ..Default::default()
+        }
+    }
+
+    pub fn registry(&self) -> Option<&AssetRegistry> {
+        self.registry.as_ref()
+    }
+
+    // Sets the runtime type that dependencies of the given `AssetType` are loaded as,
+    // types without a registered runtime type are loaded as `AssetFile`.
+    pub fn register_asset_type<T: Asset>(&mut self, asset_type: AssetType) {
+        self.converters.insert(asset_type, converter::<T>());
+    }
+
+    // Reloads cached assets in the background whenever their files in the directory change,
+    // reloaded assets are reported by `update` as `AssetEvent::Modified`.
+    pub fn watch_for_changes<T: AsRef<Path> + ?Sized>(
+        &mut self,
+        directory: &T,
+    ) -> Result<(), AssetError> {
+        let watcher = match self.watcher.as_mut() {
+            Some(watcher) => watcher,
+            None => self
+                .watcher
+                .insert(AssetWatcher::new(DEFAULT_DEBOUNCE_DURATION)?),
+        };
+
+        watcher.watch(&normalize_path(directory.as_ref()))
+    }
+
+    // Loads an asset with its dependencies on the caller's thread, an asset that is being loaded
+    // in the background stays in the `Loading` state until `update` picks it up.
+    pub fn load<T: Asset, P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Handle<T> {
+        let handle = self.find_or_insert(normalize_path(path.as_ref()), None);
+        let handle = match self.claim_entry(handle) {
+            Ok(handle) => handle,
+            Err(failed) => return failed,
+        };
+        if self.needs_load(handle.id()) {
+            self.load_entry(handle.id(), converter::<T>());
+        }
+
+        handle
+    }
+
+    pub fn load_by_guid<T: Asset>(&mut self, guid: AssetGuid) -> Handle<T> {
+        let handle = self.find_or_insert_by_guid(guid);
+        let handle = match self.claim_entry(handle) {
+            Ok(handle) => handle,
+            Err(failed) => return failed,
+        };
+        if self.needs_load(handle.id()) {
+            self.load_entry(handle.id(), converter::<T>());
+        }
+
+        handle
+    }
+
+    // Queues an asset to be loaded on a worker thread together with its dependencies, see `update`.
+    pub fn load_async<T: Asset, P: AsRef<Path> + ?Sized>(
+        &mut self,
+        path: &P,
+        priority: LoadPriority,
+    ) -> Handle<T> {
+        let handle = self.find_or_insert(normalize_path(path.as_ref()), None);
+        let handle = match self.claim_entry(handle) {
+            Ok(handle) => handle,
+            Err(failed) => return failed,
+        };
+        if self.needs_load(handle.id()) {
+            self.queue_entry(handle.id(), priority, converter::<T>());
+        }
+
+        handle
+    }
+
+    pub fn load_by_guid_async<T: Asset>(
+        &mut self,
+        guid: AssetGuid,
+        priority: LoadPriority,
+    ) -> Handle<T> {
+        let handle = self.find_or_insert_by_guid(guid);
+        let handle = match self.claim_entry(handle) {
+            Ok(handle) => handle,
+            Err(failed) => return failed,
+        };
+        if self.needs_load(handle.id()) {
+            self.queue_entry(handle.id(), priority, converter::<T>());
+        }
+
+        handle
+    }
+
+    // Applies the results of background loads and reloads changed assets,
+    // returns the assets whose state has changed since the last update, synchronous loads included.
+    pub fn update(&mut self) -> Vec<AssetEvent> {
+        let changed_paths = self
+            .watcher
+            .as_mut()
+            .map_or_else(Vec::new, |watcher| watcher.poll());
+        for path in changed_paths {
+            if let Some(id) = self.paths.get(&normalize_path(&path)).copied() {
+                self.reload_entry(id);
+            }
+        }
+
+        let completed = self
+            .loader
+            .as_mut()
+            .map_or_else(Vec::new, |loader| loader.poll());
+        for completed in completed {
+            let id = match self.tickets.remove(&completed.ticket_id) {
+                Some(id) => id,
+                None => continue,
+            };
+            let entry = match self.entries.get_mut(&id) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            entry.ticket = None;
+
+            match completed.result {
+                Ok(loaded_asset) => {
+                    let guid = loaded_asset.guid;
+                    let dependencies = loaded_asset.dependencies.clone();
+                    self.finish_load(id, guid, dependencies, loaded_asset.into_any(), true);
+                }
+                Err(AssetError::Cancelled) => {
+                    entry.state = match entry.asset.is_some() {
+                        true => LoadState::Loaded,
+                        false => LoadState::NotLoaded,
+                    };
+                    entry.is_reloading = false;
+                }
+                Err(e) => {
+                    self.fail(id, e);
+                    self.events.push(AssetEvent::Failed(id));
+                }
+            }
+        }
+        self.resolve_pending();
+
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn reload<T>(&mut self, handle: &Handle<T>) {
+        self.reload_entry(handle.id());
+    }
+
+    // Progress of an asset's background load, in the range from 0.0 to 1.0.
+    pub fn load_progress(&self, id: AssetId) -> f32 {
+        match self.entries.get(&id) {
+            Some(AssetEntry {
+                ticket: Some(ticket),
+                ..
+            }) => ticket.progress(),
+            Some(entry) if entry.state != LoadState::NotLoaded => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    pub fn get<T: Asset>(&self, handle: &Handle<T>) -> Option<Arc<T>> {
+        self.get_by_id(handle.id())
+    }
+
+    // Useful when only the id is known, e.g. from an `AssetEvent`.
+    pub fn get_by_id<T: Asset>(&self, id: AssetId) -> Option<Arc<T>> {
+        let asset = self.entries.get(&id)?.asset.clone()?;
+
+        asset.downcast::<T>().ok()
+    }
+
+    pub fn load_state(&self, id: AssetId) -> LoadState {
+        self.entries
+            .get(&id)
+            .map_or(LoadState::NotLoaded, |entry| entry.state)
+    }
+
+    pub fn load_error(&self, id: AssetId) -> Option<&AssetError> {
+        self.entries.get(&id)?.error.as_ref()
+    }
+
+    pub fn guid(&self, id: AssetId) -> Option<AssetGuid> {
+        self.entries.get(&id)?.guid
+    }
+
+    pub fn dependencies(&self, id: AssetId) -> &[AssetGuid] {
+        self.entries
+            .get(&id)
+            .map_or(&[], |entry| entry.dependencies.as_slice())
+    }
+
+    // Cached assets that directly depend on the given one.
+    pub fn dependents(&self, guid: AssetGuid) -> Vec<AssetId> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.dependencies.contains(&guid))
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    // Drops every asset that isn't referenced by a strong handle or by a dependent asset anymore,
+    // returns the count of them.
+    pub fn unload_unused(&mut self) -> usize {
+        let mut unloaded_count = 0;
+
+        loop {
+            let unused = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.handle.strong_count() == 0)
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+            if unused.is_empty() {
+                return unloaded_count;
+            }
+
+            unloaded_count += unused.len();
+            unused.into_iter().for_each(|id| self.remove_entry(id));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn needs_load(&self, id: AssetId) -> bool {
+        matches!(
+            self.load_state(id),
+            LoadState::NotLoaded | LoadState::Failed
+        )
+    }
+
+    // Entries are shared by path and GUID, so loading a file as another type than the one it is
+    // cached as fails with a handle of its own instead of one that `get` can't downcast. Entries
+    // that still have to be loaded are loaded as `T`.
+    fn claim_entry<T: Asset>(&mut self, handle: Handle<T>) -> Result<Handle<T>, Handle<T>> {
+        let needs_load = self.needs_load(handle.id());
+        let entry = self.entries.get_mut(&handle.id()).unwrap();
+        match entry.type_id {
+            Some(type_id) if type_id != TypeId::of::<T>() && !needs_load => {
+                let handle = self.insert_entry(PathBuf::new(), None);
+                let error = AssetError::UnexpectedRuntimeType(std::any::type_name::<T>());
+                self.fail(handle.id(), error);
+
+                Err(handle)
+            }
+            _ => {
+                entry.type_id = Some(TypeId::of::<T>());
+                Ok(handle)
+            }
+        }
+    }
+
+    fn find_or_insert<T>(&mut self, path: PathBuf, guid: Option<AssetGuid>) -> Handle<T> {
+        match self.paths.get(&path).copied() {
+            Some(id) => self.reuse_entry(id),
+            None => self.insert_entry(path, guid),
+        }
+    }
+
+    fn find_or_insert_by_guid<T>(&mut self, guid: AssetGuid) -> Handle<T> {
+        if let Some(id) = self.guids.get(&guid).copied() {
+            return self.reuse_entry(id);
+        }
+
+        match self
+            .registry
+            .as_ref()
+            .and_then(|registry| registry.resolve(guid))
+        {
+            Some(path) => self.find_or_insert(normalize_path(&path), Some(guid)),
+            None => {
+                let handle = self.insert_entry(PathBuf::new(), Some(guid));
+                self.fail(handle.id(), AssetError::UnknownGuid(guid));
+
+                handle
+            }
+        }
+    }
+
+    fn reuse_entry<T>(&mut self, id: AssetId) -> Handle<T> {
+        let entry = self.entries.get_mut(&id).unwrap();
+        match entry.handle.upgrade() {
+            Some(id) => Handle {
+                id,
+                _marker: PhantomData,
+            },
+            // The asset is still cached, but nobody references it, so its handle has to be recreated.
+            None => {
+                let handle = Handle {
+                    id: Arc::new(id),
+                    _marker: PhantomData,
+                };
+                entry.handle = Arc::downgrade(&handle.id);
+
+                handle
+            }
+        }
+    }
+
+    fn insert_entry<T>(&mut self, path: PathBuf, guid: Option<AssetGuid>) -> Handle<T> {
+        let id = AssetId(self.next_id);
+        self.next_id += 1;
+
+        let handle = Handle {
+            id: Arc::new(id),
+            _marker: PhantomData,
+        };
+        if !path.as_os_str().is_empty() {
+            self.paths.insert(path.clone(), id);
+        }
+        if let Some(guid) = guid {
+            self.guids.insert(guid, id);
+        }
+        self.entries.insert(
+            id,
+            AssetEntry {
+                handle: Arc::downgrade(&handle.id),
+                path,
+                guid,
+                state: LoadState::NotLoaded,
+                asset: None,
+                error: None,
+                ticket: None,
+                priority: LoadPriority::Normal,
+                dependencies: Vec::new(),
+                dependency_handles: Vec::new(),
+                pending_asset: None,
+                convert: None,
+                is_reloading: false,
+                type_id: None,
+            },
+        );
+
+        handle
+    }
+
+    fn load_entry(&mut self, id: AssetId, convert: Convert) {
+        let entry = self.entries.get_mut(&id).unwrap();
+        entry.state = LoadState::Loading;
+        entry.convert = Some(Arc::clone(&convert));
+
+        let result = AssetFile::load_asset_file(&entry.path).and_then(|asset_file| {
+            let guid = asset_file.guid();
+            let dependencies = asset_file.dependencies().to_vec();
+            convert(asset_file).map(|asset| (guid, dependencies, asset))
+        });
+        match result {
+            Ok((guid, dependencies, asset)) => {
+                self.finish_load(id, guid, dependencies, asset, false);
+                self.resolve_pending();
+            }
+            Err(e) => {
+                self.fail(id, e);
+                self.events.push(AssetEvent::Failed(id));
+            }
+        }
+    }
+
+    fn queue_entry(&mut self, id: AssetId, priority: LoadPriority, convert: Convert) {
+        let entry = self.entries.get_mut(&id).unwrap();
+        if let Some(ticket) = entry.ticket.take() {
+            ticket.cancel();
+            self.tickets.remove(&ticket.id());
+        }
+        let ticket = self
+            .loader
+            .get_or_insert_with(AsyncAssetLoader::default)
+            .load_with(entry.path.clone(), priority, Arc::clone(&convert));
+
+        entry.convert = Some(convert);
+        entry.state = match entry.is_reloading {
+            true => LoadState::Loaded,
+            false => LoadState::Loading,
+        };
+        entry.error = None;
+        entry.priority = priority;
+        self.tickets.insert(ticket.id(), id);
+        entry.ticket = Some(ticket);
+    }
+
+    fn reload_entry(&mut self, id: AssetId) {
+        let entry = match self.entries.get_mut(&id) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let convert = match (&entry.convert, entry.state) {
+            (Some(convert), LoadState::Loaded | LoadState::Failed) => Arc::clone(convert),
+            _ => return,
+        };
+        entry.is_reloading = entry.asset.is_some();
+        let priority = entry.priority;
+
+        self.queue_entry(id, priority, convert);
+    }
+
+    // Called when the asset file itself is loaded, the asset becomes `Loaded` only after
+    // all its dependencies are loaded.
+    fn finish_load(
+        &mut self,
+        id: AssetId,
+        guid: AssetGuid,
+        dependencies: Vec<AssetGuid>,
+        asset: Arc<dyn Any + Send + Sync>,
+        is_async: bool,
+    ) {
+        let entry = self.entries.get_mut(&id).unwrap();
+        entry.guid = Some(guid);
+        entry.dependencies = dependencies.clone();
+        entry.type_id = Some((*asset).type_id());
+        entry.pending_asset = Some(asset);
+        entry.error = None;
+        let priority = entry.priority;
+        self.guids.insert(guid, id);
+
+        if let Some(cycle) = self.find_cycle(guid) {
+            self.fail(id, AssetError::DependencyCycle(cycle));
+            return;
+        }
+
+        // Handles are attached before loading, so the asset can't be promoted too early.
+        let dependency_handles = dependencies
+            .into_iter()
+            .map(|dependency| self.find_or_insert_by_guid::<()>(dependency).id)
+            .collect::<Vec<_>>();
+        self.entries.get_mut(&id).unwrap().dependency_handles = dependency_handles.clone();
+
+        for dependency_id in dependency_handles {
+            let is_unknown = matches!(
+                self.load_error(*dependency_id),
+                Some(AssetError::UnknownGuid(_))
+            );
+            if self.needs_load(*dependency_id) && !is_unknown {
+                let convert = self.dependency_converter();
+                match is_async {
+                    true => self.queue_entry(*dependency_id, priority, convert),
+                    false => self.load_entry(*dependency_id, convert),
+                }
+            }
+        }
+    }
+
+    // Dependencies don't have a requested type, so it's picked by the `AssetType` of the file.
+    fn dependency_converter(&self) -> Convert {
+        let converters = self.converters.clone();
+
+        Arc::new(
+            move |asset_file: AssetFile| match converters.get(&asset_file.asset_type()) {
+                Some(convert) => convert(asset_file),
+                None => converter::<AssetFile>()(asset_file),
+            },
+        )
+    }
+
+    // Promotes assets whose dependencies are loaded and fails the ones with failed dependencies,
+    // returns the assets whose state has changed.
+    fn resolve_pending(&mut self) {
+        loop {
+            let mut changes = Vec::new();
+            for (&id, entry) in self.entries.iter() {
+                if entry.pending_asset.is_none() {
+                    continue;
+                }
+
+                let dependency_states = entry
+                    .dependencies
+                    .iter()
+                    .zip(entry.dependency_handles.iter())
+                    .map(|(&guid, dependency_id)| (guid, self.load_state(**dependency_id)));
+                let mut is_ready = true;
+                for (guid, state) in dependency_states {
+                    match state {
+                        LoadState::Loaded => (),
+                        LoadState::Failed | LoadState::NotLoaded => {
+                            changes.push((id, Err(AssetError::DependencyFailed(guid))));
+                            is_ready = false;
+                            break;
+                        }
+                        LoadState::Loading => is_ready = false,
+                    }
+                }
+                if is_ready {
+                    changes.push((id, Ok(())));
+                }
+            }
+
+            if changes.is_empty() {
+                return;
+            }
+
+            for (id, change) in changes {
+                match change {
+                    Ok(()) => {
+                        let entry = self.entries.get_mut(&id).unwrap();
+                        entry.asset = entry.pending_asset.take();
+                        entry.state = LoadState::Loaded;
+                        self.events
+                            .push(match std::mem::take(&mut entry.is_reloading) {
+                                true => AssetEvent::Modified(id),
+                                false => AssetEvent::Loaded(id),
+                            });
+                    }
+                    Err(e) => {
+                        self.fail(id, e);
+                        self.events.push(AssetEvent::Failed(id));
+                    }
+                }
+            }
+        }
+    }
+
+    // Follows the known dependencies starting from `guid`, returns the path back to it if any.
+    fn find_cycle(&self, guid: AssetGuid) -> Option<Vec<AssetGuid>> {
+        let mut stack = vec![(guid, 0)];
+        let mut visited = std::collections::HashSet::new();
+
+        while let Some((current, next_dependency)) = stack.last().copied() {
+            let dependencies = self
+                .guids
+                .get(&current)
+                .and_then(|id| self.entries.get(id))
+                .map_or(&[][..], |entry| entry.dependencies.as_slice());
+
+            match dependencies.get(next_dependency) {
+                Some(&dependency) => {
+                    stack.last_mut().unwrap().1 += 1;
+                    if dependency == guid {
+                        let mut cycle = stack.iter().map(|(guid, _)| *guid).collect::<Vec<_>>();
+                        cycle.push(guid);
+
+                        return Some(cycle);
+                    }
+                    if visited.insert(dependency) {
+                        stack.push((dependency, 0));
+                    }
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+
+        None
+    }
+
+    // A failed reload keeps the previous version of the asset loaded.
+    fn fail(&mut self, id: AssetId, e: AssetError) {
+        if let Some(entry) = self.entries.get_mut(&id) {
+            match std::mem::take(&mut entry.is_reloading) {
+                true => entry.state = LoadState::Loaded,
+                false => {
+                    entry.state = LoadState::Failed;
+                    entry.asset = None;
+                }
+            }
+            entry.pending_asset = None;
+            entry.error = Some(e);
+        }
+    }
+
+    fn remove_entry(&mut self, id: AssetId) {
+        if let Some(entry) = self.entries.remove(&id) {
+            if let Some(ticket) = entry.ticket {
+                ticket.cancel();
+                self.tickets.remove(&ticket.id());
+            }
+            if self.paths.get(&entry.path) == Some(&id) {
+                self.paths.remove(&entry.path);
+            }
+            if let Some(guid) = entry.guid {
+                if self.guids.get(&guid) == Some(&id) {
+                    self.guids.remove(&guid);
+                }
+            }
+        }
+    }
+}
+
+fn normalize_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionMode, Packaging, TextureAsset, TextureFormat};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn load_deduplicate_and_unload() {
+        const TEXTURE_PATH: &str = "src/test_asset_files/server_texture.bin";
+
+        let asset_file = TextureAsset::new(TextureFormat::RGBA8Srgb, 1, 1)
+            .pack(
+                "server_texture",
+                TEXTURE_PATH,
+                vec![255, 0, 0, 255],
+                CompressionMode::Fast,
+            )
+            .unwrap();
+        asset_file.save_asset_file().unwrap();
+
+        let mut asset_server = AssetServer::new();
+        let handle = asset_server.load::<Texture, _>(TEXTURE_PATH);
+        let same_handle = asset_server.load::<Texture, _>(TEXTURE_PATH);
+        let weak_handle = handle.downgrade();
+        std::fs::remove_file(TEXTURE_PATH).unwrap();
+
+        assert_eq!(handle, same_handle);
+        assert_eq!(asset_server.len(), 1);
+        assert_eq!(asset_server.load_state(handle.id()), LoadState::Loaded);
+        assert_eq!(asset_server.guid(handle.id()), Some(asset_file.guid()));
+        let texture = asset_server.get(&handle).unwrap();
+        assert_eq!(texture.data, [255, 0, 0, 255]);
+        assert_eq!(texture.metadata.width, 1);
+
+        assert_eq!(asset_server.unload_unused(), 0);
+        drop(handle);
+        drop(same_handle);
+        assert!(weak_handle.upgrade().is_none());
+        assert_eq!(asset_server.unload_unused(), 1);
+        assert!(asset_server.is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn failed_loads() {
+        let mut asset_server = AssetServer::new();
+
+        let handle = asset_server.load::<AssetFile, _>("src/test_asset_files/missing.bin");
+        assert_eq!(asset_server.load_state(handle.id()), LoadState::Failed);
+        assert!(matches!(
+            asset_server.load_error(handle.id()),
+            Some(AssetError::Io(_))
+        ));
+
+        let handle = asset_server.load::<Texture, _>("src/test_asset_files/asset_file.bin");
+        assert_eq!(asset_server.load_state(handle.id()), LoadState::Failed);
+        assert!(matches!(
+            asset_server.load_error(handle.id()),
+            Some(AssetError::UnexpectedAssetType { .. })
+        ));
+
+        let handle = asset_server.load_by_guid::<AssetFile>(AssetGuid::new());
+        assert_eq!(asset_server.load_state(handle.id()), LoadState::Failed);
+        assert!(asset_server.get(&handle).is_none());
+
+        // A failed load is retried as the other type.
+        let handle = asset_server.load::<AssetFile, _>("src/test_asset_files/asset_file.bin");
+        assert_eq!(asset_server.load_state(handle.id()), LoadState::Loaded);
+
+        let other_type = asset_server.load::<Texture, _>("src/test_asset_files/asset_file.bin");
+        assert_ne!(other_type.id(), handle.id());
+        assert_eq!(asset_server.load_state(other_type.id()), LoadState::Failed);
+        assert!(matches!(
+            asset_server.load_error(other_type.id()),
+            Some(AssetError::UnexpectedRuntimeType(_))
+        ));
+        assert_eq!(asset_server.load_state(handle.id()), LoadState::Loaded);
+        assert!(asset_server.get(&handle).is_some());
+    }
+
+    fn save_project_asset(
+        project_path: &str,
+        name: &str,
+        dependencies: Vec<AssetGuid>,
+    ) -> AssetGuid {
+        let asset_file = AssetFile::from_raw_parts(
+            name,
+            &format!("{project_path}/{name}.bin"),
+            AssetType::Mesh,
+            CompressionMode::Fast,
+            String::new(),
+            vec![1, 2, 3],
+        )
+        .with_dependencies(dependencies);
+        asset_file.save_asset_file().unwrap();
+
+        asset_file.guid()
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn load_dependencies_transitively() {
+        const PROJECT_PATH: &str = "src/test_asset_files/server_dependencies";
+
+        std::fs::create_dir_all(PROJECT_PATH).unwrap();
+        let texture = save_project_asset(PROJECT_PATH, "texture", vec![]);
+        let material = save_project_asset(PROJECT_PATH, "material", vec![texture]);
+        let mesh = save_project_asset(PROJECT_PATH, "mesh", vec![material]);
+        let mut registry = AssetRegistry::new(PROJECT_PATH);
+        registry.scan().unwrap();
+
+        let mut asset_server = AssetServer::with_registry(registry);
+        let handle = asset_server.load_by_guid::<AssetFile>(mesh);
+        // Synchronous loads are reported by the next update, dependencies first.
+        let ids = [texture, material, mesh].map(|guid| asset_server.guids[&guid]);
+        assert_eq!(asset_server.update(), ids.map(AssetEvent::Loaded));
+        assert!(asset_server.update().is_empty());
+
+        let mut async_asset_server = AssetServer::with_registry(AssetRegistry::new(PROJECT_PATH));
+        async_asset_server
+            .registry
+            .as_mut()
+            .unwrap()
+            .scan()
+            .unwrap();
+        let async_handle = async_asset_server
+            .load_async::<AssetFile, _>(&format!("{PROJECT_PATH}/mesh.bin"), LoadPriority::Normal);
+        while async_asset_server.load_state(async_handle.id()) == LoadState::Loading {
+            async_asset_server.update();
+            std::thread::yield_now();
+        }
+        std::fs::remove_dir_all(PROJECT_PATH).unwrap();
+
+        for (asset_server, handle) in [
+            (&mut asset_server, handle),
+            (&mut async_asset_server, async_handle),
+        ] {
+            assert_eq!(asset_server.load_state(handle.id()), LoadState::Loaded);
+            assert_eq!(asset_server.len(), 3);
+            let texture_id = asset_server.guids[&texture];
+            assert_eq!(asset_server.load_state(texture_id), LoadState::Loaded);
+            assert_eq!(
+                asset_server.dependents(texture),
+                [asset_server.guids[&material]]
+            );
+
+            // Dependencies stay loaded while their dependents are referenced.
+            assert_eq!(asset_server.unload_unused(), 0);
+            drop(handle);
+            assert_eq!(asset_server.unload_unused(), 3);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn fail_on_dependency_cycle() {
+        const PROJECT_PATH: &str = "src/test_asset_files/server_dependency_cycle";
+
+        let first = AssetGuid::new();
+        let second = AssetGuid::new();
+        std::fs::create_dir_all(PROJECT_PATH).unwrap();
+        for (guid, name, dependency) in [(first, "first", second), (second, "second", first)] {
+            let mut asset_file = AssetFile::from_raw_parts(
+                name,
+                &format!("{PROJECT_PATH}/{name}.bin"),
+                AssetType::Mesh,
+                CompressionMode::Fast,
+                String::new(),
+                vec![],
+            )
+            .with_dependencies(vec![dependency]);
+            asset_file.guid = guid;
+            asset_file.save_asset_file().unwrap();
+        }
+        let mut registry = AssetRegistry::new(PROJECT_PATH);
+        registry.scan().unwrap();
+
+        let mut asset_server = AssetServer::with_registry(registry);
+        let handle = asset_server.load_by_guid::<AssetFile>(first);
+        std::fs::remove_dir_all(PROJECT_PATH).unwrap();
+
+        assert_eq!(asset_server.load_state(handle.id()), LoadState::Failed);
+        assert!(matches!(
+            asset_server.load_error(asset_server.guids[&second]),
+            Some(AssetError::DependencyCycle(_))
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn reload_changed_asset_in_place() {
+        const PROJECT_PATH: &str = "src/test_asset_files/server_hot_reload";
+        const ASSET_FILE_PATH: &str = "src/test_asset_files/server_hot_reload/asset_file.bin";
+
+        std::fs::create_dir_all(PROJECT_PATH).unwrap();
+        let mut asset_file = AssetFile::from_raw_parts(
+            "asset_file",
+            ASSET_FILE_PATH,
+            AssetType::Mesh,
+            CompressionMode::Fast,
+            String::new(),
+            vec![1, 2, 3],
+        );
+        asset_file.save_asset_file().unwrap();
+
+        let mut asset_server = AssetServer::new();
+        asset_server.watch_for_changes(PROJECT_PATH).unwrap();
+        let handle = asset_server.load::<AssetFile, _>(ASSET_FILE_PATH);
+        assert_eq!(asset_server.update(), [AssetEvent::Loaded(handle.id())]);
+
+        asset_file.raw_data = vec![4, 5, 6];
+        asset_file.checksum = crate::checksum(&asset_file.metadata, &asset_file.raw_data);
+        asset_file.save_asset_file().unwrap();
+
+        let started_at = std::time::Instant::now();
+        let mut events = Vec::new();
+        while !events.contains(&AssetEvent::Modified(handle.id()))
+            && started_at.elapsed() < std::time::Duration::from_secs(10)
+        {
+            events.extend(asset_server.update());
+            // The previous version stays available while the asset is being reloaded.
+            assert!(asset_server.get(&handle).is_some());
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        std::fs::remove_dir_all(PROJECT_PATH).unwrap();
+
+        assert_eq!(events, [AssetEvent::Modified(handle.id())]);
+        assert_eq!(asset_server.get(&handle).unwrap().raw_data(), [4, 5, 6]);
+
+        // A failed reload keeps the last loaded version.
+        asset_server.reload(&handle);
+        let started_at = std::time::Instant::now();
+        let mut events = Vec::new();
+        while events.is_empty() && started_at.elapsed() < std::time::Duration::from_secs(10) {
+            events.extend(asset_server.update());
+            std::thread::yield_now();
+        }
+        assert_eq!(events, [AssetEvent::Failed(handle.id())]);
+        assert_eq!(asset_server.load_state(handle.id()), LoadState::Loaded);
+        assert_eq!(asset_server.get(&handle).unwrap().raw_data(), [4, 5, 6]);
+    }
+}
@@ -0,0 +1,1287 @@
+use crate::{Asset, AssetError, AssetFile, AssetType, CompressionMode};
+use serde::{Deserialize, Serialize};
+use spirv::{Decoration, Dim, ExecutionModel, Op, StorageClass};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ShaderStage {
+    Vertex,
+    TessellationControl,
+    TessellationEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+    Task,
+    Mesh,
+}
+
+impl ShaderStage {
+    fn from_execution_model(execution_model: ExecutionModel) -> Option<Self> {
+        match execution_model {
+            ExecutionModel::Vertex => Some(ShaderStage::Vertex),
+            ExecutionModel::TessellationControl => Some(ShaderStage::TessellationControl),
+            ExecutionModel::TessellationEvaluation => Some(ShaderStage::TessellationEvaluation),
+            ExecutionModel::Geometry => Some(ShaderStage::Geometry),
+            ExecutionModel::Fragment => Some(ShaderStage::Fragment),
+            ExecutionModel::GLCompute => Some(ShaderStage::Compute),
+            ExecutionModel::TaskNV | ExecutionModel::TaskEXT => Some(ShaderStage::Task),
+            ExecutionModel::MeshNV | ExecutionModel::MeshEXT => Some(ShaderStage::Mesh),
+            _ => None,
+        }
+    }
+}
+
+// Named after the matching `VkDescriptorType`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DescriptorType {
+    Sampler,
+    CombinedImageSampler,
+    SampledImage,
+    StorageImage,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+    UniformBuffer,
+    StorageBuffer,
+    InputAttachment,
+    AccelerationStructure,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ScalarType {
+    Bool,
+    Int,
+    Uint,
+    Float,
+    Double,
+}
+
+// A scalar or a vector of `components` scalars.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AttributeType {
+    pub scalar: ScalarType,
+    pub components: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryPoint {
+    pub name: String,
+    pub stage: ShaderStage,
+    // Index of the SPIR-V module containing the entry point.
+    pub module: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: DescriptorType,
+    // Zero for runtime-sized arrays.
+    pub count: u32,
+    pub name: String,
+    pub stages: Vec<ShaderStage>,
+    // Members of uniform and storage buffer blocks, in declaration order.
+    #[serde(default)]
+    pub members: Vec<BlockMember>,
+}
+
+// `size` is zero for runtime arrays.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockMember {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushConstantRange {
+    pub offset: u32,
+    pub size: u32,
+    pub stages: Vec<ShaderStage>,
+}
+
+// A vertex input or a color output of a fragment shader.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceVariable {
+    pub location: u32,
+    pub attribute_type: AttributeType,
+    pub name: String,
+}
+
+// Entry points and the interface reflected from SPIR-V modules, e.g. one per stage. Bindings,
+// push constants and interface variables are sorted.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShaderAsset {
+    pub entry_points: Vec<EntryPoint>,
+    // Sizes of the modules in the blob, in words.
+    pub module_sizes: Vec<u32>,
+    pub descriptor_bindings: Vec<DescriptorBinding>,
+    pub push_constant_ranges: Vec<PushConstantRange>,
+    pub vertex_inputs: Vec<InterfaceVariable>,
+    pub output_attachments: Vec<InterfaceVariable>,
+}
+
+// SPIR-V modules with their reflection, the blob holds the modules one after another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shader {
+    pub metadata: ShaderAsset,
+    pub modules: Vec<Vec<u32>>,
+}
+
+impl Shader {
+    // Bindings used by several modules have to agree on their type and count.
+    pub fn from_spirv(modules: Vec<Vec<u32>>) -> Result<Self, AssetError> {
+        let mut metadata = ShaderAsset {
+            module_sizes: modules.iter().map(|module| module.len() as u32).collect(),
+            ..Default::default()
+        };
+
+        for (index, module) in modules.iter().enumerate() {
+            let reflection = Reflection::parse(module)?;
+            let stages = reflection.stages()?;
+            reflection.reflect_into(&mut metadata, index as u32, &stages)?;
+        }
+
+        metadata
+            .descriptor_bindings
+            .sort_by_key(|binding| (binding.set, binding.binding));
+        metadata
+            .push_constant_ranges
+            .sort_by_key(|range| (range.offset, range.size));
+        metadata.vertex_inputs.sort_by_key(|input| input.location);
+        metadata
+            .output_attachments
+            .sort_by_key(|output| output.location);
+
+        Ok(Self { metadata, modules })
+    }
+
+    // Modules as stored in `.spv` files, in either byte order.
+    pub fn from_spirv_bytes(modules: &[&[u8]]) -> Result<Self, AssetError> {
+        let modules = modules
+            .iter()
+            .map(|module| spirv_words(module))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::from_spirv(modules)
+    }
+
+    pub fn entry_point(&self, stage: ShaderStage) -> Option<&EntryPoint> {
+        self.metadata
+            .entry_points
+            .iter()
+            .find(|entry_point| entry_point.stage == stage)
+    }
+
+    // The module containing the entry point of the stage.
+    pub fn code(&self, stage: ShaderStage) -> Option<&[u32]> {
+        self.entry_point(stage)
+            .and_then(|entry_point| self.modules.get(entry_point.module as usize))
+            .map(Vec::as_slice)
+    }
+
+    pub fn into_asset_file(
+        self,
+        name: &str,
+        path: &str,
+        compression_mode: CompressionMode,
+    ) -> Result<AssetFile, AssetError> {
+        let raw_data = self
+            .modules
+            .iter()
+            .flatten()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+
+        AssetFile::new(self.metadata, name, path, raw_data, compression_mode)
+    }
+}
+
+impl super::Packaging for ShaderAsset {
+    fn pack(
+        &self,
+        name: &str,
+        path: &str,
+        raw_data: Vec<u8>,
+        compression_mode: super::CompressionMode,
+    ) -> Result<AssetFile, AssetError> {
+        let serialized = ron::to_string(self)?;
+
+        Ok(AssetFile::from_raw_parts(
+            name,
+            path,
+            AssetType::Shader,
+            compression_mode,
+            serialized,
+            raw_data,
+        ))
+    }
+}
+
+impl Asset for Shader {
+    fn from_asset_file(asset_file: AssetFile) -> Result<Self, AssetError> {
+        asset_file.expect_asset_type(AssetType::Shader)?;
+        let metadata: ShaderAsset = ron::from_str(asset_file.metadata())?;
+
+        if let Some(entry_point) = metadata
+            .entry_points
+            .iter()
+            .find(|entry_point| entry_point.module as usize >= metadata.module_sizes.len())
+        {
+            return Err(AssetError::Serialization(format!(
+                "The entry point {} is in the module {}, but the shader has {} modules.",
+                entry_point.name,
+                entry_point.module,
+                metadata.module_sizes.len()
+            )));
+        }
+
+        let raw_data = asset_file.raw_data();
+        let size = metadata
+            .module_sizes
+            .iter()
+            .try_fold(0usize, |size, &module_size| {
+                size.checked_add((module_size as usize).checked_mul(4)?)
+            });
+        if size != Some(raw_data.len()) {
+            return Err(AssetError::Serialization(
+                "Size of the SPIR-V modules doesn't match the shader metadata.".to_string(),
+            ));
+        }
+
+        let mut words = raw_data
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()));
+        let modules = metadata
+            .module_sizes
+            .iter()
+            .map(|&size| words.by_ref().take(size as usize).collect())
+            .collect();
+
+        Ok(Self { metadata, modules })
+    }
+}
+
+fn spirv_words(bytes: &[u8]) -> Result<Vec<u32>, AssetError> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(invalid("the size isn't a multiple of 4 bytes"));
+    }
+
+    let words = bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()));
+    match words.clone().next() {
+        Some(spirv::MAGIC_NUMBER) => Ok(words.collect()),
+        Some(magic) if magic.swap_bytes() == spirv::MAGIC_NUMBER => {
+            Ok(words.map(u32::swap_bytes).collect())
+        }
+        _ => Err(invalid("the magic number is missing")),
+    }
+}
+
+// Bounds the recursion through types, which malformed modules may nest endlessly.
+const MAX_TYPE_DEPTH: u32 = 64;
+
+fn invalid(reason: &str) -> AssetError {
+    AssetError::InvalidShader(format!("Invalid SPIR-V module, {reason}."))
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Type {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image { dim: Dim, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct,
+    Pointer { pointee: u32 },
+    AccelerationStructure,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Decorations {
+    set: Option<u32>,
+    binding: Option<u32>,
+    location: Option<u32>,
+    built_in: bool,
+    buffer_block: bool,
+    array_stride: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct MemberDecorations {
+    offset: Option<u32>,
+    matrix_stride: Option<u32>,
+}
+
+struct Variable {
+    id: u32,
+    pointee: u32,
+    storage_class: StorageClass,
+}
+
+struct RawEntryPoint {
+    execution_model: ExecutionModel,
+    name: String,
+    interface: Vec<u32>,
+}
+
+// The instructions of a module that matter for reflection.
+#[derive(Default)]
+struct Reflection {
+    entry_points: Vec<RawEntryPoint>,
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    decorations: HashMap<u32, Decorations>,
+    member_decorations: HashMap<(u32, u32), MemberDecorations>,
+    types: HashMap<u32, Type>,
+    struct_members: HashMap<u32, Vec<u32>>,
+    constants: HashMap<u32, u32>,
+    variables: Vec<Variable>,
+}
+
+impl Reflection {
+    fn parse(words: &[u32]) -> Result<Self, AssetError> {
+        if words.len() < 5 || words[0] != spirv::MAGIC_NUMBER {
+            return Err(invalid("the header is missing"));
+        }
+
+        let mut reflection = Self::default();
+        let mut rest = &words[5..];
+        while let Some(&first) = rest.first() {
+            let word_count = (first >> 16) as usize;
+            if word_count == 0 || word_count > rest.len() {
+                return Err(invalid("an instruction is truncated"));
+            }
+            let (instruction, next) = rest.split_at(word_count);
+            rest = next;
+
+            if let Some(op) = Op::from_u32(first & 0xFFFF) {
+                reflection.parse_instruction(op, &instruction[1..])?;
+            }
+        }
+
+        Ok(reflection)
+    }
+
+    fn parse_instruction(&mut self, op: Op, operands: &[u32]) -> Result<(), AssetError> {
+        let operand = |index: usize| {
+            operands
+                .get(index)
+                .copied()
+                .ok_or_else(|| invalid("an instruction lacks operands"))
+        };
+        let operands_from = |index: usize| {
+            operands
+                .get(index..)
+                .ok_or_else(|| invalid("an instruction lacks operands"))
+        };
+
+        match op {
+            Op::EntryPoint => {
+                let execution_model = ExecutionModel::from_u32(operand(0)?)
+                    .ok_or_else(|| invalid("an execution model is unknown"))?;
+                let (name, name_words) = literal_string(operands_from(2)?);
+                self.entry_points.push(RawEntryPoint {
+                    execution_model,
+                    name,
+                    interface: operands_from(2 + name_words)?.to_vec(),
+                });
+            }
+            Op::Name => {
+                let (name, _) = literal_string(operands_from(1)?);
+                self.names.insert(operand(0)?, name);
+            }
+            Op::MemberName => {
+                let (name, _) = literal_string(operands_from(2)?);
+                self.member_names.insert((operand(0)?, operand(1)?), name);
+            }
+            Op::Decorate => {
+                let decorations = self.decorations.entry(operand(0)?).or_default();
+                match Decoration::from_u32(operand(1)?) {
+                    Some(Decoration::DescriptorSet) => decorations.set = Some(operand(2)?),
+                    Some(Decoration::Binding) => decorations.binding = Some(operand(2)?),
+                    Some(Decoration::Location) => decorations.location = Some(operand(2)?),
+                    Some(Decoration::BuiltIn) => decorations.built_in = true,
+                    Some(Decoration::BufferBlock) => decorations.buffer_block = true,
+                    Some(Decoration::ArrayStride) => decorations.array_stride = Some(operand(2)?),
+                    _ => (),
+                }
+            }
+            Op::MemberDecorate => {
+                let decorations = self
+                    .member_decorations
+                    .entry((operand(0)?, operand(1)?))
+                    .or_default();
+                match Decoration::from_u32(operand(2)?) {
+                    Some(Decoration::Offset) => decorations.offset = Some(operand(3)?),
+                    Some(Decoration::MatrixStride) => decorations.matrix_stride = Some(operand(3)?),
+                    _ => (),
+                }
+            }
+            Op::TypeBool => {
+                self.types.insert(operand(0)?, Type::Bool);
+            }
+            Op::TypeInt => {
+                let width = operand(1)?;
+                let signed = operand(2)? == 1;
+                self.types.insert(operand(0)?, Type::Int { width, signed });
+            }
+            Op::TypeFloat => {
+                let width = operand(1)?;
+                self.types.insert(operand(0)?, Type::Float { width });
+            }
+            Op::TypeVector => {
+                let (component, count) = (operand(1)?, operand(2)?);
+                self.types
+                    .insert(operand(0)?, Type::Vector { component, count });
+            }
+            Op::TypeMatrix => {
+                let (column, count) = (operand(1)?, operand(2)?);
+                self.types
+                    .insert(operand(0)?, Type::Matrix { column, count });
+            }
+            Op::TypeImage => {
+                let dim = Dim::from_u32(operand(2)?)
+                    .ok_or_else(|| invalid("an image dimension is unknown"))?;
+                let sampled = operand(6)?;
+                self.types.insert(operand(0)?, Type::Image { dim, sampled });
+            }
+            Op::TypeSampler => {
+                self.types.insert(operand(0)?, Type::Sampler);
+            }
+            Op::TypeSampledImage => {
+                self.types.insert(operand(0)?, Type::SampledImage);
+            }
+            Op::TypeArray => {
+                let (element, length) = (operand(1)?, operand(2)?);
+                self.types
+                    .insert(operand(0)?, Type::Array { element, length });
+            }
+            Op::TypeRuntimeArray => {
+                let element = operand(1)?;
+                self.types
+                    .insert(operand(0)?, Type::RuntimeArray { element });
+            }
+            Op::TypeStruct => {
+                self.types.insert(operand(0)?, Type::Struct);
+                self.struct_members
+                    .insert(operand(0)?, operands_from(1)?.to_vec());
+            }
+            Op::TypePointer => {
+                let pointee = operand(2)?;
+                self.types.insert(operand(0)?, Type::Pointer { pointee });
+            }
+            Op::TypeAccelerationStructureKHR => {
+                self.types.insert(operand(0)?, Type::AccelerationStructure);
+            }
+            // Only 32-bit constants are needed, for the lengths of arrays.
+            Op::Constant => {
+                self.constants.insert(operand(1)?, operand(2)?);
+            }
+            Op::Variable => {
+                let pointer = operand(0)?;
+                let Some(&Type::Pointer { pointee }) = self.types.get(&pointer) else {
+                    return Err(invalid("a variable isn't a pointer"));
+                };
+                let storage_class = StorageClass::from_u32(operand(2)?)
+                    .ok_or_else(|| invalid("a storage class is unknown"))?;
+                self.variables.push(Variable {
+                    id: operand(1)?,
+                    pointee,
+                    storage_class,
+                });
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn stages(&self) -> Result<Vec<ShaderStage>, AssetError> {
+        let mut stages = Vec::new();
+        for entry_point in &self.entry_points {
+            let stage = ShaderStage::from_execution_model(entry_point.execution_model).ok_or_else(
+                || {
+                    AssetError::InvalidShader(format!(
+                        "The execution model {:?} isn't supported.",
+                        entry_point.execution_model
+                    ))
+                },
+            )?;
+            if !stages.contains(&stage) {
+                stages.push(stage);
+            }
+        }
+        match stages.is_empty() {
+            true => Err(invalid("there is no entry point")),
+            false => Ok(stages),
+        }
+    }
+
+    // Resources are attributed to every stage of the module, as finding the entry points that
+    // really access them would need a walk of the call graph.
+    fn reflect_into(
+        &self,
+        metadata: &mut ShaderAsset,
+        module: u32,
+        stages: &[ShaderStage],
+    ) -> Result<(), AssetError> {
+        for entry_point in &self.entry_points {
+            let stage = ShaderStage::from_execution_model(entry_point.execution_model).unwrap();
+            metadata.entry_points.push(EntryPoint {
+                name: entry_point.name.clone(),
+                stage,
+                module,
+            });
+
+            let interface = match stage {
+                ShaderStage::Vertex => &mut metadata.vertex_inputs,
+                ShaderStage::Fragment => &mut metadata.output_attachments,
+                _ => continue,
+            };
+            let storage_class = match stage {
+                ShaderStage::Vertex => StorageClass::Input,
+                _ => StorageClass::Output,
+            };
+            for variable in self
+                .variables
+                .iter()
+                .filter(|variable| variable.storage_class == storage_class)
+                .filter(|variable| entry_point.interface.contains(&variable.id))
+            {
+                let decorations = self.decorations(variable.id);
+                let Some(location) = decorations.location else {
+                    continue;
+                };
+                if decorations.built_in || interface.iter().any(|v| v.location == location) {
+                    continue;
+                }
+
+                interface.push(InterfaceVariable {
+                    location,
+                    attribute_type: self.attribute_type(variable.pointee)?,
+                    name: self.name(variable.id),
+                });
+            }
+        }
+
+        for variable in &self.variables {
+            match variable.storage_class {
+                StorageClass::UniformConstant
+                | StorageClass::Uniform
+                | StorageClass::StorageBuffer => {
+                    self.reflect_binding(metadata, variable, stages)?
+                }
+                StorageClass::PushConstant => {
+                    let range = self.push_constant_range(variable.pointee, stages)?;
+                    match metadata.push_constant_ranges.iter_mut().find(|existing| {
+                        (existing.offset, existing.size) == (range.offset, range.size)
+                    }) {
+                        Some(existing) => merge_stages(&mut existing.stages, stages),
+                        None => metadata.push_constant_ranges.push(range),
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn reflect_binding(
+        &self,
+        metadata: &mut ShaderAsset,
+        variable: &Variable,
+        stages: &[ShaderStage],
+    ) -> Result<(), AssetError> {
+        let decorations = self.decorations(variable.id);
+        let (Some(set), Some(binding)) = (decorations.set, decorations.binding) else {
+            return Ok(());
+        };
+
+        let (mut resource, mut count) = (variable.pointee, 1u32);
+        for depth in 0.. {
+            if depth > MAX_TYPE_DEPTH {
+                return Err(invalid("types are nested too deeply"));
+            }
+            match self.types.get(&resource) {
+                Some(&Type::Array { element, length }) => {
+                    let length = self
+                        .constants
+                        .get(&length)
+                        .copied()
+                        .ok_or_else(|| invalid("an array length isn't a constant"))?;
+                    count = count
+                        .checked_mul(length)
+                        .ok_or_else(|| invalid("a descriptor array is too large"))?;
+                    resource = element;
+                }
+                Some(&Type::RuntimeArray { element }) => {
+                    count = 0;
+                    resource = element;
+                }
+                _ => break,
+            }
+        }
+
+        let descriptor_type = match (self.types.get(&resource), variable.storage_class) {
+            (Some(Type::Sampler), _) => DescriptorType::Sampler,
+            (Some(Type::SampledImage), _) => DescriptorType::CombinedImageSampler,
+            (Some(&Type::Image { dim, sampled }), _) => match (dim, sampled) {
+                (Dim::DimSubpassData, _) => DescriptorType::InputAttachment,
+                (Dim::DimBuffer, 1) => DescriptorType::UniformTexelBuffer,
+                (Dim::DimBuffer, _) => DescriptorType::StorageTexelBuffer,
+                (_, 1) => DescriptorType::SampledImage,
+                _ => DescriptorType::StorageImage,
+            },
+            (Some(Type::AccelerationStructure), _) => DescriptorType::AccelerationStructure,
+            (Some(Type::Struct), StorageClass::StorageBuffer) => DescriptorType::StorageBuffer,
+            (Some(Type::Struct), _) if self.decorations(resource).buffer_block => {
+                DescriptorType::StorageBuffer
+            }
+            (Some(Type::Struct), _) => DescriptorType::UniformBuffer,
+            _ => return Err(invalid("a descriptor has an unknown type")),
+        };
+        let members = match descriptor_type {
+            DescriptorType::UniformBuffer | DescriptorType::StorageBuffer => {
+                self.block_members(resource)?
+            }
+            _ => Vec::new(),
+        };
+
+        let existing = metadata
+            .descriptor_bindings
+            .iter_mut()
+            .find(|existing| (existing.set, existing.binding) == (set, binding));
+        match existing {
+            Some(existing)
+                if (existing.descriptor_type, existing.count) == (descriptor_type, count) =>
+            {
+                merge_stages(&mut existing.stages, stages)
+            }
+            Some(_) => {
+                return Err(AssetError::InvalidShader(format!(
+                    "Stages declare the binding {binding} of the set {set} differently."
+                )))
+            }
+            None => metadata.descriptor_bindings.push(DescriptorBinding {
+                set,
+                binding,
+                descriptor_type,
+                count,
+                name: self.name(variable.id),
+                stages: stages.to_vec(),
+                members,
+            }),
+        }
+
+        Ok(())
+    }
+
+    // Members of nested structs are flattened into `outer.inner`, naga wraps blocks into a
+    // struct with an unnamed member.
+    fn block_members(&self, block: u32) -> Result<Vec<BlockMember>, AssetError> {
+        let mut members = Vec::new();
+        self.collect_block_members(block, 0, "", 0, &mut members)?;
+
+        Ok(members)
+    }
+
+    fn collect_block_members(
+        &self,
+        block: u32,
+        base_offset: u32,
+        prefix: &str,
+        depth: u32,
+        members: &mut Vec<BlockMember>,
+    ) -> Result<(), AssetError> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(invalid("types are nested too deeply"));
+        }
+        let struct_members = self
+            .struct_members
+            .get(&block)
+            .ok_or_else(|| invalid("a block isn't a struct"))?;
+
+        for (member, &member_type) in struct_members.iter().enumerate() {
+            let member = member as u32;
+            let decorations = self.member_decorations(block, member);
+            let offset = decorations
+                .offset
+                .and_then(|offset| base_offset.checked_add(offset))
+                .ok_or_else(|| invalid("a block member has no valid offset"))?;
+            let name = match (prefix, self.member_names.get(&(block, member))) {
+                (prefix, Some(name)) if !prefix.is_empty() => format!("{prefix}.{name}"),
+                (_, Some(name)) => name.clone(),
+                (prefix, None) => prefix.to_string(),
+            };
+
+            match self.types.get(&member_type) {
+                Some(Type::Struct) => {
+                    self.collect_block_members(member_type, offset, &name, depth + 1, members)?
+                }
+                Some(Type::RuntimeArray { .. }) => members.push(BlockMember {
+                    name,
+                    offset,
+                    size: 0,
+                }),
+                _ => members.push(BlockMember {
+                    name,
+                    offset,
+                    size: self.size(member_type, decorations.matrix_stride)?,
+                }),
+            }
+        }
+
+        Ok(())
+    }
+
+    // The range starts at the first member, as blocks of different stages may share a struct
+    // with members they don't use.
+    fn push_constant_range(
+        &self,
+        block: u32,
+        stages: &[ShaderStage],
+    ) -> Result<PushConstantRange, AssetError> {
+        let members = self
+            .struct_members
+            .get(&block)
+            .ok_or_else(|| invalid("a push constant block isn't a struct"))?;
+        let offset = (0..members.len() as u32)
+            .filter_map(|member| self.member_decorations(block, member).offset)
+            .min()
+            .unwrap_or(0);
+
+        Ok(PushConstantRange {
+            offset,
+            size: self.size(block, None)? - offset,
+            stages: stages.to_vec(),
+        })
+    }
+
+    fn size(&self, id: u32, matrix_stride: Option<u32>) -> Result<u32, AssetError> {
+        self.nested_size(id, matrix_stride, 0)
+    }
+
+    fn nested_size(
+        &self,
+        id: u32,
+        matrix_stride: Option<u32>,
+        depth: u32,
+    ) -> Result<u32, AssetError> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(invalid("types are nested too deeply"));
+        }
+        let too_large = || invalid("a block is too large");
+        let size_of = |id, matrix_stride| self.nested_size(id, matrix_stride, depth + 1);
+
+        let size = match self.types.get(&id) {
+            Some(Type::Bool) => 4,
+            Some(&Type::Int { width, .. }) | Some(&Type::Float { width }) => width / 8,
+            Some(&Type::Vector { component, count }) => count
+                .checked_mul(size_of(component, None)?)
+                .ok_or_else(too_large)?,
+            Some(&Type::Matrix { column, count }) => {
+                let stride = match matrix_stride {
+                    Some(matrix_stride) => matrix_stride,
+                    None => size_of(column, None)?,
+                };
+                count.checked_mul(stride).ok_or_else(too_large)?
+            }
+            Some(&Type::Array { element, length }) => {
+                let length = self
+                    .constants
+                    .get(&length)
+                    .copied()
+                    .ok_or_else(|| invalid("an array length isn't a constant"))?;
+                let stride = match self.decorations(id).array_stride {
+                    Some(stride) => stride,
+                    None => size_of(element, matrix_stride)?,
+                };
+                length.checked_mul(stride).ok_or_else(too_large)?
+            }
+            Some(Type::Struct) => {
+                let members = self
+                    .struct_members
+                    .get(&id)
+                    .ok_or_else(|| invalid("a block isn't a struct"))?;
+                let mut size = 0u32;
+                for (member, &member_type) in members.iter().enumerate() {
+                    let decorations = self.member_decorations(id, member as u32);
+                    let member_size = size_of(member_type, decorations.matrix_stride)?;
+                    let end = decorations
+                        .offset
+                        .unwrap_or(size)
+                        .checked_add(member_size)
+                        .ok_or_else(too_large)?;
+                    size = size.max(end);
+                }
+                size
+            }
+            _ => return Err(invalid("a block member has a type without a size")),
+        };
+
+        Ok(size)
+    }
+
+    fn attribute_type(&self, id: u32) -> Result<AttributeType, AssetError> {
+        let (scalar, components) = match self.types.get(&id) {
+            Some(&Type::Vector { component, count }) => (component, count),
+            _ => (id, 1),
+        };
+        let scalar =
+            match self.types.get(&scalar) {
+                Some(Type::Bool) => ScalarType::Bool,
+                Some(Type::Int { signed: true, .. }) => ScalarType::Int,
+                Some(Type::Int { signed: false, .. }) => ScalarType::Uint,
+                Some(Type::Float { width: 64 }) => ScalarType::Double,
+                Some(Type::Float { .. }) => ScalarType::Float,
+                _ => return Err(AssetError::InvalidShader(
+                    "Only scalars and vectors are supported as vertex inputs and color outputs."
+                        .to_string(),
+                )),
+            };
+
+        Ok(AttributeType { scalar, components })
+    }
+
+    fn decorations(&self, id: u32) -> Decorations {
+        self.decorations.get(&id).copied().unwrap_or_default()
+    }
+
+    fn member_decorations(&self, id: u32, member: u32) -> MemberDecorations {
+        self.member_decorations
+            .get(&(id, member))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn name(&self, id: u32) -> String {
+        self.names.get(&id).cloned().unwrap_or_default()
+    }
+}
+
+// Returns the string and the count of words it takes, including the terminating null.
+fn literal_string(words: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (index, word) in words.iter().enumerate() {
+        for byte in word.to_le_bytes() {
+            if byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), index + 1);
+            }
+            bytes.push(byte);
+        }
+    }
+
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
+}
+
+fn merge_stages(stages: &mut Vec<ShaderStage>, other: &[ShaderStage]) {
+    for &stage in other {
+        if !stages.contains(&stage) {
+            stages.push(stage);
+        }
+    }
+    stages.sort();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(op: Op, operands: &[u32]) -> Vec<u32> {
+        let mut words = vec![((operands.len() as u32 + 1) << 16) | op as u32];
+        words.extend(operands);
+        words
+    }
+
+    fn string(string: &str) -> Vec<u32> {
+        let mut bytes = string.as_bytes().to_vec();
+        bytes.resize(bytes.len() / 4 * 4 + 4, 0);
+        bytes
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect()
+    }
+
+    fn named(op: Op, id: u32, name: &str) -> Vec<u32> {
+        instruction(op, &[&[id][..], &string(name)].concat())
+    }
+
+    fn decorate(id: u32, decoration: Decoration, operands: &[u32]) -> Vec<u32> {
+        instruction(
+            Op::Decorate,
+            &[&[id, decoration as u32][..], operands].concat(),
+        )
+    }
+
+    fn module(
+        execution_model: ExecutionModel,
+        interface: &[u32],
+        instructions: Vec<Vec<u32>>,
+    ) -> Vec<u32> {
+        let mut words = vec![spirv::MAGIC_NUMBER, 0x0001_0000, 0, 100, 0];
+        words.extend(instruction(Op::Capability, &[1]));
+        words.extend(instruction(Op::MemoryModel, &[0, 1]));
+        let entry_point = [
+            &[execution_model as u32, 99][..],
+            &string("main"),
+            interface,
+        ]
+        .concat();
+        words.extend(instruction(Op::EntryPoint, &entry_point));
+        // Shared types: void, float, vectors, a 4x4 matrix, a parameter block and a texture.
+        for type_instruction in [
+            instruction(Op::TypeVoid, &[1]),
+            instruction(Op::TypeFloat, &[2, 32]),
+            instruction(Op::TypeVector, &[3, 2, 2]),
+            instruction(Op::TypeVector, &[4, 2, 3]),
+            instruction(Op::TypeVector, &[5, 2, 4]),
+            instruction(Op::TypeMatrix, &[6, 5, 4]),
+            instruction(Op::TypeStruct, &[7, 5, 2]),
+            instruction(Op::TypeImage, &[9, 2, Dim::Dim2D as u32, 0, 0, 0, 1, 0]),
+            instruction(Op::TypeSampledImage, &[10, 9]),
+            instruction(Op::TypePointer, &[14, StorageClass::Uniform as u32, 7]),
+            instruction(
+                Op::TypePointer,
+                &[15, StorageClass::UniformConstant as u32, 10],
+            ),
+            instruction(Op::Variable, &[14, 23, StorageClass::Uniform as u32]),
+            named(Op::Name, 23, "params"),
+            instruction(Op::MemberName, &[&[7, 0][..], &string("tint")].concat()),
+            instruction(Op::MemberName, &[&[7, 1][..], &string("strength")].concat()),
+            decorate(7, Decoration::Block, &[]),
+            instruction(Op::MemberDecorate, &[7, 0, Decoration::Offset as u32, 0]),
+            instruction(Op::MemberDecorate, &[7, 1, Decoration::Offset as u32, 16]),
+            decorate(23, Decoration::DescriptorSet, &[0]),
+            decorate(23, Decoration::Binding, &[0]),
+        ] {
+            words.extend(type_instruction);
+        }
+        instructions.into_iter().for_each(|i| words.extend(i));
+        words.extend(instruction(Op::TypeFunction, &[98, 1]));
+        words.extend(instruction(Op::Function, &[1, 99, 0, 98]));
+        words.extend(instruction(Op::Label, &[97]));
+        words.extend(instruction(Op::Return, &[]));
+        words.extend(instruction(Op::FunctionEnd, &[]));
+        words
+    }
+
+    fn vertex_module() -> Vec<u32> {
+        module(
+            ExecutionModel::Vertex,
+            &[20, 21, 22, 26],
+            vec![
+                instruction(Op::TypePointer, &[11, StorageClass::Input as u32, 4]),
+                instruction(Op::TypePointer, &[12, StorageClass::Input as u32, 3]),
+                instruction(Op::TypePointer, &[13, StorageClass::Output as u32, 3]),
+                instruction(Op::TypeStruct, &[8, 6]),
+                instruction(Op::TypePointer, &[16, StorageClass::PushConstant as u32, 8]),
+                instruction(Op::TypeInt, &[40, 32, 1]),
+                instruction(Op::TypePointer, &[41, StorageClass::Input as u32, 40]),
+                instruction(Op::Variable, &[11, 20, StorageClass::Input as u32]),
+                instruction(Op::Variable, &[12, 21, StorageClass::Input as u32]),
+                instruction(Op::Variable, &[13, 22, StorageClass::Output as u32]),
+                instruction(Op::Variable, &[16, 25, StorageClass::PushConstant as u32]),
+                instruction(Op::Variable, &[41, 26, StorageClass::Input as u32]),
+                named(Op::Name, 20, "position"),
+                named(Op::Name, 21, "uv"),
+                decorate(20, Decoration::Location, &[0]),
+                decorate(21, Decoration::Location, &[3]),
+                decorate(22, Decoration::Location, &[0]),
+                // gl_VertexIndex isn't a vertex input of the pipeline.
+                decorate(26, Decoration::BuiltIn, &[42]),
+                decorate(8, Decoration::Block, &[]),
+                instruction(Op::MemberDecorate, &[8, 0, Decoration::Offset as u32, 0]),
+                instruction(
+                    Op::MemberDecorate,
+                    &[8, 0, Decoration::MatrixStride as u32, 16],
+                ),
+            ],
+        )
+    }
+
+    fn fragment_module(albedo_binding: u32) -> Vec<u32> {
+        module(
+            ExecutionModel::Fragment,
+            &[21, 27],
+            vec![
+                instruction(Op::TypePointer, &[12, StorageClass::Input as u32, 3]),
+                instruction(Op::TypePointer, &[17, StorageClass::Output as u32, 5]),
+                instruction(Op::TypeInt, &[40, 32, 0]),
+                instruction(Op::Constant, &[40, 41, 4]),
+                instruction(Op::TypeArray, &[42, 10, 41]),
+                instruction(
+                    Op::TypePointer,
+                    &[43, StorageClass::UniformConstant as u32, 42],
+                ),
+                instruction(Op::TypeStruct, &[44, 5, 5]),
+                instruction(
+                    Op::TypePointer,
+                    &[45, StorageClass::PushConstant as u32, 44],
+                ),
+                instruction(Op::Variable, &[12, 21, StorageClass::Input as u32]),
+                instruction(Op::Variable, &[17, 27, StorageClass::Output as u32]),
+                instruction(
+                    Op::Variable,
+                    &[15, 24, StorageClass::UniformConstant as u32],
+                ),
+                instruction(
+                    Op::Variable,
+                    &[43, 28, StorageClass::UniformConstant as u32],
+                ),
+                instruction(Op::Variable, &[45, 29, StorageClass::PushConstant as u32]),
+                named(Op::Name, 24, "albedo"),
+                named(Op::Name, 27, "color"),
+                named(Op::Name, 28, "shadows"),
+                decorate(21, Decoration::Location, &[0]),
+                decorate(27, Decoration::Location, &[0]),
+                decorate(24, Decoration::DescriptorSet, &[0]),
+                decorate(24, Decoration::Binding, &[albedo_binding]),
+                decorate(28, Decoration::DescriptorSet, &[1]),
+                decorate(28, Decoration::Binding, &[0]),
+                // Only the second vec4 of the block is used by the fragment stage.
+                instruction(Op::MemberDecorate, &[44, 0, Decoration::Offset as u32, 64]),
+                instruction(Op::MemberDecorate, &[44, 1, Decoration::Offset as u32, 80]),
+            ],
+        )
+    }
+
+    #[test]
+    fn reflect_spirv_modules() {
+        let shader = Shader::from_spirv(vec![vertex_module(), fragment_module(1)]).unwrap();
+        let metadata = &shader.metadata;
+
+        assert_eq!(
+            metadata.entry_points,
+            [
+                EntryPoint {
+                    name: "main".to_string(),
+                    stage: ShaderStage::Vertex,
+                    module: 0,
+                },
+                EntryPoint {
+                    name: "main".to_string(),
+                    stage: ShaderStage::Fragment,
+                    module: 1,
+                },
+            ]
+        );
+        let bindings = metadata
+            .descriptor_bindings
+            .iter()
+            .map(|binding| {
+                (
+                    binding.set,
+                    binding.binding,
+                    binding.descriptor_type,
+                    binding.count,
+                    binding.name.as_str(),
+                    binding.stages.as_slice(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bindings,
+            [
+                (
+                    0,
+                    0,
+                    DescriptorType::UniformBuffer,
+                    1,
+                    "params",
+                    &[ShaderStage::Vertex, ShaderStage::Fragment][..]
+                ),
+                (
+                    0,
+                    1,
+                    DescriptorType::CombinedImageSampler,
+                    1,
+                    "albedo",
+                    &[ShaderStage::Fragment][..]
+                ),
+                (
+                    1,
+                    0,
+                    DescriptorType::CombinedImageSampler,
+                    4,
+                    "shadows",
+                    &[ShaderStage::Fragment][..]
+                ),
+            ]
+        );
+        assert_eq!(
+            metadata.descriptor_bindings[0].members,
+            [
+                BlockMember {
+                    name: "tint".to_string(),
+                    offset: 0,
+                    size: 16,
+                },
+                BlockMember {
+                    name: "strength".to_string(),
+                    offset: 16,
+                    size: 4,
+                },
+            ]
+        );
+        assert!(metadata.descriptor_bindings[1].members.is_empty());
+        assert_eq!(
+            metadata.push_constant_ranges,
+            [
+                PushConstantRange {
+                    offset: 0,
+                    size: 64,
+                    stages: vec![ShaderStage::Vertex],
+                },
+                PushConstantRange {
+                    offset: 64,
+                    size: 32,
+                    stages: vec![ShaderStage::Fragment],
+                },
+            ]
+        );
+
+        let float = |components| AttributeType {
+            scalar: ScalarType::Float,
+            components,
+        };
+        let interface = |variables: &[InterfaceVariable]| {
+            variables
+                .iter()
+                .map(|v| (v.location, v.attribute_type, v.name.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            interface(&metadata.vertex_inputs),
+            [
+                (0, float(3), "position".to_string()),
+                (3, float(2), "uv".to_string())
+            ]
+        );
+        assert_eq!(
+            interface(&metadata.output_attachments),
+            [(0, float(4), "color".to_string())]
+        );
+
+        let asset_file = shader
+            .clone()
+            .into_asset_file("shader", "shader.bin", CompressionMode::Fast)
+            .unwrap();
+        let loaded = Shader::from_asset_file(asset_file).unwrap();
+        assert_eq!(loaded, shader);
+        assert_eq!(
+            loaded.code(ShaderStage::Fragment).unwrap(),
+            fragment_module(1)
+        );
+        assert!(loaded.code(ShaderStage::Compute).is_none());
+    }
+
+    #[test]
+    fn reject_invalid_spirv() {
+        // Big-endian modules are accepted.
+        let big_endian = vertex_module()
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect::<Vec<_>>();
+        let shader = Shader::from_spirv_bytes(&[&big_endian]).unwrap();
+        assert_eq!(shader.modules, [vertex_module()]);
+
+        assert!(Shader::from_spirv_bytes(&[&big_endian[..10]]).is_err());
+        assert!(Shader::from_spirv_bytes(&[&[0; 20]]).is_err());
+
+        let mut truncated = vertex_module();
+        // Cuts `OpLabel` in half.
+        truncated.truncate(truncated.len() - 3);
+        assert!(Shader::from_spirv(vec![truncated]).is_err());
+
+        // The fragment stage declares the uniform buffer of the vertex stage as a texture.
+        assert!(matches!(
+            Shader::from_spirv(vec![vertex_module(), fragment_module(0)]),
+            Err(AssetError::InvalidShader(_))
+        ));
+
+        // Arrays containing themselves and sizes beyond 32 bits.
+        let push_constant_block = |array: Vec<u32>| {
+            module(
+                ExecutionModel::Vertex,
+                &[],
+                vec![
+                    instruction(Op::TypeInt, &[40, 32, 0]),
+                    instruction(Op::Constant, &[40, 41, 4]),
+                    instruction(Op::Constant, &[40, 42, u32::MAX]),
+                    array,
+                    instruction(Op::TypeStruct, &[51, 50]),
+                    instruction(
+                        Op::TypePointer,
+                        &[52, StorageClass::PushConstant as u32, 51],
+                    ),
+                    instruction(Op::Variable, &[52, 53, StorageClass::PushConstant as u32]),
+                    instruction(Op::MemberDecorate, &[51, 0, Decoration::Offset as u32, 0]),
+                ],
+            )
+        };
+        for array in [
+            instruction(Op::TypeArray, &[50, 50, 41]),
+            instruction(Op::TypeArray, &[50, 5, 42]),
+        ] {
+            assert!(matches!(
+                Shader::from_spirv(vec![push_constant_block(array)]),
+                Err(AssetError::InvalidShader(_))
+            ));
+        }
+        let descriptor_array = module(
+            ExecutionModel::Fragment,
+            &[],
+            vec![
+                instruction(Op::TypeInt, &[40, 32, 0]),
+                instruction(Op::Constant, &[40, 41, 4]),
+                instruction(Op::TypeArray, &[50, 50, 41]),
+                instruction(
+                    Op::TypePointer,
+                    &[52, StorageClass::UniformConstant as u32, 50],
+                ),
+                instruction(
+                    Op::Variable,
+                    &[52, 53, StorageClass::UniformConstant as u32],
+                ),
+                decorate(53, Decoration::DescriptorSet, &[0]),
+                decorate(53, Decoration::Binding, &[1]),
+            ],
+        );
+        assert!(matches!(
+            Shader::from_spirv(vec![descriptor_array]),
+            Err(AssetError::InvalidShader(_))
+        ));
+
+        // Names and entry points without their operands.
+        for truncated in [
+            instruction(Op::Name, &[]),
+            instruction(Op::MemberName, &[7]),
+            instruction(Op::EntryPoint, &[ExecutionModel::Vertex as u32]),
+        ] {
+            assert!(matches!(
+                Shader::from_spirv(vec![module(ExecutionModel::Vertex, &[], vec![truncated])]),
+                Err(AssetError::InvalidShader(_))
+            ));
+        }
+
+        // Metadata of asset files is checked against the blob.
+        let shader = Shader::from_spirv(vec![vertex_module()]).unwrap();
+        let mut missing_module = shader.clone();
+        missing_module.metadata.entry_points[0].module = 1;
+        let mut huge_modules = shader;
+        huge_modules.metadata.module_sizes = vec![u32::MAX, u32::MAX];
+        for shader in [missing_module, huge_modules] {
+            let asset_file = shader
+                .into_asset_file("shader", "shader.bin", CompressionMode::None)
+                .unwrap();
+            assert!(Shader::from_asset_file(asset_file).is_err());
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use crate::{AssetError, AssetFile, AssetGuid, AssetType, CompressionMode, ASSET_FILE_MAGIC};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// The blob is decompressed and handed to the sink in chunks of at most this size.
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+enum RawDataReader<R: Read> {
+    Stored(std::io::Take<R>),
+    Compressed(lz4::Decoder<R>),
+}
+
+impl<R: Read> Read for RawDataReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            RawDataReader::Stored(reader) => reader.read(buf),
+            RawDataReader::Compressed(decoder) => decoder.read(buf),
+        }
+    }
+}
+
+// Reads the header of an asset file up front and then streams its blob into a caller-provided
+// sink, so memory use stays bounded no matter how large the blob is.
+pub struct AssetReader<R: Read> {
+    // `raw_data` of the header is always empty.
+    header: AssetFile,
+    raw_data_offset: u64,
+    raw_data_size: u64,
+    raw_data: RawDataReader<R>,
+}
+
+impl AssetReader<File> {
+    pub fn open<T: AsRef<Path> + ?Sized>(path: &T) -> Result<Self, AssetError> {
+        Self::new(File::open(path)?)
+    }
+}
+
+impl<R: Read> AssetReader<R> {
+    pub fn new(mut asset_file: R) -> Result<Self, AssetError> {
+        let (header, raw_data_offset, raw_data_size) = read_header(&mut asset_file, None)?;
+
+        Self::with_header(header, raw_data_offset, raw_data_size, asset_file)
+    }
+
+    // `asset_file` has to be positioned at the start of the blob.
+    fn with_header(
+        header: AssetFile,
+        raw_data_offset: u64,
+        raw_data_size: u64,
+        asset_file: R,
+    ) -> Result<Self, AssetError> {
+        let raw_data = match header.compression_mode() {
+            CompressionMode::None => RawDataReader::Stored(asset_file.take(raw_data_size)),
+            _ => RawDataReader::Compressed(
+                lz4::Decoder::new(asset_file)
+                    .map_err(|e| AssetError::Compression(e.to_string()))?,
+            ),
+        };
+
+        Ok(Self {
+            header,
+            raw_data_offset,
+            raw_data_size,
+            raw_data,
+        })
+    }
+
+    // The header with an empty blob.
+    pub fn header(&self) -> &AssetFile {
+        &self.header
+    }
+
+    pub fn guid(&self) -> AssetGuid {
+        self.header.guid()
+    }
+
+    pub fn name(&self) -> &str {
+        self.header.name()
+    }
+
+    pub fn asset_type(&self) -> AssetType {
+        self.header.asset_type()
+    }
+
+    pub fn compression_mode(&self) -> CompressionMode {
+        self.header.compression_mode()
+    }
+
+    pub fn metadata(&self) -> &str {
+        self.header.metadata()
+    }
+
+    pub fn dependencies(&self) -> &[AssetGuid] {
+        self.header.dependencies()
+    }
+
+    // Position of the blob in the asset file, everything after it is the stored blob.
+    pub fn raw_data_offset(&self) -> u64 {
+        self.raw_data_offset
+    }
+
+    // Size of the decompressed blob, e.g. to allocate a staging buffer before streaming into it.
+    pub fn raw_data_size(&self) -> u64 {
+        self.raw_data_size
+    }
+
+    // Returns the count of bytes written to the sink. The checksum can only be verified once the
+    // whole blob was streamed, so on a mismatch the sink has already received the corrupted data.
+    pub fn read_raw_data_to<W: Write>(mut self, sink: W) -> Result<u64, AssetError> {
+        self.stream_raw_data(sink)
+    }
+
+    pub fn into_asset_file(mut self) -> Result<AssetFile, AssetError> {
+        // The size comes from the file, so the blob grows as it's read instead of trusting it.
+        let mut raw_data = Vec::new();
+        self.stream_raw_data(&mut raw_data)?;
+
+        let mut asset_file = self.header;
+        asset_file.raw_data = raw_data;
+
+        Ok(asset_file)
+    }
+
+    fn stream_raw_data<W: Write>(&mut self, mut sink: W) -> Result<u64, AssetError> {
+        let mut hasher = crate::checksum_hasher(self.header.metadata());
+        let mut chunk = vec![0; STREAM_CHUNK_SIZE];
+        let mut written = 0;
+
+        loop {
+            let read = match &mut self.raw_data {
+                RawDataReader::Stored(reader) => reader.read(&mut chunk)?,
+                RawDataReader::Compressed(decoder) => decoder
+                    .read(&mut chunk)
+                    .map_err(|e| AssetError::Compression(e.to_string()))?,
+            };
+            if read == 0 {
+                break;
+            }
+
+            hasher.update(&chunk[..read]);
+            sink.write_all(&chunk[..read])?;
+            written += read as u64;
+        }
+
+        if written != self.raw_data_size {
+            return Err(AssetError::Serialization(format!(
+                "The blob of the asset file has {written} bytes instead of {}.",
+                self.raw_data_size
+            )));
+        }
+        self.header.verify_digest(hasher.digest())?;
+
+        Ok(written)
+    }
+}
+
+// Reads the header and leaves `asset_file` at the start of the blob. Returns the header, the
+// offset of the blob and the size of the decompressed blob. `len` is the size of the asset file
+// if it's known, sizes past it are rejected before anything is allocated for them.
+fn read_header<R: Read>(
+    asset_file: &mut R,
+    len: Option<u64>,
+) -> Result<(AssetFile, u64, u64), AssetError> {
+    let mut magic = [0; 4];
+    asset_file.read_exact(&mut magic)?;
+    if &magic != ASSET_FILE_MAGIC {
+        return Err(AssetError::Serialization(
+            "The asset file has an unknown format and can't be streamed.".to_string(),
+        ));
+    }
+
+    let mut sizes = [0; 16];
+    asset_file.read_exact(&mut sizes)?;
+    let (header_size, raw_data_size) = crate::parse_sizes(&sizes)?;
+    let raw_data_offset = crate::raw_data_offset(header_size) as u64;
+    if let Some(len) = len {
+        if raw_data_offset > len {
+            return Err(AssetError::Serialization(
+                "The header of the asset file is truncated.".to_string(),
+            ));
+        }
+    }
+
+    // The header is followed by the padding before the blob.
+    let mut header = vec![0; crate::raw_data_offset(header_size) - crate::header_end(0)];
+    asset_file.read_exact(&mut header)?;
+    let header = AssetFile::parse_header(&header[..header_size])?;
+
+    // Only a stored blob has to fit into the asset file as is.
+    if let (Some(len), CompressionMode::None) = (len, header.compression_mode()) {
+        if raw_data_offset
+            .checked_add(raw_data_size)
+            .is_none_or(|end| end > len)
+        {
+            return Err(AssetError::Serialization(
+                "The blob of the asset file is truncated.".to_string(),
+            ));
+        }
+    }
+
+    Ok((header, raw_data_offset, raw_data_size))
+}
+
+// An asset file of which only the header was read. The blob is read on request, as often as
+// needed, by seeking back to it. The asset file may start anywhere in the reader, e.g. inside a
+// pack file.
+pub struct LazyAssetFile<R: Read + Seek> {
+    // `raw_data` of the header is always empty.
+    header: AssetFile,
+    // Position of the blob in the reader.
+    raw_data_position: u64,
+    raw_data_offset: u64,
+    raw_data_size: u64,
+    asset_file: R,
+}
+
+impl LazyAssetFile<File> {
+    pub fn open<T: AsRef<Path> + ?Sized>(path: &T) -> Result<Self, AssetError> {
+        Self::new(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> LazyAssetFile<R> {
+    // Reads the header from the current position of `asset_file`.
+    pub fn new(mut asset_file: R) -> Result<Self, AssetError> {
+        let start = asset_file.stream_position()?;
+        let end = asset_file.seek(SeekFrom::End(0))?;
+        asset_file.seek(SeekFrom::Start(start))?;
+        let (header, raw_data_offset, raw_data_size) =
+            read_header(&mut asset_file, Some(end.saturating_sub(start)))?;
+
+        Ok(Self {
+            header,
+            raw_data_position: start + raw_data_offset,
+            raw_data_offset,
+            raw_data_size,
+            asset_file,
+        })
+    }
+
+    // The header with an empty blob.
+    pub fn header(&self) -> &AssetFile {
+        &self.header
+    }
+
+    pub fn raw_data_offset(&self) -> u64 {
+        self.raw_data_offset
+    }
+
+    pub fn raw_data_size(&self) -> u64 {
+        self.raw_data_size
+    }
+
+    pub fn read_raw_data(&mut self) -> Result<Vec<u8>, AssetError> {
+        let mut raw_data = Vec::new();
+        self.read_raw_data_to(&mut raw_data)?;
+
+        Ok(raw_data)
+    }
+
+    // Streams the blob like `AssetReader::read_raw_data_to`.
+    pub fn read_raw_data_to<W: Write>(&mut self, sink: W) -> Result<u64, AssetError> {
+        self.asset_file
+            .seek(SeekFrom::Start(self.raw_data_position))?;
+
+        AssetReader::with_header(
+            self.header.clone(),
+            self.raw_data_offset,
+            self.raw_data_size,
+            &mut self.asset_file,
+        )?
+        .read_raw_data_to(sink)
+    }
+
+    pub fn into_asset_file(mut self) -> Result<AssetFile, AssetError> {
+        let raw_data = self.read_raw_data()?;
+        let mut asset_file = self.header;
+        asset_file.raw_data = raw_data;
+
+        Ok(asset_file)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.asset_file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Records the largest write to check that the blob is streamed in bounded chunks.
+    struct ChunkSink<'a> {
+        buffer: &'a mut [u8],
+        largest_write: usize,
+    }
+
+    impl Write for ChunkSink<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.largest_write = self.largest_write.max(buf.len());
+            self.buffer.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn stream_blob_into_sink() {
+        const ASSET_FILE_PATH: &str = "src/test_asset_files/streamed.bin";
+
+        let raw_data = (0..1024 * 1024)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        AssetFile::from_raw_parts(
+            "streamed",
+            ASSET_FILE_PATH,
+            AssetType::Texture,
+            CompressionMode::Fast,
+            "HI".to_string(),
+            raw_data.clone(),
+        )
+        .save_asset_file()
+        .unwrap();
+
+        let reader = AssetReader::open(ASSET_FILE_PATH).unwrap();
+        assert_eq!(reader.metadata(), "HI");
+        assert_eq!(reader.raw_data_size(), raw_data.len() as u64);
+
+        let mut staging_buffer = vec![0; reader.raw_data_size() as usize];
+        let mut sink = ChunkSink {
+            buffer: &mut staging_buffer,
+            largest_write: 0,
+        };
+        let written = reader.read_raw_data_to(&mut sink).unwrap();
+        assert!(sink.largest_write <= STREAM_CHUNK_SIZE);
+        assert_eq!(written, raw_data.len() as u64);
+        assert_eq!(staging_buffer, raw_data);
+
+        // A sink that is too small fails instead of truncating the blob.
+        let reader = AssetReader::open(ASSET_FILE_PATH).unwrap();
+        let mut small_buffer = vec![0; 16];
+        let result = reader.read_raw_data_to(small_buffer.as_mut_slice());
+        std::fs::remove_file(ASSET_FILE_PATH).unwrap();
+
+        assert!(matches!(result, Err(AssetError::Io(_))));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn read_blob_lazily_at_an_offset() {
+        let raw_data = (0..100_000).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+        let asset_file = AssetFile::from_raw_parts(
+            "lazy",
+            "lazy.bin",
+            AssetType::Mesh,
+            CompressionMode::Fast,
+            "HI".to_string(),
+            raw_data.clone(),
+        );
+
+        // The asset file follows other data, like inside a pack file.
+        let mut stored = vec![0xAB; 10];
+        asset_file.write_to(&mut stored).unwrap();
+        let mut reader = std::io::Cursor::new(stored);
+        reader.seek(SeekFrom::Start(10)).unwrap();
+
+        let mut lazy = LazyAssetFile::new(reader).unwrap();
+        assert_eq!(lazy.header().name(), "lazy");
+        assert!(lazy.header().raw_data().is_empty());
+        assert_eq!(lazy.raw_data_size(), raw_data.len() as u64);
+        assert_eq!(lazy.read_raw_data().unwrap(), raw_data);
+        assert_eq!(lazy.read_raw_data().unwrap(), raw_data);
+        assert_eq!(lazy.into_asset_file().unwrap().raw_data(), raw_data);
+    }
+
+    #[test]
+    fn reject_invalid_sizes() {
+        let asset_file = AssetFile::from_raw_parts(
+            "invalid_sizes",
+            "invalid_sizes.bin",
+            AssetType::Mesh,
+            CompressionMode::None,
+            String::new(),
+            vec![1, 2, 3],
+        );
+        let mut content = Vec::new();
+        asset_file.write_to(&mut content).unwrap();
+        let corrupt = |offsets: &[usize], size: u64| {
+            let mut corrupted = content.clone();
+            for &offset in offsets {
+                corrupted[offset..offset + 8].copy_from_slice(&size.to_le_bytes());
+            }
+            std::io::Cursor::new(corrupted)
+        };
+
+        let results = [
+            AssetReader::new(corrupt(&[4, 12], u64::MAX)).map(|_| ()),
+            LazyAssetFile::new(corrupt(&[4, 12], u64::MAX)).map(|_| ()),
+            // A header past the end of the asset file.
+            LazyAssetFile::new(corrupt(&[4], 1 << 20)).map(|_| ()),
+            // A blob past the end of the asset file, which a plain reader only notices once it
+            // runs out of data.
+            LazyAssetFile::new(corrupt(&[12], u64::MAX)).map(|_| ()),
+            AssetReader::new(corrupt(&[12], u64::MAX))
+                .unwrap()
+                .into_asset_file()
+                .map(|_| ()),
+        ];
+
+        for result in results {
+            assert!(
+                matches!(result, Err(AssetError::Serialization(_))),
+                "{result:?}"
+            );
+        }
+    }
+}
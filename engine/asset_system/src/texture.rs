@@ -1,30 +1,579 @@
-use crate::{AssetFile, AssetType};
+use crate::{Asset, AssetError, AssetFile, AssetType};
 use serde::{Deserialize, Serialize};
 
+// Discriminants match the values of `VkFormat`.
 #[repr(u8)]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TextureFormat {
-    RGBA8 = 43,
-    RGB8 = 29,
+    R8Unorm = 9,
+    R8Srgb = 15,
+    RG8Unorm = 16,
+    RG8Srgb = 22,
+    RGB8Unorm = 23,
+    #[serde(alias = "RGB8")]
+    RGB8Srgb = 29,
+    RGBA8Unorm = 37,
+    #[serde(alias = "RGBA8")]
+    RGBA8Srgb = 43,
+    RGBA16F = 97,
+    RGBA32F = 109,
+    R11G11B10F = 122,
+    D16Unorm = 124,
+    D32F = 126,
+    D24UnormS8Uint = 129,
+    D32FS8Uint = 130,
+    BC1RgbUnorm = 131,
+    BC1RgbSrgb = 132,
+    BC1RgbaUnorm = 133,
+    BC1RgbaSrgb = 134,
+    BC2Unorm = 135,
+    BC2Srgb = 136,
+    BC3Unorm = 137,
+    BC3Srgb = 138,
+    BC4Unorm = 139,
+    BC4Snorm = 140,
+    BC5Unorm = 141,
+    BC5Snorm = 142,
+    BC6HUfloat = 143,
+    BC6HSfloat = 144,
+    BC7Unorm = 145,
+    BC7Srgb = 146,
+    ASTC4x4Unorm = 157,
+    ASTC4x4Srgb = 158,
+    ASTC5x4Unorm = 159,
+    ASTC5x4Srgb = 160,
+    ASTC5x5Unorm = 161,
+    ASTC5x5Srgb = 162,
+    ASTC6x5Unorm = 163,
+    ASTC6x5Srgb = 164,
+    ASTC6x6Unorm = 165,
+    ASTC6x6Srgb = 166,
+    ASTC8x5Unorm = 167,
+    ASTC8x5Srgb = 168,
+    ASTC8x6Unorm = 169,
+    ASTC8x6Srgb = 170,
+    ASTC8x8Unorm = 171,
+    ASTC8x8Srgb = 172,
+    ASTC10x5Unorm = 173,
+    ASTC10x5Srgb = 174,
+    ASTC10x6Unorm = 175,
+    ASTC10x6Srgb = 176,
+    ASTC10x8Unorm = 177,
+    ASTC10x8Srgb = 178,
+    ASTC10x10Unorm = 179,
+    ASTC10x10Srgb = 180,
+    ASTC12x10Unorm = 181,
+    ASTC12x10Srgb = 182,
+    ASTC12x12Unorm = 183,
+    ASTC12x12Srgb = 184,
     Unknown = 0,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+const ALL_TEXTURE_FORMATS: [TextureFormat; 60] = [
+    TextureFormat::R8Unorm,
+    TextureFormat::R8Srgb,
+    TextureFormat::RG8Unorm,
+    TextureFormat::RG8Srgb,
+    TextureFormat::RGB8Unorm,
+    TextureFormat::RGB8Srgb,
+    TextureFormat::RGBA8Unorm,
+    TextureFormat::RGBA8Srgb,
+    TextureFormat::RGBA16F,
+    TextureFormat::RGBA32F,
+    TextureFormat::R11G11B10F,
+    TextureFormat::D16Unorm,
+    TextureFormat::D32F,
+    TextureFormat::D24UnormS8Uint,
+    TextureFormat::D32FS8Uint,
+    TextureFormat::BC1RgbUnorm,
+    TextureFormat::BC1RgbSrgb,
+    TextureFormat::BC1RgbaUnorm,
+    TextureFormat::BC1RgbaSrgb,
+    TextureFormat::BC2Unorm,
+    TextureFormat::BC2Srgb,
+    TextureFormat::BC3Unorm,
+    TextureFormat::BC3Srgb,
+    TextureFormat::BC4Unorm,
+    TextureFormat::BC4Snorm,
+    TextureFormat::BC5Unorm,
+    TextureFormat::BC5Snorm,
+    TextureFormat::BC6HUfloat,
+    TextureFormat::BC6HSfloat,
+    TextureFormat::BC7Unorm,
+    TextureFormat::BC7Srgb,
+    TextureFormat::ASTC4x4Unorm,
+    TextureFormat::ASTC4x4Srgb,
+    TextureFormat::ASTC5x4Unorm,
+    TextureFormat::ASTC5x4Srgb,
+    TextureFormat::ASTC5x5Unorm,
+    TextureFormat::ASTC5x5Srgb,
+    TextureFormat::ASTC6x5Unorm,
+    TextureFormat::ASTC6x5Srgb,
+    TextureFormat::ASTC6x6Unorm,
+    TextureFormat::ASTC6x6Srgb,
+    TextureFormat::ASTC8x5Unorm,
+    TextureFormat::ASTC8x5Srgb,
+    TextureFormat::ASTC8x6Unorm,
+    TextureFormat::ASTC8x6Srgb,
+    TextureFormat::ASTC8x8Unorm,
+    TextureFormat::ASTC8x8Srgb,
+    TextureFormat::ASTC10x5Unorm,
+    TextureFormat::ASTC10x5Srgb,
+    TextureFormat::ASTC10x6Unorm,
+    TextureFormat::ASTC10x6Srgb,
+    TextureFormat::ASTC10x8Unorm,
+    TextureFormat::ASTC10x8Srgb,
+    TextureFormat::ASTC10x10Unorm,
+    TextureFormat::ASTC10x10Srgb,
+    TextureFormat::ASTC12x10Unorm,
+    TextureFormat::ASTC12x10Srgb,
+    TextureFormat::ASTC12x12Unorm,
+    TextureFormat::ASTC12x12Srgb,
+    TextureFormat::Unknown,
+];
+
+impl TextureFormat {
+    // Width and height in texels of a block, 1x1 for uncompressed formats.
+    pub fn block_dimensions(self) -> (u32, u32) {
+        use TextureFormat::*;
+
+        match self {
+            BC1RgbUnorm | BC1RgbSrgb | BC1RgbaUnorm | BC1RgbaSrgb | BC2Unorm | BC2Srgb
+            | BC3Unorm | BC3Srgb | BC4Unorm | BC4Snorm | BC5Unorm | BC5Snorm | BC6HUfloat
+            | BC6HSfloat | BC7Unorm | BC7Srgb | ASTC4x4Unorm | ASTC4x4Srgb => (4, 4),
+            ASTC5x4Unorm | ASTC5x4Srgb => (5, 4),
+            ASTC5x5Unorm | ASTC5x5Srgb => (5, 5),
+            ASTC6x5Unorm | ASTC6x5Srgb => (6, 5),
+            ASTC6x6Unorm | ASTC6x6Srgb => (6, 6),
+            ASTC8x5Unorm | ASTC8x5Srgb => (8, 5),
+            ASTC8x6Unorm | ASTC8x6Srgb => (8, 6),
+            ASTC8x8Unorm | ASTC8x8Srgb => (8, 8),
+            ASTC10x5Unorm | ASTC10x5Srgb => (10, 5),
+            ASTC10x6Unorm | ASTC10x6Srgb => (10, 6),
+            ASTC10x8Unorm | ASTC10x8Srgb => (10, 8),
+            ASTC10x10Unorm | ASTC10x10Srgb => (10, 10),
+            ASTC12x10Unorm | ASTC12x10Srgb => (12, 10),
+            ASTC12x12Unorm | ASTC12x12Srgb => (12, 12),
+            _ => (1, 1),
+        }
+    }
+
+    // Size of a block in bytes, which is the size of a texel for uncompressed formats.
+    pub fn bytes_per_block(self) -> u32 {
+        use TextureFormat::*;
+
+        match self {
+            Unknown => 0,
+            R8Unorm | R8Srgb => 1,
+            RG8Unorm | RG8Srgb | D16Unorm => 2,
+            RGB8Unorm | RGB8Srgb => 3,
+            RGBA8Unorm | RGBA8Srgb | R11G11B10F | D32F | D24UnormS8Uint => 4,
+            // Implementations usually pad the stencil of D32FS8Uint to 32 bits.
+            RGBA16F | D32FS8Uint => 8,
+            RGBA32F => 16,
+            BC1RgbUnorm | BC1RgbSrgb | BC1RgbaUnorm | BC1RgbaSrgb | BC4Unorm | BC4Snorm => 8,
+            // BC2, BC3, BC5, BC6H, BC7 and every ASTC block size.
+            _ => 16,
+        }
+    }
+
+    pub fn is_block_compressed(self) -> bool {
+        self.block_dimensions() != (1, 1)
+    }
+
+    pub fn is_astc(self) -> bool {
+        (TextureFormat::ASTC4x4Unorm as u8..=TextureFormat::ASTC12x12Srgb as u8)
+            .contains(&(self as u8))
+    }
+
+    pub fn is_depth(self) -> bool {
+        use TextureFormat::*;
+
+        matches!(self, D16Unorm | D32F | D24UnormS8Uint | D32FS8Uint)
+    }
+
+    pub fn has_stencil(self) -> bool {
+        matches!(
+            self,
+            TextureFormat::D24UnormS8Uint | TextureFormat::D32FS8Uint
+        )
+    }
+
+    pub fn is_srgb(self) -> bool {
+        self.to_unorm() != self
+    }
+
+    // The sRGB counterpart of a UNORM format, other formats are returned as is.
+    pub fn to_srgb(self) -> Self {
+        match self.srgb_pair() {
+            Some((_, srgb)) => srgb,
+            None => self,
+        }
+    }
+
+    // The UNORM counterpart of an sRGB format, other formats are returned as is.
+    pub fn to_unorm(self) -> Self {
+        match self.srgb_pair() {
+            Some((unorm, _)) => unorm,
+            None => self,
+        }
+    }
+
+    fn srgb_pair(self) -> Option<(Self, Self)> {
+        use TextureFormat::*;
+
+        let pairs = [
+            (R8Unorm, R8Srgb),
+            (RG8Unorm, RG8Srgb),
+            (RGB8Unorm, RGB8Srgb),
+            (RGBA8Unorm, RGBA8Srgb),
+            (BC1RgbUnorm, BC1RgbSrgb),
+            (BC1RgbaUnorm, BC1RgbaSrgb),
+            (BC2Unorm, BC2Srgb),
+            (BC3Unorm, BC3Srgb),
+            (BC7Unorm, BC7Srgb),
+        ];
+        if let Some(pair) = pairs
+            .into_iter()
+            .find(|&(unorm, srgb)| self == unorm || self == srgb)
+        {
+            return Some(pair);
+        }
+
+        // Every ASTC block size has a UNORM value followed by an sRGB one.
+        let value = self as u8;
+        match value {
+            157..=184 => {
+                let unorm = value - (value - 157) % 2;
+                Some((Self::from_value(unorm)?, Self::from_value(unorm + 1)?))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn from_vk_format(value: i32) -> Option<Self> {
+        Self::from_value(u8::try_from(value).ok()?)
+    }
+
+    fn from_value(value: u8) -> Option<Self> {
+        ALL_TEXTURE_FORMATS
+            .iter()
+            .copied()
+            .find(|&format| format as u8 == value)
+    }
+
+    // Size of the texels of an image, rounded up to whole blocks.
+    pub fn data_size(self, width: u32, height: u32) -> usize {
+        let (block_width, block_height) = self.block_dimensions();
+
+        width.div_ceil(block_width) as usize
+            * height.div_ceil(block_height) as usize
+            * self.bytes_per_block() as usize
+    }
+}
+
+// How the texels have to be interpreted, color textures are usually authored in sRGB while
+// normal maps, masks and HDR images are linear.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    Linear,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TextureDimension {
+    #[default]
+    D2,
+    D2Array,
+    // Six layers per cube, ordered as `CUBE_FACES`.
+    Cube,
+    CubeArray,
+    D3,
+}
+
+// Faces of a cube in the order of its layers, which is the order Vulkan expects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+pub const CUBE_FACES: [CubeFace; 6] = [
+    CubeFace::PositiveX,
+    CubeFace::NegativeX,
+    CubeFace::PositiveY,
+    CubeFace::NegativeY,
+    CubeFace::PositiveZ,
+    CubeFace::NegativeZ,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TextureAsset {
     pub texture_format: TextureFormat,
     pub width: u32,
     pub height: u32,
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    // Mip levels are stored one after another, starting from the full resolution. Every level
+    // holds all of its array layers or depth slices.
+    #[serde(default = "default_one")]
+    pub mip_levels: u32,
+    #[serde(default)]
+    pub dimension: TextureDimension,
+    // Counts the faces of cubes, so a cube array of two cubes has 12 layers.
+    #[serde(default = "default_one")]
+    pub array_layers: u32,
+    // Only 3D textures are deeper than one slice.
+    #[serde(default = "default_one")]
+    pub depth: u32,
+}
+
+fn default_one() -> u32 {
+    1
+}
+
+// A loaded texture: its metadata together with the texel data.
+#[derive(Debug)]
+pub struct Texture {
+    pub metadata: TextureAsset,
+    pub data: Vec<u8>,
 }
 
 impl TextureAsset {
-    #[allow(unused)]
     pub fn new(texture_format: TextureFormat, width: u32, height: u32) -> Self {
         Self {
             texture_format,
             width,
             height,
+            color_space: ColorSpace::default(),
+            mip_levels: 1,
+            dimension: TextureDimension::D2,
+            array_layers: 1,
+            depth: 1,
         }
     }
+
+    pub fn new_cube(texture_format: TextureFormat, size: u32) -> Self {
+        Self::new(texture_format, size, size).with_layers(TextureDimension::Cube, 6)
+    }
+
+    pub fn new_3d(texture_format: TextureFormat, width: u32, height: u32, depth: u32) -> Self {
+        Self {
+            dimension: TextureDimension::D3,
+            depth,
+            ..Self::new(texture_format, width, height)
+        }
+    }
+
+    // `array_layers` includes the faces of cubes.
+    pub fn with_layers(mut self, dimension: TextureDimension, array_layers: u32) -> Self {
+        self.dimension = dimension;
+        self.array_layers = array_layers;
+        self
+    }
+
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    pub fn with_mip_levels(mut self, mip_levels: u32) -> Self {
+        self.mip_levels = mip_levels;
+        self
+    }
+
+    pub fn mip_extent(&self, mip_level: u32) -> (u32, u32) {
+        (
+            (self.width >> mip_level).max(1),
+            (self.height >> mip_level).max(1),
+        )
+    }
+
+    pub fn mip_depth(&self, mip_level: u32) -> u32 {
+        (self.depth >> mip_level).max(1)
+    }
+
+    // 2D images a mip level consists of, array layers or depth slices.
+    pub fn mip_slices(&self, mip_level: u32) -> u32 {
+        self.array_layers * self.mip_depth(mip_level)
+    }
+
+    // Byte range of a mip level in the texel data.
+    pub fn mip_level_range(&self, mip_level: u32) -> std::ops::Range<usize> {
+        let offset = (0..mip_level).map(|level| self.mip_level_size(level)).sum();
+
+        offset..offset + self.mip_level_size(mip_level)
+    }
+
+    pub fn data_size(&self) -> usize {
+        (0..self.mip_levels)
+            .map(|level| self.mip_level_size(level))
+            .sum()
+    }
+
+    // Checks that the layer and depth counts fit the dimension.
+    pub fn validate(&self) -> Result<(), AssetError> {
+        let is_valid = self.width > 0
+            && self.height > 0
+            && self.mip_levels > 0
+            && match self.dimension {
+                TextureDimension::D2 => self.array_layers == 1 && self.depth == 1,
+                TextureDimension::D2Array => self.array_layers > 0 && self.depth == 1,
+                TextureDimension::Cube => {
+                    self.array_layers == 6 && self.depth == 1 && self.width == self.height
+                }
+                TextureDimension::CubeArray => {
+                    self.array_layers > 0
+                        && self.array_layers.is_multiple_of(6)
+                        && self.depth == 1
+                        && self.width == self.height
+                }
+                TextureDimension::D3 => self.array_layers == 1 && self.depth > 0,
+            };
+
+        match is_valid {
+            true => Ok(()),
+            false => Err(AssetError::Serialization(format!(
+                "Invalid {:?} texture of {}x{}x{} with {} layers and {} mip levels.",
+                self.dimension,
+                self.width,
+                self.height,
+                self.depth,
+                self.array_layers,
+                self.mip_levels
+            ))),
+        }
+    }
+
+    fn mip_level_size(&self, mip_level: u32) -> usize {
+        self.mip_slice_size(mip_level) * self.mip_slices(mip_level) as usize
+    }
+
+    fn mip_slice_size(&self, mip_level: u32) -> usize {
+        let (width, height) = self.mip_extent(mip_level);
+
+        self.texture_format.data_size(width, height)
+    }
+}
+
+// Count of mip levels down to 1x1.
+pub(crate) fn full_mip_chain_length(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+impl Texture {
+    // Converts block-compressed and 1–3 channel 8-bit textures into RGBA8, for devices that can't
+    // sample the original format.
+    pub fn to_rgba8(&self) -> Result<Texture, AssetError> {
+        let format = self.metadata.texture_format;
+        let channels = match format.to_unorm() {
+            TextureFormat::R8Unorm => 1,
+            TextureFormat::RG8Unorm => 2,
+            TextureFormat::RGB8Unorm => 3,
+            TextureFormat::RGBA8Unorm => 4,
+            format if format.is_block_compressed() => 0,
+            _ => return Err(AssetError::UnsupportedTextureFormat(format)),
+        };
+        if self.data.len() < self.metadata.data_size() {
+            return Err(AssetError::Serialization(
+                "Texel data is smaller than the texture metadata describes.".to_string(),
+            ));
+        }
+
+        let mut data = Vec::new();
+        for mip_level in 0..self.metadata.mip_levels {
+            let (width, height) = self.metadata.mip_extent(mip_level);
+            let texels = &self.data[self.metadata.mip_level_range(mip_level)];
+
+            match channels {
+                0 => {
+                    for slice in texels.chunks(self.metadata.mip_slice_size(mip_level)) {
+                        data.extend(crate::block_compression::decompress(
+                            format, slice, width, height,
+                        )?);
+                    }
+                }
+                channels => texels.chunks(channels).for_each(|texel| {
+                    let mut rgba = [0, 0, 0, u8::MAX];
+                    rgba[..channels].copy_from_slice(texel);
+                    data.extend(rgba);
+                }),
+            }
+        }
+
+        let rgba8 = match format.is_srgb() {
+            true => TextureFormat::RGBA8Srgb,
+            false => TextureFormat::RGBA8Unorm,
+        };
+        let metadata = TextureAsset {
+            texture_format: rgba8,
+            ..self.metadata
+        };
+
+        Ok(Texture { metadata, data })
+    }
+
+    // Encodes one array layer or depth slice of a mip level as an RGBA8 PNG. Float textures are
+    // clamped to [0, 1] without tone mapping.
+    pub fn to_png(&self, mip_level: u32, slice: u32) -> Result<Vec<u8>, AssetError> {
+        let metadata = &self.metadata;
+        if mip_level >= metadata.mip_levels || slice >= metadata.mip_slices(mip_level) {
+            return Err(AssetError::Serialization(format!(
+                "The texture has no slice {slice} in the mip level {mip_level}."
+            )));
+        }
+        let (width, height) = metadata.mip_extent(mip_level);
+        let slice_range = |metadata: &TextureAsset| {
+            let slice_size = metadata.mip_slice_size(mip_level);
+            let offset = metadata.mip_level_range(mip_level).start + slice as usize * slice_size;
+
+            offset..offset + slice_size
+        };
+        let invalid_size = || {
+            AssetError::Serialization(
+                "Texel data is smaller than the texture metadata describes.".to_string(),
+            )
+        };
+
+        let image = match metadata.texture_format {
+            TextureFormat::RGBA16F | TextureFormat::RGBA32F => {
+                let texels = self
+                    .data
+                    .get(slice_range(metadata))
+                    .ok_or_else(invalid_size)?;
+                let values = match metadata.texture_format {
+                    TextureFormat::RGBA16F => texels
+                        .chunks_exact(2)
+                        .map(|value| half::f16::from_le_bytes([value[0], value[1]]).to_f32())
+                        .collect(),
+                    _ => texels
+                        .chunks_exact(4)
+                        .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                        .collect(),
+                };
+                let image = image::Rgba32FImage::from_raw(width, height, values)
+                    .ok_or_else(invalid_size)?;
+
+                image::DynamicImage::ImageRgba32F(image).to_rgba8()
+            }
+            _ => {
+                let rgba8 = self.to_rgba8()?;
+                let texels = rgba8.data[slice_range(&rgba8.metadata)].to_vec();
+
+                image::RgbaImage::from_raw(width, height, texels).ok_or_else(invalid_size)?
+            }
+        };
+
+        let mut png = Vec::new();
+        image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+
+        Ok(png)
+    }
 }
 
 impl super::Packaging for TextureAsset {
@@ -34,17 +583,141 @@ impl super::Packaging for TextureAsset {
         path: &str,
         raw_data: Vec<u8>,
         compression_mode: super::CompressionMode,
-    ) -> Result<AssetFile, String> {
-        let serialized = ron::to_string(self).map_err(|e| e.to_string())?;
+    ) -> Result<AssetFile, AssetError> {
+        let serialized = ron::to_string(self)?;
 
-        Ok(AssetFile {
-            name: name.to_string(),
-            path: path.to_string(),
-            asset_type: AssetType::Texture,
+        Ok(AssetFile::from_raw_parts(
+            name,
+            path,
+            AssetType::Texture,
             compression_mode,
-            version: super::CURRENT_ASSET_SYSTEM_VERSION.to_string(),
-            metadata: serialized,
+            serialized,
             raw_data,
+        ))
+    }
+}
+
+impl Asset for Texture {
+    fn from_asset_file(asset_file: AssetFile) -> Result<Self, AssetError> {
+        asset_file.expect_asset_type(AssetType::Texture)?;
+        let metadata: TextureAsset = ron::from_str(asset_file.metadata())?;
+        metadata.validate()?;
+
+        Ok(Self {
+            metadata,
+            data: asset_file.into_raw_data(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_format_layouts() {
+        assert_eq!(TextureFormat::RGBA8Srgb.data_size(3, 2), 3 * 2 * 4);
+        assert_eq!(TextureFormat::BC1RgbaUnorm.data_size(5, 4), 2 * 8);
+        assert_eq!(TextureFormat::BC7Srgb.data_size(4, 4), 16);
+        assert_eq!(TextureFormat::ASTC10x8Srgb.block_dimensions(), (10, 8));
+        assert_eq!(TextureFormat::ASTC10x8Srgb.data_size(11, 8), 2 * 16);
+        assert!(TextureFormat::BC5Unorm.is_block_compressed());
+        assert!(!TextureFormat::R11G11B10F.is_block_compressed());
+        assert!(TextureFormat::D32FS8Uint.is_depth() && TextureFormat::D32FS8Uint.has_stencil());
+        assert!(!TextureFormat::D32F.has_stencil());
+    }
+
+    #[test]
+    fn texture_format_srgb_variants() {
+        assert_eq!(
+            TextureFormat::RGBA8Unorm.to_srgb(),
+            TextureFormat::RGBA8Srgb
+        );
+        assert_eq!(TextureFormat::BC7Srgb.to_unorm(), TextureFormat::BC7Unorm);
+        assert_eq!(
+            TextureFormat::ASTC6x6Srgb.to_unorm(),
+            TextureFormat::ASTC6x6Unorm
+        );
+        assert_eq!(
+            TextureFormat::ASTC12x12Unorm.to_srgb(),
+            TextureFormat::ASTC12x12Srgb
+        );
+        assert_eq!(TextureFormat::BC4Unorm.to_srgb(), TextureFormat::BC4Unorm);
+        assert!(TextureFormat::R8Srgb.is_srgb() && !TextureFormat::RGBA16F.is_srgb());
+
+        for format in ALL_TEXTURE_FORMATS {
+            assert_eq!(TextureFormat::from_vk_format(format as i32), Some(format));
+        }
+        assert_eq!(TextureFormat::from_vk_format(1000), None);
+    }
+
+    #[test]
+    fn deserialize_old_texture_format_names() {
+        let texture_asset: TextureAsset =
+            ron::from_str("(texture_format:RGBA8,width:1,height:1)").unwrap();
+
+        assert_eq!(texture_asset.texture_format, TextureFormat::RGBA8Srgb);
+        assert_eq!(texture_asset.color_space, ColorSpace::Srgb);
+        assert_eq!(texture_asset.dimension, TextureDimension::D2);
+        assert_eq!((texture_asset.array_layers, texture_asset.depth), (1, 1));
+    }
+
+    #[test]
+    fn layered_texture_layouts() {
+        let cube = TextureAsset::new_cube(TextureFormat::RGBA8Srgb, 4).with_mip_levels(3);
+        assert!(cube.validate().is_ok());
+        assert_eq!(cube.mip_level_range(1), 6 * 4 * 4 * 4..6 * (16 + 4) * 4);
+        assert_eq!(cube.data_size(), 6 * (16 + 4 + 1) * 4);
+
+        let cube_array = cube.with_layers(TextureDimension::CubeArray, 12);
+        assert!(cube_array.validate().is_ok());
+        assert_eq!(cube_array.data_size(), 2 * cube.data_size());
+        assert!(cube
+            .with_layers(TextureDimension::CubeArray, 8)
+            .validate()
+            .is_err());
+        assert!(TextureAsset::new_cube(TextureFormat::RGBA8Srgb, 4)
+            .with_layers(TextureDimension::Cube, 1)
+            .validate()
+            .is_err());
+
+        // Depth is halved together with the other dimensions.
+        let volume = TextureAsset::new_3d(TextureFormat::R8Unorm, 4, 4, 2).with_mip_levels(3);
+        assert!(volume.validate().is_ok());
+        assert_eq!(
+            (0..3)
+                .map(|level| volume.mip_slices(level))
+                .collect::<Vec<_>>(),
+            [2, 1, 1]
+        );
+        assert_eq!(volume.data_size(), 16 * 2 + 4 + 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn export_png_slices() {
+        // Two layers of 2x1 RG8 texels, the second one is exported.
+        let texture = Texture {
+            metadata: TextureAsset::new(TextureFormat::RG8Unorm, 2, 1)
+                .with_layers(TextureDimension::D2Array, 2),
+            data: vec![0, 0, 0, 0, 10, 20, 30, 40],
+        };
+        let image = image::load_from_memory(&texture.to_png(0, 1).unwrap()).unwrap();
+        assert_eq!(
+            image.to_rgba8().into_raw(),
+            [10, 20, 0, 255, 30, 40, 0, 255]
+        );
+        assert!(texture.to_png(1, 0).is_err());
+        assert!(texture.to_png(0, 2).is_err());
+
+        let hdr = Texture {
+            metadata: TextureAsset::new(TextureFormat::RGBA32F, 1, 1),
+            data: [2.0f32, 1.0, 0.0, 1.0]
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect(),
+        };
+        let image = image::load_from_memory(&hdr.to_png(0, 0).unwrap()).unwrap();
+        assert_eq!(image.to_rgba8().into_raw(), [255, 255, 0, 255]);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{AssetError, ASSET_FILE_EXTENSION};
+use notify::Watcher;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_DEBOUNCE_DURATION: Duration = Duration::from_millis(300);
+
+// Watches asset directories and reports changed asset files once they stop changing,
+// so a file written in several steps is reported only once.
+pub struct AssetWatcher {
+    watcher: notify::RecommendedWatcher,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+    changed: HashMap<PathBuf, Instant>,
+    debounce_duration: Duration,
+}
+
+impl AssetWatcher {
+    pub fn new(debounce_duration: Duration) -> Result<Self, AssetError> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(sender).map_err(watcher_error)?;
+
+        Ok(Self {
+            watcher,
+            events,
+            changed: HashMap::new(),
+            debounce_duration,
+        })
+    }
+
+    pub fn watch<T: AsRef<Path> + ?Sized>(&mut self, directory: &T) -> Result<(), AssetError> {
+        self.watcher
+            .watch(directory.as_ref(), notify::RecursiveMode::Recursive)
+            .map_err(watcher_error)
+    }
+
+    pub fn unwatch<T: AsRef<Path> + ?Sized>(&mut self, directory: &T) -> Result<(), AssetError> {
+        self.watcher
+            .unwatch(directory.as_ref())
+            .map_err(watcher_error)
+    }
+
+    // Returns asset files that were changed and haven't been touched for the debounce duration.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+
+        for event in self.events.try_iter().filter_map(Result::ok) {
+            if !matches!(
+                event.kind,
+                notify::EventKind::Create(_) | notify::EventKind::Modify(_)
+            ) {
+                continue;
+            }
+
+            event
+                .paths
+                .into_iter()
+                .filter(|path| path.extension().is_some_and(|e| e == ASSET_FILE_EXTENSION))
+                .for_each(|path| {
+                    self.changed.insert(path, now);
+                });
+        }
+
+        let mut settled = self
+            .changed
+            .iter()
+            .filter(|(_, &changed_at)| now.duration_since(changed_at) >= self.debounce_duration)
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        settled.iter().for_each(|path| {
+            self.changed.remove(path);
+        });
+        settled.sort();
+
+        settled
+    }
+}
+
+fn watcher_error(e: notify::Error) -> AssetError {
+    match e.kind {
+        notify::ErrorKind::Io(e) => AssetError::Io(e),
+        kind => AssetError::Io(std::io::Error::other(format!("{kind:?}"))),
+    }
+}
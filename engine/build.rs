@@ -0,0 +1,69 @@
+// Compiles every shader in `shaders` to SPIR-V in `$OUT_DIR/shaders`. A variant is written for
+// each permutation of the keywords of a shader, named after the shader followed by its keywords,
+// e.g. `lit.frag.spv` and `lit.frag.ALPHA_TEST.spv`. Files without a stage extension are only
+// included by others.
+use asset_system::ShaderCompiler;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let shader_directory = Path::new("shaders");
+    let output_directory = PathBuf::from(std::env::var_os("OUT_DIR").unwrap()).join("shaders");
+    // Catches shaders that are added or removed.
+    println!("cargo:rerun-if-changed={}", shader_directory.display());
+
+    let mut compiler = ShaderCompiler::new();
+    compiler.include_directories.push(shader_directory.into());
+
+    let result = std::fs::create_dir_all(&output_directory)
+        .and_then(|()| std::fs::read_dir(shader_directory))
+        .map_err(|e| e.to_string())
+        .and_then(|entries| {
+            let mut paths = entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            paths.sort();
+
+            paths
+                .iter()
+                .filter(|path| ShaderCompiler::stage(path).is_some())
+                .try_for_each(|path| compile(&compiler, path, &output_directory))
+        });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn compile(compiler: &ShaderCompiler, path: &Path, output_directory: &Path) -> Result<(), String> {
+    let stage = ShaderCompiler::stage(path).unwrap();
+    let source = compiler.preprocess(path).map_err(|e| e.to_string())?;
+    for file in source.files() {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+
+    for keywords in source.permutations() {
+        let code = compiler
+            .compile(&source, stage, &keywords)
+            .map_err(|e| e.to_string())?;
+
+        let mut file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        keywords
+            .iter()
+            .for_each(|keyword| file_name += &format!(".{keyword}"));
+        file_name += ".spv";
+
+        let bytes = code
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+        std::fs::write(output_directory.join(file_name), bytes).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+#version 450
+
+// Metallic-roughness shading of the PBR fields of material assets. The renderer has no lights
+// yet, so surfaces are lit by a fixed directional light and seen along Z.
+
+layout(location = 0) in vec3 fragNormal;
+layout(location = 1) in vec4 fragTangent;
+layout(location = 2) in vec2 fragUv;
+
+layout(location = 0) out vec4 outColor;
+
+layout(set = 0, binding = 0) uniform Material {
+    vec4 base_color_factor;
+    vec3 emissive_factor;
+    float metallic_factor;
+    float roughness_factor;
+    float normal_scale;
+    float occlusion_strength;
+    // Zero unless the alpha mode is a mask.
+    float alpha_cutoff;
+} material;
+layout(set = 0, binding = 1) uniform sampler material_sampler;
+layout(set = 0, binding = 2) uniform texture2D base_color_texture;
+layout(set = 0, binding = 3) uniform texture2D metallic_roughness_texture;
+layout(set = 0, binding = 4) uniform texture2D normal_texture;
+layout(set = 0, binding = 5) uniform texture2D occlusion_texture;
+layout(set = 0, binding = 6) uniform texture2D emissive_texture;
+
+const float PI = 3.14159265;
+const vec3 LIGHT_DIRECTION = vec3(0.4, 0.8, 0.45);
+const float LIGHT_INTENSITY = 3.0;
+const float AMBIENT_INTENSITY = 0.05;
+const vec3 VIEW_DIRECTION = vec3(0.0, 0.0, 1.0);
+
+void main() {
+    vec4 base_color = material.base_color_factor
+        * texture(sampler2D(base_color_texture, material_sampler), fragUv);
+    if (base_color.a < material.alpha_cutoff) {
+        discard;
+    }
+
+    vec4 metallic_roughness =
+        texture(sampler2D(metallic_roughness_texture, material_sampler), fragUv);
+    float metallic = clamp(material.metallic_factor * metallic_roughness.b, 0.0, 1.0);
+    float roughness = clamp(material.roughness_factor * metallic_roughness.g, 0.04, 1.0);
+
+    // Meshes without tangents keep their vertex normals.
+    vec3 normal = normalize(fragNormal);
+    if (dot(fragTangent.xyz, fragTangent.xyz) > 0.0) {
+        vec3 tangent = normalize(fragTangent.xyz - normal * dot(normal, fragTangent.xyz));
+        vec3 bitangent = cross(normal, tangent) * fragTangent.w;
+        vec3 texel = texture(sampler2D(normal_texture, material_sampler), fragUv).xyz * 2.0 - 1.0;
+        texel.xy *= material.normal_scale;
+        normal = normalize(mat3(tangent, bitangent, normal) * texel);
+    }
+
+    vec3 light = normalize(LIGHT_DIRECTION);
+    vec3 halfway = normalize(light + VIEW_DIRECTION);
+    float n_dot_l = max(dot(normal, light), 0.0);
+    float n_dot_v = max(dot(normal, VIEW_DIRECTION), 0.0001);
+    float n_dot_h = max(dot(normal, halfway), 0.0);
+    float v_dot_h = max(dot(VIEW_DIRECTION, halfway), 0.0);
+
+    // GGX distribution, Schlick-GGX visibility and Schlick's Fresnel.
+    float alpha = roughness * roughness;
+    float alpha2 = alpha * alpha;
+    float denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    float distribution = alpha2 / (PI * denominator * denominator);
+    float k = alpha / 2.0;
+    float visibility = 0.25 / ((n_dot_l * (1.0 - k) + k) * (n_dot_v * (1.0 - k) + k));
+    vec3 f0 = mix(vec3(0.04), base_color.rgb, metallic);
+    vec3 fresnel = f0 + (1.0 - f0) * pow(1.0 - v_dot_h, 5.0);
+
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * base_color.rgb / PI;
+    vec3 specular = fresnel * distribution * visibility;
+    vec3 color = (diffuse + specular) * n_dot_l * LIGHT_INTENSITY;
+
+    float occlusion = texture(sampler2D(occlusion_texture, material_sampler), fragUv).r;
+    color += AMBIENT_INTENSITY * base_color.rgb * mix(1.0, occlusion, material.occlusion_strength);
+    color += material.emissive_factor
+        * texture(sampler2D(emissive_texture, material_sampler), fragUv).rgb;
+
+    outColor = vec4(color, base_color.a);
+}
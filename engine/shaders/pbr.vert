@@ -0,0 +1,22 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec4 tangent;
+layout(location = 3) in vec2 uv;
+
+layout(location = 0) out vec3 fragNormal;
+layout(location = 1) out vec4 fragTangent;
+layout(location = 2) out vec2 fragUv;
+
+// Pushed by the renderer for each draw.
+layout(push_constant) uniform Transform {
+    mat4 clip_from_object;
+} transform;
+
+void main() {
+    gl_Position = transform.clip_from_object * vec4(position, 1.0);
+    fragNormal = normal;
+    fragTangent = tangent;
+    fragUv = uv;
+}
@@ -7,8 +7,10 @@ use smallvec::SmallVec;
 use std::ffi::{CStr, CString};
 use tracing::error;
 
-const TRIANGLE_VERT: &[u8] = include_bytes!("../shaders/triangle.vert.spv");
-const TRIANGLE_FRAG: &[u8] = include_bytes!("../shaders/triangle.frag.spv");
+pub(crate) const TRIANGLE_VERT: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/shaders/triangle.vert.spv"));
+pub(crate) const TRIANGLE_FRAG: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/shaders/triangle.frag.spv"));
 
 pub struct Context {
     pub render_semaphore: vk::Semaphore,
@@ -28,11 +30,14 @@ pub struct Context {
     pub command_pool: vk::CommandPool,
 
     pub graphics_queue: vk::Queue,
+    pub queue_family_index: u32,
     pub device: erupt::DeviceLoader,
-    _physical_device: vk::PhysicalDevice,
+    physical_device: vk::PhysicalDevice,
     _physical_device_properties: vk::PhysicalDeviceProperties,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    enabled_features: vk::PhysicalDeviceFeatures,
 
-    _surface_format: vk::SurfaceFormatKHR,
+    surface_format: vk::SurfaceFormatKHR,
     surface: vk::SurfaceKHR,
 
     #[cfg(all(
@@ -45,6 +50,7 @@ pub struct Context {
 }
 
 impl Context {
+    #[allow(clippy::assertions_on_constants)]
     pub fn new(
         window: &impl HasRawWindowHandle,
         width: u32,
@@ -288,6 +294,17 @@ impl Context {
             )
             .expect("Error: Failed to find a suitable device.");
 
+        // Block-compressed textures are uploaded as is when the device supports them.
+        let supported_features = unsafe { instance.get_physical_device_features(physical_device) };
+        let enabled_features = vk::PhysicalDeviceFeatures {
+            texture_compression_bc: supported_features.texture_compression_bc,
+            texture_compression_astc_ldr: supported_features.texture_compression_astc_ldr,
+            image_cube_array: supported_features.image_cube_array,
+            ..Default::default()
+        };
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+
         #[cfg(all(
             any(feature = "no_log", feature = "log"),
             not(all(feature = "no_log", feature = "log"))
@@ -313,6 +330,7 @@ impl Context {
             not(all(feature = "no_log", feature = "log"))
         ))]
         let mut device_features = vk::PhysicalDeviceFeatures2KHRBuilder::new()
+            .features(enabled_features)
             .extend_from(&mut dynamic_rendering)
             .extend_from(&mut sync_2);
 
@@ -539,10 +557,13 @@ impl Context {
             images: swapchain_images,
             swapchain,
             graphics_queue,
+            queue_family_index,
             device,
-            _physical_device: physical_device,
+            physical_device,
             _physical_device_properties: physical_device_properties,
-            _surface_format: surface_format,
+            memory_properties,
+            enabled_features,
+            surface_format,
             surface,
             #[cfg(all(
                 any(feature = "no_log", feature = "log"),
@@ -552,6 +573,51 @@ impl Context {
         })
     }
 
+    // Whether images of the format can be sampled with optimal tiling.
+    pub fn supports_sampled_format(&self, format: vk::Format) -> bool {
+        let texture_format = super::texture::texture_format(format);
+        let is_enabled = match texture_format {
+            Some(texture_format) if texture_format.is_astc() => {
+                self.enabled_features.texture_compression_astc_ldr
+            }
+            Some(texture_format) if texture_format.is_block_compressed() => {
+                self.enabled_features.texture_compression_bc
+            }
+            _ => vk::TRUE,
+        };
+
+        let format_properties = unsafe {
+            self.instance
+                .get_physical_device_format_properties(self.physical_device, format)
+        };
+
+        is_enabled == vk::TRUE
+            && format_properties
+                .optimal_tiling_features
+                .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE)
+    }
+
+    pub fn surface_format(&self) -> vk::Format {
+        self.surface_format.format
+    }
+
+    pub fn supports_cube_arrays(&self) -> bool {
+        self.enabled_features.image_cube_array == vk::TRUE
+    }
+
+    pub fn find_memory_type(
+        &self,
+        memory_type_bits: u32,
+        properties: vk::MemoryPropertyFlags,
+    ) -> Option<u32> {
+        (0..self.memory_properties.memory_type_count).find(|&i| {
+            memory_type_bits & (1 << i) != 0
+                && self.memory_properties.memory_types[i as usize]
+                    .property_flags
+                    .contains(properties)
+        })
+    }
+
     // TODO: Unify function for the possibility to automate the process of creation and binding ShaderModules.
     fn create_shader_modules(
         device: &erupt::DeviceLoader,
@@ -581,7 +647,7 @@ impl Context {
         shader_module: vk::ShaderModule,
         entry_point: &CStr,
         stage: vk::ShaderStageFlagBits,
-    ) -> vk::PipelineShaderStageCreateInfoBuilder {
+    ) -> vk::PipelineShaderStageCreateInfoBuilder<'_> {
         vk::PipelineShaderStageCreateInfoBuilder::new()
             .module(shader_module)
             .name(entry_point)
@@ -1,15 +1,29 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 #![deny(unstable_features)]
 
+use asset_system::{
+    AssetError, AssetEvent, AssetGuid, AssetId, AssetServer, LoadState, MaterialAsset, Shader,
+    Texture,
+};
 use erupt::vk;
 use raw_window_handle::HasRawWindowHandle;
+use std::collections::HashMap;
 
 mod context;
+mod material;
 mod renderer;
 mod scene;
+mod texture;
 mod utils;
 
+pub use material::{GpuMaterial, MaterialSystem};
+pub use texture::{image_view_type, texture_format, vk_format, GpuTexture};
+
 pub struct Engine {
+    asset_server: AssetServer,
+    textures: HashMap<AssetId, texture::GpuTexture>,
+    materials: HashMap<AssetId, material::GpuMaterial>,
+    material_system: material::MaterialSystem,
     renderer: renderer::Renderer,
     context: context::Context,
 
@@ -27,8 +41,14 @@ impl Engine {
         let logging_guard = utils::logging::init_logging();
         let context = context::Context::new(window, width, height)?;
         let renderer = renderer::Renderer::new();
+        let material_system =
+            material::MaterialSystem::new(&context, context.surface_format(), None)?;
 
         Ok(Self {
+            asset_server: AssetServer::new(),
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            material_system,
             context,
             renderer,
             #[cfg(all(not(feature = "no_log"), feature = "log"))]
@@ -36,10 +56,187 @@ impl Engine {
         })
     }
 
+    pub fn asset_server(&mut self) -> &mut AssetServer {
+        &mut self.asset_server
+    }
+
+    // Assets loaded through the engine are reloaded when their files in the directory change.
+    pub fn watch_assets<T: AsRef<std::path::Path> + ?Sized>(
+        &mut self,
+        directory: &T,
+    ) -> Result<(), AssetError> {
+        self.asset_server.watch_for_changes(directory)
+    }
+
     #[inline(always)]
     pub fn draw_call(&mut self) -> Result<(), vk::Result> {
+        // Assets reloaded in the background are swapped in before the frame is recorded.
+        for event in self.asset_server.update() {
+            match event {
+                AssetEvent::Modified(id) => {
+                    tracing::info!("Asset {id:?} was reloaded.");
+                    self.upload_asset(id)?;
+                }
+                AssetEvent::Failed(id) => tracing::warn!(
+                    "Failed to load an asset {id:?}: {:?}",
+                    self.asset_server.load_error(id)
+                ),
+                AssetEvent::Loaded(id) => self.upload_asset(id)?,
+            }
+        }
+
+        // GPU copies of unloaded textures and materials are released.
+        let asset_server = &self.asset_server;
+        let unloaded = self
+            .textures
+            .keys()
+            .chain(self.materials.keys())
+            .copied()
+            .filter(|&id| asset_server.load_state(id) != LoadState::Loaded)
+            .collect::<Vec<_>>();
+        if !unloaded.is_empty() {
+            unsafe { self.context.device.device_wait_idle().result()? };
+            for id in unloaded {
+                if let Some(texture) = self.textures.remove(&id) {
+                    texture.destroy(&self.context.device);
+                }
+                if let Some(material) = self.materials.remove(&id) {
+                    material.destroy(&self.context.device);
+                }
+            }
+        }
+
         self.renderer.draw(&self.context)?;
 
         Ok(())
     }
+
+    pub fn texture(&self, id: AssetId) -> Option<&texture::GpuTexture> {
+        self.textures.get(&id)
+    }
+
+    // `None` until the material and all its textures are loaded.
+    pub fn material(&self, id: AssetId) -> Option<&material::GpuMaterial> {
+        self.materials.get(&id)
+    }
+
+    pub fn material_system(&mut self) -> &mut material::MaterialSystem {
+        &mut self.material_system
+    }
+
+    fn upload_asset(&mut self, id: AssetId) -> Result<(), vk::Result> {
+        if self.upload_texture(id)? || self.upload_shader(id)? {
+            // Materials using the texture or the shader are bound to its previous version, or
+            // waited for it.
+            if let Some(guid) = self.asset_server.guid(id) {
+                for dependent in self.asset_server.dependents(guid) {
+                    self.upload_material(dependent)?;
+                }
+            }
+        }
+
+        self.upload_material(id)
+    }
+
+    // Replaces the GPU copy of a texture asset, other asset types are ignored. Returns whether
+    // the texture was uploaded.
+    fn upload_texture(&mut self, id: AssetId) -> Result<bool, vk::Result> {
+        let Some(texture) = self.asset_server.get_by_id::<Texture>(id) else {
+            return Ok(false);
+        };
+
+        match texture::GpuTexture::upload(&self.context, &texture) {
+            Ok(gpu_texture) => {
+                if let Some(old_texture) = self.textures.insert(id, gpu_texture) {
+                    unsafe { self.context.device.device_wait_idle().result()? };
+                    old_texture.destroy(&self.context.device);
+                }
+
+                Ok(true)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to upload a texture {id:?}: {e:?}");
+                Ok(false)
+            }
+        }
+    }
+
+    // Replaces the pipeline layout of a shader asset, other asset types are ignored. Returns
+    // whether the shader was uploaded.
+    fn upload_shader(&mut self, id: AssetId) -> Result<bool, vk::Result> {
+        let (Some(shader), Some(guid)) = (
+            self.asset_server.get_by_id::<Shader>(id),
+            self.asset_server.guid(id),
+        ) else {
+            return Ok(false);
+        };
+
+        // The pipelines of the previous version are destroyed with it.
+        if self.material_system.has_shader(guid) {
+            for dependent in self.asset_server.dependents(guid) {
+                if let Some(old_material) = self.materials.remove(&dependent) {
+                    unsafe { self.context.device.device_wait_idle().result()? };
+                    old_material.destroy(&self.context.device);
+                }
+            }
+        }
+
+        match self
+            .material_system
+            .add_shader(&self.context, guid, &shader)
+        {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                tracing::warn!("Failed to upload a shader {id:?}: {e:?}");
+                Ok(false)
+            }
+        }
+    }
+
+    // Creates the pipeline and descriptor set of a material asset once its textures are on the
+    // GPU, other asset types are ignored.
+    fn upload_material(&mut self, id: AssetId) -> Result<(), vk::Result> {
+        let Some(material) = self.asset_server.get_by_id::<MaterialAsset>(id) else {
+            return Ok(());
+        };
+
+        let asset_server = &self.asset_server;
+        let textures = self
+            .textures
+            .iter()
+            .filter_map(|(&id, texture)| asset_server.guid(id).map(|guid| (guid, texture)))
+            .collect::<HashMap<AssetGuid, _>>();
+
+        let gpu_material =
+            match self
+                .material_system
+                .create_material(&self.context, &material, &textures)
+            {
+                Ok(Some(gpu_material)) => gpu_material,
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!("Failed to create a material {id:?}: {e:?}");
+                    return Ok(());
+                }
+            };
+        if let Some(old_material) = self.materials.insert(id, gpu_material) {
+            unsafe { self.context.device.device_wait_idle().result()? };
+            old_material.destroy(&self.context.device);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        unsafe { self.context.device.device_wait_idle().unwrap() };
+        self.materials
+            .drain()
+            .for_each(|(_, material)| material.destroy(&self.context.device));
+        self.material_system.destroy(&self.context.device);
+        self.textures
+            .drain()
+            .for_each(|(_, texture)| texture.destroy(&self.context.device));
+    }
 }
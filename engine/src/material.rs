@@ -0,0 +1,874 @@
+use super::context::Context;
+use super::texture::GpuTexture;
+use asset_system::{
+    AlphaMode, AssetGuid, BlendMode, CompareOp, CullMode, DescriptorBinding, DescriptorType,
+    MaterialAsset, MaterialParameter, RenderState, Shader, ShaderStage, Texture, TextureAsset,
+    TextureFormat,
+};
+use erupt::{vk, ExtendableFrom};
+use std::collections::HashMap;
+use std::ffi::CString;
+
+const PBR_VERT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/shaders/pbr.vert.spv"));
+const PBR_FRAG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/shaders/pbr.frag.spv"));
+
+// Formats and offsets of the fields of `asset_system::Vertex` by location: position, normal,
+// tangent and UV.
+const VERTEX_ATTRIBUTES: [(vk::Format, u32); 4] = [
+    (vk::Format::R32G32B32_SFLOAT, 0),
+    (vk::Format::R32G32B32_SFLOAT, 12),
+    (vk::Format::R32G32B32A32_SFLOAT, 24),
+    (vk::Format::R32G32_SFLOAT, 40),
+];
+
+// Pipelines are shared by materials with the same shader and render state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    shader: Option<AssetGuid>,
+    render_state: RenderState,
+}
+
+// The pipeline and the resources of a material asset. The descriptor set is the set 0 of the
+// shader: its uniform buffer holds the parameters at the offsets of the block members of the
+// same names, its images the textures of the slots of the same names and its samplers the
+// sampler of the material system. Elements of image arrays take the slots `name[0]`, `name[1]`
+// and so on. Other sets are left to the renderer.
+#[derive(Debug)]
+pub struct GpuMaterial {
+    pub pipeline: vk::Pipeline,
+    pub pipeline_layout: vk::PipelineLayout,
+    // Null if the shader has no bindings in the set 0.
+    pub descriptor_set: vk::DescriptorSet,
+    descriptor_pool: vk::DescriptorPool,
+    parameter_buffer: vk::Buffer,
+    parameter_memory: vk::DeviceMemory,
+}
+
+impl GpuMaterial {
+    // Pipelines and layouts are owned by the material system.
+    pub fn destroy(&self, device: &erupt::DeviceLoader) {
+        unsafe {
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_buffer(self.parameter_buffer, None);
+            device.free_memory(self.parameter_memory, None);
+        }
+    }
+}
+
+// Shader modules and the pipeline layout created from the reflection of a shader asset.
+#[derive(Debug, Default)]
+struct PipelineShader {
+    modules: Vec<vk::ShaderModule>,
+    stages: Vec<(vk::ShaderStageFlagBits, vk::ShaderModule, CString)>,
+    vertex_locations: Vec<u32>,
+    set_layouts: Vec<vk::DescriptorSetLayout>,
+    pipeline_layout: vk::PipelineLayout,
+    material_bindings: Vec<DescriptorBinding>,
+}
+
+impl PipelineShader {
+    fn new(device: &erupt::DeviceLoader, shader: &Shader) -> Result<Self, vk::Result> {
+        let mut pipeline_shader = Self::default();
+        match pipeline_shader.create(device, shader) {
+            Ok(()) => Ok(pipeline_shader),
+            Err(e) => {
+                pipeline_shader.destroy(device);
+                Err(e)
+            }
+        }
+    }
+
+    fn create(&mut self, device: &erupt::DeviceLoader, shader: &Shader) -> Result<(), vk::Result> {
+        let metadata = &shader.metadata;
+        let unsupported = |reason: String| {
+            tracing::warn!("Unsupported shader: {reason}");
+            vk::Result::ERROR_INITIALIZATION_FAILED
+        };
+
+        for module in &shader.modules {
+            let shader_module_info = vk::ShaderModuleCreateInfoBuilder::new().code(module);
+            let shader_module = unsafe {
+                device
+                    .create_shader_module(&shader_module_info, None)
+                    .result()?
+            };
+            self.modules.push(shader_module);
+        }
+
+        for entry_point in &metadata.entry_points {
+            let Some(stage) = graphics_stage(entry_point.stage) else {
+                continue;
+            };
+            let name = CString::new(entry_point.name.as_str())
+                .map_err(|_| unsupported(format!("the entry point {}", entry_point.name)))?;
+            let module = self
+                .modules
+                .get(entry_point.module as usize)
+                .ok_or_else(|| unsupported(format!("no module of the {stage:?} stage")))?;
+            self.stages.push((stage, *module, name));
+        }
+        for required in [ShaderStage::Vertex, ShaderStage::Fragment] {
+            if shader.entry_point(required).is_none() {
+                return Err(unsupported(format!("no {required:?} stage")));
+            }
+        }
+
+        self.vertex_locations = metadata
+            .vertex_inputs
+            .iter()
+            .map(|input| input.location)
+            .collect();
+        if let Some(input) = metadata
+            .vertex_inputs
+            .iter()
+            .find(|input| input.location as usize >= VERTEX_ATTRIBUTES.len())
+        {
+            return Err(unsupported(format!(
+                "meshes have no vertex attribute at the location {} of {}",
+                input.location, input.name
+            )));
+        }
+
+        let set_count = metadata
+            .descriptor_bindings
+            .iter()
+            .map(|binding| binding.set + 1)
+            .max()
+            .unwrap_or(0);
+        for set in 0..set_count {
+            let bindings = metadata
+                .descriptor_bindings
+                .iter()
+                .filter(|binding| binding.set == set)
+                .map(|binding| match binding.count {
+                    0 => Err(unsupported(format!("the runtime array {}", binding.name))),
+                    count => Ok(vk::DescriptorSetLayoutBindingBuilder::new()
+                        .binding(binding.binding)
+                        .descriptor_type(descriptor_type(binding.descriptor_type))
+                        .descriptor_count(count)
+                        .stage_flags(stage_flags(&binding.stages))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let descriptor_set_layout_info =
+                vk::DescriptorSetLayoutCreateInfoBuilder::new().bindings(&bindings);
+            let descriptor_set_layout = unsafe {
+                device
+                    .create_descriptor_set_layout(&descriptor_set_layout_info, None)
+                    .result()?
+            };
+            self.set_layouts.push(descriptor_set_layout);
+        }
+
+        let push_constant_ranges = metadata
+            .push_constant_ranges
+            .iter()
+            .map(|range| {
+                vk::PushConstantRangeBuilder::new()
+                    .stage_flags(stage_flags(&range.stages))
+                    .offset(range.offset)
+                    .size(range.size)
+            })
+            .collect::<Vec<_>>();
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfoBuilder::new()
+            .set_layouts(&self.set_layouts)
+            .push_constant_ranges(&push_constant_ranges);
+        self.pipeline_layout = unsafe {
+            device
+                .create_pipeline_layout(&pipeline_layout_info, None)
+                .result()?
+        };
+
+        self.material_bindings = metadata
+            .descriptor_bindings
+            .iter()
+            .filter(|binding| binding.set == 0)
+            .cloned()
+            .collect();
+        // Materials only fill a parameter block, images and samplers.
+        let mut has_parameter_block = false;
+        for binding in &self.material_bindings {
+            match binding.descriptor_type {
+                DescriptorType::UniformBuffer if !has_parameter_block => has_parameter_block = true,
+                DescriptorType::Sampler
+                | DescriptorType::SampledImage
+                | DescriptorType::CombinedImageSampler => (),
+                descriptor_type => {
+                    return Err(unsupported(format!(
+                        "materials can't fill the {descriptor_type:?} {} of the set 0",
+                        binding.name
+                    )))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // The uniform buffer of the set 0.
+    fn parameter_block(&self) -> Option<&DescriptorBinding> {
+        self.material_bindings
+            .iter()
+            .find(|binding| binding.descriptor_type == DescriptorType::UniformBuffer)
+    }
+
+    // The texture slots of the images of the set 0.
+    fn texture_slots(&self) -> impl Iterator<Item = String> + '_ {
+        self.material_bindings
+            .iter()
+            .filter(|binding| has_image(binding.descriptor_type))
+            .flat_map(|binding| (0..binding.count).map(move |index| slot_name(binding, index)))
+    }
+
+    fn destroy(&self, device: &erupt::DeviceLoader) {
+        unsafe {
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            self.set_layouts
+                .iter()
+                .for_each(|&layout| device.destroy_descriptor_set_layout(layout, None));
+            self.modules
+                .iter()
+                .for_each(|&module| device.destroy_shader_module(module, None));
+        }
+    }
+}
+
+// Turns material assets into pipelines and descriptor sets. Shader assets are registered by
+// their GUIDs, layouts follow the reflection of the shaders. Materials without a shader use the
+// built-in PBR shader, which takes the PBR fields of the material. PBR textures a material
+// doesn't reference are replaced by white ones, or a flat normal map.
+pub struct MaterialSystem {
+    color_format: vk::Format,
+    // The renderer has no depth buffer yet, so depth state only applies once a format is given.
+    depth_format: vk::Format,
+    sampler: vk::Sampler,
+    white_texture: GpuTexture,
+    flat_normal_texture: GpuTexture,
+    default_shader: PipelineShader,
+    shaders: HashMap<AssetGuid, PipelineShader>,
+    pipelines: HashMap<PipelineKey, vk::Pipeline>,
+}
+
+impl MaterialSystem {
+    pub fn new(
+        context: &Context,
+        color_format: vk::Format,
+        depth_format: Option<vk::Format>,
+    ) -> Result<Self, vk::Result> {
+        let device = &context.device;
+
+        let default_shader = Shader::from_spirv_bytes(&[PBR_VERT, PBR_FRAG]).map_err(|e| {
+            tracing::error!("Failed to reflect the built-in shader: {e}");
+            vk::Result::ERROR_INITIALIZATION_FAILED
+        })?;
+        let default_shader = PipelineShader::new(device, &default_shader)?;
+
+        let texture = |texel: [u8; 4]| {
+            GpuTexture::upload(
+                context,
+                &Texture {
+                    metadata: TextureAsset::new(TextureFormat::RGBA8Unorm, 1, 1),
+                    data: texel.to_vec(),
+                },
+            )
+        };
+        let white_texture = match texture([255; 4]) {
+            Ok(texture) => texture,
+            Err(e) => {
+                default_shader.destroy(device);
+                return Err(e);
+            }
+        };
+        let flat_normal_texture = match texture([128, 128, 255, 255]) {
+            Ok(texture) => texture,
+            Err(e) => {
+                white_texture.destroy(device);
+                default_shader.destroy(device);
+                return Err(e);
+            }
+        };
+
+        let sampler_info = vk::SamplerCreateInfoBuilder::new()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::REPEAT)
+            .address_mode_v(vk::SamplerAddressMode::REPEAT)
+            .address_mode_w(vk::SamplerAddressMode::REPEAT)
+            .max_lod(vk::LOD_CLAMP_NONE);
+        let sampler = match unsafe { device.create_sampler(&sampler_info, None) }.result() {
+            Ok(sampler) => sampler,
+            Err(e) => {
+                flat_normal_texture.destroy(device);
+                white_texture.destroy(device);
+                default_shader.destroy(device);
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            color_format,
+            depth_format: depth_format.unwrap_or(vk::Format::UNDEFINED),
+            sampler,
+            white_texture,
+            flat_normal_texture,
+            default_shader,
+            shaders: HashMap::new(),
+            pipelines: HashMap::new(),
+        })
+    }
+
+    // Replaces the modules and the layout of a shader asset. Pipelines built from the previous
+    // ones are destroyed, so materials using them have to be created again.
+    pub fn add_shader(
+        &mut self,
+        context: &Context,
+        guid: AssetGuid,
+        shader: &Shader,
+    ) -> Result<(), vk::Result> {
+        let device = &context.device;
+        let pipeline_shader = PipelineShader::new(device, shader)?;
+
+        if let Some(old_shader) = self.shaders.insert(guid, pipeline_shader) {
+            unsafe { device.device_wait_idle().result()? };
+            self.pipelines.retain(|key, &mut pipeline| {
+                let is_stale = key.shader == Some(guid);
+                if is_stale {
+                    unsafe { device.destroy_pipeline(pipeline, None) };
+                }
+
+                !is_stale
+            });
+            old_shader.destroy(device);
+        }
+
+        Ok(())
+    }
+
+    pub fn has_shader(&self, guid: AssetGuid) -> bool {
+        self.shaders.contains_key(&guid)
+    }
+
+    // `None` until the shader and every texture of the material are on the GPU, or if the
+    // parameters or texture slots don't match the shader.
+    pub fn create_material(
+        &mut self,
+        context: &Context,
+        material: &MaterialAsset,
+        textures: &HashMap<AssetGuid, &GpuTexture>,
+    ) -> Result<Option<GpuMaterial>, vk::Result> {
+        let pbr_material;
+        let (shader, material) = match material.shader {
+            Some(guid) => match self.shaders.get(&guid) {
+                Some(shader) => (shader, material),
+                None => return Ok(None),
+            },
+            None => {
+                pbr_material = pbr_inputs(material);
+                (&self.default_shader, &pbr_material)
+            }
+        };
+        let slots = shader.texture_slots().collect::<Vec<_>>();
+        if let Some(slot) = material
+            .texture_slots
+            .keys()
+            .find(|&slot| !slots.contains(slot))
+        {
+            tracing::warn!("The shader of a material has no texture {slot}.");
+            return Ok(None);
+        }
+        let mut image_views = HashMap::new();
+        for slot in slots {
+            let image_view = match material.texture_slots.get(&slot) {
+                Some(guid) => match textures.get(guid) {
+                    Some(texture) => texture.image_view,
+                    None => return Ok(None),
+                },
+                None if material.shader.is_some() => {
+                    tracing::warn!("A material has no texture for the slot {slot} of its shader.");
+                    return Ok(None);
+                }
+                None if slot == "normal_texture" => self.flat_normal_texture.image_view,
+                None => self.white_texture.image_view,
+            };
+            image_views.insert(slot, image_view);
+        }
+
+        let members = shader
+            .parameter_block()
+            .map_or(&[][..], |binding| binding.members.as_slice());
+        let parameter_block = match material.parameter_block(members) {
+            Ok(parameter_block) => parameter_block,
+            Err(e) => {
+                tracing::warn!("A material doesn't match its shader. {e}");
+                return Ok(None);
+            }
+        };
+
+        let key = PipelineKey {
+            shader: material.shader,
+            render_state: material.render_state,
+        };
+        let pipeline = self.pipeline(context, key)?;
+        let shader = match material.shader {
+            Some(guid) => &self.shaders[&guid],
+            None => &self.default_shader,
+        };
+
+        let device = &context.device;
+        // Uniform buffers can't be empty.
+        let parameter_data = match parameter_block.is_empty() {
+            true => vec![0; 16],
+            false => parameter_block,
+        };
+        let (parameter_buffer, parameter_memory) =
+            Self::create_parameter_buffer(context, &parameter_data)?;
+        let mut material = GpuMaterial {
+            pipeline,
+            pipeline_layout: shader.pipeline_layout,
+            descriptor_set: vk::DescriptorSet::null(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            parameter_buffer,
+            parameter_memory,
+        };
+        if shader.material_bindings.is_empty() {
+            return Ok(Some(material));
+        }
+
+        match self.allocate_descriptor_set(device, shader, &mut material, &image_views) {
+            Ok(()) => Ok(Some(material)),
+            Err(e) => {
+                material.destroy(device);
+                Err(e)
+            }
+        }
+    }
+
+    pub fn destroy(&mut self, device: &erupt::DeviceLoader) {
+        unsafe {
+            self.pipelines
+                .drain()
+                .for_each(|(_, pipeline)| device.destroy_pipeline(pipeline, None));
+            device.destroy_sampler(self.sampler, None);
+        }
+        self.white_texture.destroy(device);
+        self.flat_normal_texture.destroy(device);
+        self.shaders
+            .drain()
+            .for_each(|(_, shader)| shader.destroy(device));
+        self.default_shader.destroy(device);
+    }
+
+    fn allocate_descriptor_set(
+        &self,
+        device: &erupt::DeviceLoader,
+        shader: &PipelineShader,
+        material: &mut GpuMaterial,
+        image_views: &HashMap<String, vk::ImageView>,
+    ) -> Result<(), vk::Result> {
+        let mut pool_sizes = HashMap::<vk::DescriptorType, u32>::new();
+        for binding in &shader.material_bindings {
+            *pool_sizes
+                .entry(descriptor_type(binding.descriptor_type))
+                .or_default() += binding.count;
+        }
+        let pool_sizes = pool_sizes
+            .into_iter()
+            .map(|(descriptor_type, count)| {
+                vk::DescriptorPoolSizeBuilder::new()
+                    ._type(descriptor_type)
+                    .descriptor_count(count)
+            })
+            .collect::<Vec<_>>();
+        let pool_info = vk::DescriptorPoolCreateInfoBuilder::new()
+            .max_sets(1)
+            .pool_sizes(&pool_sizes);
+        material.descriptor_pool =
+            unsafe { device.create_descriptor_pool(&pool_info, None).result()? };
+
+        let set_layouts = [shader.set_layouts[0]];
+        let allocate_info = vk::DescriptorSetAllocateInfoBuilder::new()
+            .descriptor_pool(material.descriptor_pool)
+            .set_layouts(&set_layouts);
+        let descriptor_set =
+            unsafe { device.allocate_descriptor_sets(&allocate_info).result()? }[0];
+        material.descriptor_set = descriptor_set;
+
+        let buffer_infos = [vk::DescriptorBufferInfoBuilder::new()
+            .buffer(material.parameter_buffer)
+            .range(vk::WHOLE_SIZE)];
+        let image_infos = shader
+            .material_bindings
+            .iter()
+            .filter(|binding| binding.descriptor_type != DescriptorType::UniformBuffer)
+            .map(|binding| {
+                let image_infos = (0..binding.count)
+                    .map(|index| {
+                        let image_info = vk::DescriptorImageInfoBuilder::new();
+                        let image_info = match binding.descriptor_type {
+                            DescriptorType::Sampler | DescriptorType::CombinedImageSampler => {
+                                image_info.sampler(self.sampler)
+                            }
+                            _ => image_info,
+                        };
+                        match has_image(binding.descriptor_type) {
+                            true => image_info
+                                .image_view(image_views[&slot_name(binding, index)])
+                                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+                            false => image_info,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                (binding, image_infos)
+            })
+            .collect::<Vec<_>>();
+
+        let mut writes = Vec::new();
+        if let Some(binding) = shader.parameter_block() {
+            writes.push(
+                vk::WriteDescriptorSetBuilder::new()
+                    .dst_set(descriptor_set)
+                    .dst_binding(binding.binding)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                    .buffer_info(&buffer_infos),
+            );
+        }
+        for (binding, image_infos) in &image_infos {
+            writes.push(
+                vk::WriteDescriptorSetBuilder::new()
+                    .dst_set(descriptor_set)
+                    .dst_binding(binding.binding)
+                    .descriptor_type(descriptor_type(binding.descriptor_type))
+                    .image_info(image_infos),
+            );
+        }
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+        Ok(())
+    }
+
+    fn pipeline(
+        &mut self,
+        context: &Context,
+        key: PipelineKey,
+    ) -> Result<vk::Pipeline, vk::Result> {
+        if let Some(&pipeline) = self.pipelines.get(&key) {
+            return Ok(pipeline);
+        }
+
+        let device = &context.device;
+        let shader = match key.shader {
+            Some(guid) => &self.shaders[&guid],
+            None => &self.default_shader,
+        };
+
+        let stages = shader
+            .stages
+            .iter()
+            .map(|(stage, module, entry_point)| {
+                vk::PipelineShaderStageCreateInfoBuilder::new()
+                    .module(*module)
+                    .name(entry_point)
+                    .stage(*stage)
+            })
+            .collect::<Vec<_>>();
+
+        // Only the vertex attributes the shader reads are fetched.
+        let vertex_bindings = [vk::VertexInputBindingDescriptionBuilder::new()
+            .binding(0)
+            .stride(asset_system::VERTEX_SIZE as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)];
+        let vertex_attributes = shader
+            .vertex_locations
+            .iter()
+            .map(|&location| {
+                let (format, offset) = VERTEX_ATTRIBUTES[location as usize];
+                vk::VertexInputAttributeDescriptionBuilder::new()
+                    .location(location)
+                    .binding(0)
+                    .format(format)
+                    .offset(offset)
+            })
+            .collect::<Vec<_>>();
+        let vertex_bindings = match vertex_attributes.is_empty() {
+            true => &[][..],
+            false => &vertex_bindings[..],
+        };
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfoBuilder::new()
+            .vertex_binding_descriptions(vertex_bindings)
+            .vertex_attribute_descriptions(&vertex_attributes);
+        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfoBuilder::new()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+
+        // Viewports and scissors are set when drawing, so pipelines outlive swapchain resizes.
+        let viewport_state = vk::PipelineViewportStateCreateInfoBuilder::new()
+            .viewport_count(1)
+            .scissor_count(1);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfoBuilder::new().dynamic_states(&dynamic_states);
+
+        let render_state = key.render_state;
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfoBuilder::new()
+            .cull_mode(cull_mode(render_state.cull_mode))
+            .polygon_mode(vk::PolygonMode::FILL)
+            .line_width(1.0)
+            .front_face(vk::FrontFace::COUNTER_CLOCKWISE);
+        let multisample_state = vk::PipelineMultisampleStateCreateInfoBuilder::new()
+            .rasterization_samples(vk::SampleCountFlagBits::_1);
+        let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfoBuilder::new()
+            .depth_test_enable(render_state.depth_test)
+            .depth_write_enable(render_state.depth_write)
+            .depth_compare_op(compare_op(render_state.depth_compare));
+
+        let color_blend_attachments = [color_blend_attachment(render_state.blend_mode)];
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfoBuilder::new()
+            .attachments(&color_blend_attachments);
+
+        let color_attachment_formats = [self.color_format];
+        let mut pipeline_rendering_info = vk::PipelineRenderingCreateInfoKHRBuilder::new()
+            .color_attachment_formats(&color_attachment_formats)
+            .depth_attachment_format(self.depth_format);
+
+        let pipeline_infos = [vk::GraphicsPipelineCreateInfoBuilder::new()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input_state)
+            .input_assembly_state(&input_assembly_state)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterization_state)
+            .multisample_state(&multisample_state)
+            .depth_stencil_state(&depth_stencil_state)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state)
+            .layout(shader.pipeline_layout)
+            .extend_from(&mut pipeline_rendering_info)];
+        let pipeline = unsafe {
+            device
+                .create_graphics_pipelines(vk::PipelineCache::null(), &pipeline_infos, None)
+                .result()?[0]
+        };
+        self.pipelines.insert(key, pipeline);
+
+        Ok(pipeline)
+    }
+
+    fn create_parameter_buffer(
+        context: &Context,
+        data: &[u8],
+    ) -> Result<(vk::Buffer, vk::DeviceMemory), vk::Result> {
+        let device = &context.device;
+
+        let buffer_info = vk::BufferCreateInfoBuilder::new()
+            .size(data.len() as vk::DeviceSize)
+            .usage(vk::BufferUsageFlags::UNIFORM_BUFFER)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        let buffer = unsafe { device.create_buffer(&buffer_info, None).result()? };
+
+        let result = (|| unsafe {
+            let requirements = device.get_buffer_memory_requirements(buffer);
+            let memory_type_index = context
+                .find_memory_type(
+                    requirements.memory_type_bits,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                )
+                .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)?;
+            let allocate_info = vk::MemoryAllocateInfoBuilder::new()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type_index);
+            let memory = device.allocate_memory(&allocate_info, None).result()?;
+
+            let mapped = device
+                .bind_buffer_memory(buffer, memory, 0)
+                .result()
+                .and_then(|()| {
+                    device
+                        .map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+                        .result()
+                });
+            match mapped {
+                Ok(mapped) => {
+                    std::ptr::copy_nonoverlapping(data.as_ptr(), mapped.cast::<u8>(), data.len());
+                    device.unmap_memory(memory);
+                    Ok(memory)
+                }
+                Err(e) => {
+                    device.free_memory(memory, None);
+                    Err(e)
+                }
+            }
+        })();
+
+        match result {
+            Ok(memory) => Ok((buffer, memory)),
+            Err(e) => {
+                unsafe { device.destroy_buffer(buffer, None) };
+                Err(e)
+            }
+        }
+    }
+}
+
+// The texture slot bound to an element of an image binding.
+fn slot_name(binding: &DescriptorBinding, index: u32) -> String {
+    match binding.count {
+        1 => binding.name.clone(),
+        _ => format!("{}[{index}]", binding.name),
+    }
+}
+
+fn has_image(descriptor_type: DescriptorType) -> bool {
+    matches!(
+        descriptor_type,
+        DescriptorType::SampledImage | DescriptorType::CombinedImageSampler
+    )
+}
+
+// The parameters and texture slots of the built-in PBR shader, from the PBR fields of a
+// material. The alpha cutoff only applies to masked materials.
+fn pbr_inputs(material: &MaterialAsset) -> MaterialAsset {
+    let alpha_cutoff = match material.alpha_mode {
+        AlphaMode::Mask => material.alpha_cutoff,
+        AlphaMode::Opaque | AlphaMode::Blend => 0.0,
+    };
+    let parameters = [
+        (
+            "base_color_factor",
+            MaterialParameter::Color(material.base_color_factor),
+        ),
+        (
+            "emissive_factor",
+            MaterialParameter::Vector3(material.emissive_factor),
+        ),
+        (
+            "metallic_factor",
+            MaterialParameter::Scalar(material.metallic_factor),
+        ),
+        (
+            "roughness_factor",
+            MaterialParameter::Scalar(material.roughness_factor),
+        ),
+        (
+            "normal_scale",
+            MaterialParameter::Scalar(material.normal_scale),
+        ),
+        (
+            "occlusion_strength",
+            MaterialParameter::Scalar(material.occlusion_strength),
+        ),
+        ("alpha_cutoff", MaterialParameter::Scalar(alpha_cutoff)),
+    ];
+    let texture_slots = [
+        ("base_color_texture", material.base_color_texture),
+        (
+            "metallic_roughness_texture",
+            material.metallic_roughness_texture,
+        ),
+        ("normal_texture", material.normal_texture),
+        ("occlusion_texture", material.occlusion_texture),
+        ("emissive_texture", material.emissive_texture),
+    ];
+
+    let mut pbr_material = material.clone();
+    pbr_material.parameters.extend(
+        parameters
+            .into_iter()
+            .map(|(name, parameter)| (name.to_owned(), parameter)),
+    );
+    pbr_material.texture_slots.extend(
+        texture_slots
+            .into_iter()
+            .filter_map(|(slot, texture)| Some((slot.to_owned(), texture?))),
+    );
+    pbr_material
+}
+
+// Compute, task and mesh stages don't take part in the pipelines of materials.
+fn graphics_stage(stage: ShaderStage) -> Option<vk::ShaderStageFlagBits> {
+    match stage {
+        ShaderStage::Vertex => Some(vk::ShaderStageFlagBits::VERTEX),
+        ShaderStage::TessellationControl => Some(vk::ShaderStageFlagBits::TESSELLATION_CONTROL),
+        ShaderStage::TessellationEvaluation => {
+            Some(vk::ShaderStageFlagBits::TESSELLATION_EVALUATION)
+        }
+        ShaderStage::Geometry => Some(vk::ShaderStageFlagBits::GEOMETRY),
+        ShaderStage::Fragment => Some(vk::ShaderStageFlagBits::FRAGMENT),
+        ShaderStage::Compute | ShaderStage::Task | ShaderStage::Mesh => None,
+    }
+}
+
+fn stage_flags(stages: &[ShaderStage]) -> vk::ShaderStageFlags {
+    stages
+        .iter()
+        .map(|&stage| match stage {
+            ShaderStage::Vertex => vk::ShaderStageFlags::VERTEX,
+            ShaderStage::TessellationControl => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+            ShaderStage::TessellationEvaluation => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+            ShaderStage::Geometry => vk::ShaderStageFlags::GEOMETRY,
+            ShaderStage::Fragment => vk::ShaderStageFlags::FRAGMENT,
+            ShaderStage::Compute => vk::ShaderStageFlags::COMPUTE,
+            ShaderStage::Task => vk::ShaderStageFlags::TASK_NV,
+            ShaderStage::Mesh => vk::ShaderStageFlags::MESH_NV,
+        })
+        .fold(vk::ShaderStageFlags::empty(), |flags, stage| flags | stage)
+}
+
+fn descriptor_type(descriptor_type: DescriptorType) -> vk::DescriptorType {
+    match descriptor_type {
+        DescriptorType::Sampler => vk::DescriptorType::SAMPLER,
+        DescriptorType::CombinedImageSampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+        DescriptorType::SampledImage => vk::DescriptorType::SAMPLED_IMAGE,
+        DescriptorType::StorageImage => vk::DescriptorType::STORAGE_IMAGE,
+        DescriptorType::UniformTexelBuffer => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+        DescriptorType::StorageTexelBuffer => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+        DescriptorType::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
+        DescriptorType::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
+        DescriptorType::InputAttachment => vk::DescriptorType::INPUT_ATTACHMENT,
+        DescriptorType::AccelerationStructure => vk::DescriptorType::ACCELERATION_STRUCTURE_KHR,
+    }
+}
+
+fn cull_mode(cull_mode: CullMode) -> vk::CullModeFlags {
+    match cull_mode {
+        CullMode::None => vk::CullModeFlags::NONE,
+        CullMode::Front => vk::CullModeFlags::FRONT,
+        CullMode::Back => vk::CullModeFlags::BACK,
+    }
+}
+
+fn compare_op(compare_op: CompareOp) -> vk::CompareOp {
+    match compare_op {
+        CompareOp::Never => vk::CompareOp::NEVER,
+        CompareOp::Less => vk::CompareOp::LESS,
+        CompareOp::Equal => vk::CompareOp::EQUAL,
+        CompareOp::LessOrEqual => vk::CompareOp::LESS_OR_EQUAL,
+        CompareOp::Greater => vk::CompareOp::GREATER,
+        CompareOp::NotEqual => vk::CompareOp::NOT_EQUAL,
+        CompareOp::GreaterOrEqual => vk::CompareOp::GREATER_OR_EQUAL,
+        CompareOp::Always => vk::CompareOp::ALWAYS,
+    }
+}
+
+fn color_blend_attachment(
+    blend_mode: BlendMode,
+) -> vk::PipelineColorBlendAttachmentStateBuilder<'static> {
+    let attachment = vk::PipelineColorBlendAttachmentStateBuilder::new()
+        .color_write_mask(vk::ColorComponentFlags::all());
+    let (source, destination) = match blend_mode {
+        BlendMode::Opaque => return attachment,
+        BlendMode::Alpha => (
+            vk::BlendFactor::SRC_ALPHA,
+            vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+        ),
+        BlendMode::Premultiplied => (vk::BlendFactor::ONE, vk::BlendFactor::ONE_MINUS_SRC_ALPHA),
+        BlendMode::Additive => (vk::BlendFactor::ONE, vk::BlendFactor::ONE),
+        BlendMode::Multiply => (vk::BlendFactor::DST_COLOR, vk::BlendFactor::ZERO),
+    };
+
+    attachment
+        .blend_enable(true)
+        .src_color_blend_factor(source)
+        .dst_color_blend_factor(destination)
+        .color_blend_op(vk::BlendOp::ADD)
+        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+        .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .alpha_blend_op(vk::BlendOp::ADD)
+}
@@ -0,0 +1,362 @@
+use super::context::Context;
+use asset_system::{Texture, TextureDimension, TextureFormat};
+use erupt::vk;
+
+// Discriminants of `TextureFormat` are the values of `VkFormat`.
+pub fn vk_format(texture_format: TextureFormat) -> vk::Format {
+    vk::Format(texture_format as i32)
+}
+
+pub fn texture_format(format: vk::Format) -> Option<TextureFormat> {
+    TextureFormat::from_vk_format(format.0)
+}
+
+pub fn image_view_type(dimension: TextureDimension) -> vk::ImageViewType {
+    match dimension {
+        TextureDimension::D2 => vk::ImageViewType::_2D,
+        TextureDimension::D2Array => vk::ImageViewType::_2D_ARRAY,
+        TextureDimension::Cube => vk::ImageViewType::CUBE,
+        TextureDimension::CubeArray => vk::ImageViewType::CUBE_ARRAY,
+        TextureDimension::D3 => vk::ImageViewType::_3D,
+    }
+}
+
+// A sampled image with the texel data of a texture asset.
+#[derive(Debug)]
+pub struct GpuTexture {
+    pub image: vk::Image,
+    pub image_view: vk::ImageView,
+    pub format: vk::Format,
+    memory: vk::DeviceMemory,
+}
+
+impl GpuTexture {
+    // Block-compressed texels are copied as is, textures in formats the device can't sample are decoded to RGBA8.
+    pub fn upload(context: &Context, texture: &Texture) -> Result<Self, vk::Result> {
+        let decoded;
+        let texture =
+            match context.supports_sampled_format(vk_format(texture.metadata.texture_format)) {
+                true => texture,
+                false => match texture.to_rgba8() {
+                    Ok(texture) => {
+                        decoded = texture;
+                        &decoded
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to decode a texture: {e:?}");
+                        return Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED);
+                    }
+                },
+            };
+        let metadata = &texture.metadata;
+        if texture.data.len() < metadata.data_size() {
+            return Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED);
+        }
+
+        if metadata.dimension == TextureDimension::CubeArray && !context.supports_cube_arrays() {
+            return Err(vk::Result::ERROR_FEATURE_NOT_PRESENT);
+        }
+
+        let device = &context.device;
+        let format = vk_format(metadata.texture_format);
+
+        let (staging_buffer, staging_memory) = Self::create_staging_buffer(context, &texture.data)?;
+        let result = Self::create_image(context, texture, format, staging_buffer);
+        unsafe {
+            device.destroy_buffer(staging_buffer, None);
+            device.free_memory(staging_memory, None);
+        }
+        let (image, memory, image_view) = result?;
+
+        Ok(Self {
+            image,
+            image_view,
+            format,
+            memory,
+        })
+    }
+
+    pub fn destroy(&self, device: &erupt::DeviceLoader) {
+        unsafe {
+            device.destroy_image_view(self.image_view, None);
+            device.destroy_image(self.image, None);
+            device.free_memory(self.memory, None);
+        }
+    }
+
+    // Creates the image with its memory and view and copies the texels from the staging buffer
+    // into it. Nothing is left behind on failure.
+    fn create_image(
+        context: &Context,
+        texture: &Texture,
+        format: vk::Format,
+        staging_buffer: vk::Buffer,
+    ) -> Result<(vk::Image, vk::DeviceMemory, vk::ImageView), vk::Result> {
+        let device = &context.device;
+        let metadata = &texture.metadata;
+
+        let (image_type, flags) = match metadata.dimension {
+            TextureDimension::D3 => (vk::ImageType::_3D, vk::ImageCreateFlags::empty()),
+            TextureDimension::Cube | TextureDimension::CubeArray => {
+                (vk::ImageType::_2D, vk::ImageCreateFlags::CUBE_COMPATIBLE)
+            }
+            TextureDimension::D2 | TextureDimension::D2Array => {
+                (vk::ImageType::_2D, vk::ImageCreateFlags::empty())
+            }
+        };
+        let image_info = vk::ImageCreateInfoBuilder::new()
+            .flags(flags)
+            .image_type(image_type)
+            .format(format)
+            .extent(vk::Extent3D {
+                width: metadata.width,
+                height: metadata.height,
+                depth: metadata.depth,
+            })
+            .mip_levels(metadata.mip_levels)
+            .array_layers(metadata.array_layers)
+            .samples(vk::SampleCountFlagBits::_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        let image = unsafe { device.create_image(&image_info, None).result()? };
+        let memory = match Self::allocate_memory(
+            context,
+            unsafe { device.get_image_memory_requirements(image) },
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        ) {
+            Ok(memory) => memory,
+            Err(e) => {
+                unsafe { device.destroy_image(image, None) };
+                return Err(e);
+            }
+        };
+
+        let result = (|| {
+            unsafe { device.bind_image_memory(image, memory, 0).result()? };
+
+            let subresource_range = vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: metadata.mip_levels,
+                base_array_layer: 0,
+                layer_count: metadata.array_layers,
+            };
+            let regions = (0..metadata.mip_levels)
+                .map(|mip_level| {
+                    let (width, height) = metadata.mip_extent(mip_level);
+
+                    vk::BufferImageCopyBuilder::new()
+                        .buffer_offset(metadata.mip_level_range(mip_level).start as vk::DeviceSize)
+                        .image_subresource(vk::ImageSubresourceLayers {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            mip_level,
+                            base_array_layer: 0,
+                            layer_count: metadata.array_layers,
+                        })
+                        .image_extent(vk::Extent3D {
+                            width,
+                            height,
+                            depth: metadata.mip_depth(mip_level),
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            Self::submit_copy(context, |command_buffer| unsafe {
+                device.cmd_pipeline_barrier2(
+                    command_buffer,
+                    &vk::DependencyInfoKHRBuilder::new().image_memory_barriers(&[
+                        vk::ImageMemoryBarrier2 {
+                            src_stage_mask: vk::PipelineStageFlags2::TOP_OF_PIPE,
+                            dst_stage_mask: vk::PipelineStageFlags2::COPY,
+                            dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                            new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                            image,
+                            subresource_range,
+                            ..Default::default()
+                        }
+                        .into_builder(),
+                    ]),
+                );
+                device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    staging_buffer,
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &regions,
+                );
+                device.cmd_pipeline_barrier2(
+                    command_buffer,
+                    &vk::DependencyInfoKHRBuilder::new().image_memory_barriers(&[
+                        vk::ImageMemoryBarrier2 {
+                            src_stage_mask: vk::PipelineStageFlags2::COPY,
+                            dst_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                            dst_access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ,
+                            old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                            image,
+                            subresource_range,
+                            ..Default::default()
+                        }
+                        .into_builder(),
+                    ]),
+                );
+            })?;
+
+            let image_view_info = vk::ImageViewCreateInfoBuilder::new()
+                .image(image)
+                .view_type(image_view_type(metadata.dimension))
+                .format(format)
+                .subresource_range(subresource_range);
+            unsafe { device.create_image_view(&image_view_info, None).result() }
+        })();
+
+        match result {
+            Ok(image_view) => Ok((image, memory, image_view)),
+            Err(e) => {
+                unsafe {
+                    device.destroy_image(image, None);
+                    device.free_memory(memory, None);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn create_staging_buffer(
+        context: &Context,
+        data: &[u8],
+    ) -> Result<(vk::Buffer, vk::DeviceMemory), vk::Result> {
+        let device = &context.device;
+
+        let buffer_info = vk::BufferCreateInfoBuilder::new()
+            .size(data.len() as vk::DeviceSize)
+            .usage(vk::BufferUsageFlags::TRANSFER_SRC)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        let buffer = unsafe { device.create_buffer(&buffer_info, None).result()? };
+
+        let result = (|| unsafe {
+            let memory = Self::allocate_memory(
+                context,
+                device.get_buffer_memory_requirements(buffer),
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+
+            let mapped = device
+                .bind_buffer_memory(buffer, memory, 0)
+                .result()
+                .and_then(|()| {
+                    device
+                        .map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+                        .result()
+                });
+            match mapped {
+                Ok(mapped) => {
+                    std::ptr::copy_nonoverlapping(data.as_ptr(), mapped.cast::<u8>(), data.len());
+                    device.unmap_memory(memory);
+                    Ok(memory)
+                }
+                Err(e) => {
+                    device.free_memory(memory, None);
+                    Err(e)
+                }
+            }
+        })();
+
+        match result {
+            Ok(memory) => Ok((buffer, memory)),
+            Err(e) => {
+                unsafe { device.destroy_buffer(buffer, None) };
+                Err(e)
+            }
+        }
+    }
+
+    fn allocate_memory(
+        context: &Context,
+        requirements: vk::MemoryRequirements,
+        properties: vk::MemoryPropertyFlags,
+    ) -> Result<vk::DeviceMemory, vk::Result> {
+        let memory_type_index = context
+            .find_memory_type(requirements.memory_type_bits, properties)
+            .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)?;
+        let allocate_info = vk::MemoryAllocateInfoBuilder::new()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type_index);
+
+        unsafe {
+            context
+                .device
+                .allocate_memory(&allocate_info, None)
+                .result()
+        }
+    }
+
+    // Records commands into a transient command buffer and waits until the graphics queue executes them.
+    fn submit_copy(
+        context: &Context,
+        record: impl FnOnce(vk::CommandBuffer),
+    ) -> Result<(), vk::Result> {
+        let device = &context.device;
+
+        let command_pool_info = vk::CommandPoolCreateInfoBuilder::new()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(context.queue_family_index);
+        let command_pool = unsafe {
+            device
+                .create_command_pool(&command_pool_info, None)
+                .result()?
+        };
+        let fence = match unsafe {
+            device
+                .create_fence(&vk::FenceCreateInfoBuilder::new(), None)
+                .result()
+        } {
+            Ok(fence) => fence,
+            Err(e) => {
+                unsafe { device.destroy_command_pool(command_pool, None) };
+                return Err(e);
+            }
+        };
+
+        let result = (|| unsafe {
+            let command_buffer_info = vk::CommandBufferAllocateInfoBuilder::new()
+                .command_pool(command_pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1);
+            let command_buffer = device
+                .allocate_command_buffers(&command_buffer_info)
+                .result()?[0];
+
+            let begin_info = vk::CommandBufferBeginInfoBuilder::new()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            device
+                .begin_command_buffer(command_buffer, &begin_info)
+                .result()?;
+            record(command_buffer);
+            device.end_command_buffer(command_buffer).result()?;
+
+            let command_buffer_infos =
+                [vk::CommandBufferSubmitInfoBuilder::new().command_buffer(command_buffer)];
+            let submit_info =
+                vk::SubmitInfo2Builder::new().command_buffer_infos(&command_buffer_infos);
+            device
+                .queue_submit2(context.graphics_queue, &[submit_info], fence)
+                .result()?;
+            device.wait_for_fences(&[fence], true, u64::MAX).result()
+        })();
+
+        unsafe {
+            device.destroy_fence(fence, None);
+            device.destroy_command_pool(command_pool, None);
+        }
+
+        result
+    }
+}